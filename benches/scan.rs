@@ -0,0 +1,117 @@
+//! Header probing against full decoding, on synthetic photos
+//!
+//! Run with `cargo bench --bench scan`.
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use datalint_core::probe::probe;
+use datalint_core::scanner::{process_images, ScanOptions};
+use image::{DynamicImage, ImageFormat, RgbImage};
+use std::hint::black_box;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+const IMAGES: usize = 32;
+
+/// A noisy gradient, so encoders cannot shrink it to nothing
+fn photo(width: u32, height: u32, seed: u32) -> DynamicImage {
+    let mut state = seed.wrapping_mul(2_654_435_761) | 1;
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let noise = (state & 0x1F) as u8;
+        image::Rgb([
+            (x * 255 / width) as u8 ^ noise,
+            (y * 255 / height) as u8 ^ noise,
+            ((x + y) % 256) as u8,
+        ])
+    }))
+}
+
+fn encode(img: &DynamicImage, format: ImageFormat) -> Vec<u8> {
+    let mut data = Vec::new();
+    img.write_to(&mut Cursor::new(&mut data), format).unwrap();
+    data
+}
+
+/// Dataset directory of 1280x960 images, removed on drop
+struct Dataset(PathBuf);
+
+impl Dataset {
+    fn new(format: ImageFormat, extension: &str) -> Self {
+        let root = std::env::temp_dir().join(format!(
+            "datalint-bench-{}-{}",
+            extension,
+            std::process::id()
+        ));
+        let images = root.join("images").join("train");
+        std::fs::create_dir_all(&images).unwrap();
+        for i in 0..IMAGES {
+            let data = encode(&photo(1280, 960, i as u32), format);
+            std::fs::write(images.join(format!("{:04}.{}", i, extension)), data).unwrap();
+        }
+        Self(root)
+    }
+
+    fn paths(&self) -> Vec<PathBuf> {
+        let images = self.0.join("images").join("train");
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(images)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        paths.sort();
+        paths
+    }
+}
+
+impl Drop for Dataset {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn header(c: &mut Criterion) {
+    let mut group = c.benchmark_group("header");
+    for (format, name) in [
+        (ImageFormat::Jpeg, "jpeg"),
+        (ImageFormat::Png, "png"),
+        (ImageFormat::WebP, "webp"),
+    ] {
+        let data = encode(&photo(1280, 960, 1), format);
+        group.bench_with_input(BenchmarkId::new("probe", name), &data, |b, data| {
+            b.iter(|| probe(black_box(data)))
+        });
+        group.bench_with_input(BenchmarkId::new("decode", name), &data, |b, data| {
+            b.iter(|| image::load_from_memory(black_box(data)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn scan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scan");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(IMAGES as u64));
+
+    for (format, name) in [(ImageFormat::Jpeg, "jpg"), (ImageFormat::Png, "png")] {
+        let dataset = Dataset::new(format, name);
+        let paths = dataset.paths();
+        for (mode, options) in [
+            ("probe", ScanOptions::default()),
+            (
+                "full_decode",
+                ScanOptions {
+                    full_decode: true,
+                    ..ScanOptions::default()
+                },
+            ),
+        ] {
+            group.bench_with_input(BenchmarkId::new(mode, name), &paths, |b, paths| {
+                b.iter(|| process_images(paths, &dataset.0, &options))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, header, scan);
+criterion_main!(benches);
@@ -0,0 +1,2 @@
+-- Optional parent category for labels (COCO supercategory)
+ALTER TABLE labels ADD COLUMN IF NOT EXISTS supercategory TEXT;
@@ -0,0 +1,14 @@
+-- Run-length encoded masks (COCO compressed counts), e.g. iscrowd regions
+CREATE SEQUENCE IF NOT EXISTS masks_id_seq START 1;
+
+CREATE TABLE IF NOT EXISTS masks (
+    id INTEGER PRIMARY KEY DEFAULT nextval('masks_id_seq'),
+    bbox_id INTEGER NOT NULL REFERENCES bboxes(id),
+    height INTEGER NOT NULL,
+    width INTEGER NOT NULL,
+    counts TEXT NOT NULL,
+    area INTEGER NOT NULL,
+    is_crowd INTEGER NOT NULL DEFAULT 0 CHECK(is_crowd IN (0, 1))
+);
+
+CREATE INDEX IF NOT EXISTS idx_masks_bbox ON masks(bbox_id);
@@ -0,0 +1,2 @@
+-- Per-annotation attributes as JSON (VOC difficult/truncated/occluded/pose, ...)
+ALTER TABLE bboxes ADD COLUMN IF NOT EXISTS attributes TEXT;
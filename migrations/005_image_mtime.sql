@@ -0,0 +1,3 @@
+-- Modification time (nanoseconds since the Unix epoch) used to skip unchanged
+-- files when refreshing a cache
+ALTER TABLE images ADD COLUMN IF NOT EXISTS file_mtime BIGINT;
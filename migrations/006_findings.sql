@@ -0,0 +1,16 @@
+-- Lint results, one row per finding. Findings are derived data and are
+-- replaced per rule on every run, so they do not reference other tables.
+CREATE SEQUENCE IF NOT EXISTS findings_id_seq START 1;
+
+CREATE TABLE IF NOT EXISTS findings (
+    id INTEGER PRIMARY KEY DEFAULT nextval('findings_id_seq'),
+    rule_id TEXT NOT NULL,
+    severity TEXT NOT NULL CHECK(severity IN ('info', 'warning', 'error')),
+    image_id INTEGER,
+    bbox_id INTEGER,
+    message TEXT NOT NULL,
+    extra TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_findings_rule ON findings(rule_id);
+CREATE INDEX IF NOT EXISTS idx_findings_image ON findings(image_id);
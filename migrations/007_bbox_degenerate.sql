@@ -0,0 +1,73 @@
+-- Keep degenerate boxes (x1 >= x2 or y1 >= y2) so lint rules can report them
+-- instead of losing them to a CHECK failure at insert time. DuckDB can neither
+-- drop a CHECK constraint nor a table that is still referenced, so bboxes and
+-- the tables pointing at it are rebuilt.
+CREATE TEMP TABLE bboxes_old AS SELECT * FROM bboxes;
+CREATE TEMP TABLE segmentations_old AS SELECT * FROM segmentations;
+CREATE TEMP TABLE keypoints_old AS SELECT * FROM keypoints;
+CREATE TEMP TABLE masks_old AS SELECT * FROM masks;
+
+DROP TABLE masks;
+DROP TABLE keypoints;
+DROP TABLE segmentations;
+DROP TABLE bboxes;
+
+CREATE TABLE bboxes (
+    id INTEGER PRIMARY KEY DEFAULT nextval('bboxes_id_seq'),
+    image_id INTEGER NOT NULL REFERENCES images(id),
+    label_id INTEGER NOT NULL REFERENCES labels(id),
+    -- Store corners
+    x1 REAL NOT NULL,
+    y1 REAL NOT NULL,
+    x2 REAL NOT NULL,
+    y2 REAL NOT NULL,
+    -- Computed values (calculated during insertion)
+    cx REAL NOT NULL,  -- center x
+    cy REAL NOT NULL,  -- center y
+    w REAL NOT NULL,   -- width
+    h REAL NOT NULL,   -- height
+    area REAL NOT NULL,
+    angle REAL DEFAULT 0,
+    confidence REAL,
+    attributes TEXT
+);
+
+CREATE TABLE segmentations (
+    id INTEGER PRIMARY KEY DEFAULT nextval('segmentations_id_seq'),
+    bbox_id INTEGER NOT NULL REFERENCES bboxes(id),
+    vertices TEXT NOT NULL,
+    vertex_count INTEGER NOT NULL
+);
+
+CREATE TABLE keypoints (
+    id INTEGER PRIMARY KEY DEFAULT nextval('keypoints_id_seq'),
+    bbox_id INTEGER NOT NULL REFERENCES bboxes(id),
+    points TEXT NOT NULL,
+    point_count INTEGER NOT NULL,
+    has_visibility INTEGER NOT NULL CHECK(has_visibility IN (0, 1))
+);
+
+CREATE TABLE masks (
+    id INTEGER PRIMARY KEY DEFAULT nextval('masks_id_seq'),
+    bbox_id INTEGER NOT NULL REFERENCES bboxes(id),
+    height INTEGER NOT NULL,
+    width INTEGER NOT NULL,
+    counts TEXT NOT NULL,
+    area INTEGER NOT NULL,
+    is_crowd INTEGER NOT NULL DEFAULT 0 CHECK(is_crowd IN (0, 1))
+);
+
+INSERT INTO bboxes SELECT * FROM bboxes_old;
+INSERT INTO segmentations SELECT * FROM segmentations_old;
+INSERT INTO keypoints SELECT * FROM keypoints_old;
+INSERT INTO masks SELECT * FROM masks_old;
+
+DROP TABLE bboxes_old;
+DROP TABLE segmentations_old;
+DROP TABLE keypoints_old;
+DROP TABLE masks_old;
+
+CREATE INDEX idx_bboxes_image ON bboxes(image_id);
+CREATE INDEX idx_bboxes_label ON bboxes(label_id);
+CREATE INDEX idx_bboxes_spatial ON bboxes(x1, y1, x2, y2);
+CREATE INDEX idx_masks_bbox ON masks(bbox_id);
@@ -0,0 +1,5 @@
+-- Perceptual hashes (average, difference, DCT) as 64-bit patterns, NULL when
+-- not computed
+ALTER TABLE images ADD COLUMN IF NOT EXISTS ahash BIGINT;
+ALTER TABLE images ADD COLUMN IF NOT EXISTS dhash BIGINT;
+ALTER TABLE images ADD COLUMN IF NOT EXISTS phash BIGINT;
@@ -0,0 +1,16 @@
+-- Annotation files seen by the importers, including those matching no image,
+-- so orphaned, empty and missing label files can be reported. Rebuilt with
+-- the annotations, so it does not reference images.
+CREATE SEQUENCE IF NOT EXISTS label_files_id_seq START 1;
+
+CREATE TABLE IF NOT EXISTS label_files (
+    id INTEGER PRIMARY KEY DEFAULT nextval('label_files_id_seq'),
+    path TEXT NOT NULL UNIQUE,  -- relative to the dataset root
+    image_id INTEGER,           -- NULL when no image matches
+    annotation_count INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_label_files_image ON label_files(image_id);
+
+-- Findings about files outside the images table point at them by path
+ALTER TABLE findings ADD COLUMN IF NOT EXISTS path TEXT;
@@ -0,0 +1,12 @@
+-- Files the scanner could not list, read or insert, one row per failure.
+-- Replaced on every scan, so it does not reference images.
+CREATE SEQUENCE IF NOT EXISTS scan_errors_id_seq START 1;
+
+CREATE TABLE IF NOT EXISTS scan_errors (
+    id INTEGER PRIMARY KEY DEFAULT nextval('scan_errors_id_seq'),
+    path TEXT NOT NULL,
+    stage TEXT NOT NULL CHECK(stage IN ('walk', 'read', 'insert')),
+    kind TEXT NOT NULL,
+    message TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
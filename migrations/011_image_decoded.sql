@@ -0,0 +1,3 @@
+-- Whether the pixel data was decoded, as opposed to only the header being
+-- read. Earlier releases always decoded.
+ALTER TABLE images ADD COLUMN IF NOT EXISTS is_decoded BOOLEAN DEFAULT TRUE;
@@ -0,0 +1,20 @@
+-- Problems found while importing annotations (malformed rows, annotations
+-- referencing missing images, ...) are logged with the scan errors at the
+-- `annotation` stage. DuckDB cannot alter a CHECK constraint, so the table is
+-- rebuilt.
+CREATE TEMP TABLE scan_errors_old AS SELECT * FROM scan_errors;
+
+DROP TABLE scan_errors;
+
+CREATE TABLE scan_errors (
+    id INTEGER PRIMARY KEY DEFAULT nextval('scan_errors_id_seq'),
+    path TEXT NOT NULL,
+    stage TEXT NOT NULL CHECK(stage IN ('walk', 'read', 'insert', 'annotation')),
+    kind TEXT NOT NULL,
+    message TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+INSERT INTO scan_errors SELECT * FROM scan_errors_old;
+
+DROP TABLE scan_errors_old;
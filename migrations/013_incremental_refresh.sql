@@ -0,0 +1,95 @@
+-- A cache refresh replaces removed images and the annotations of changed ones
+-- in a single transaction. DuckDB checks foreign keys against the state before
+-- the transaction, so rows deleted together with their parents would still
+-- block the delete. Annotations are rebuilt by the importers, like label files
+-- and findings, so they no longer reference images and labels.
+CREATE TEMP TABLE bboxes_old AS SELECT * FROM bboxes;
+CREATE TEMP TABLE segmentations_old AS SELECT * FROM segmentations;
+CREATE TEMP TABLE keypoints_old AS SELECT * FROM keypoints;
+CREATE TEMP TABLE masks_old AS SELECT * FROM masks;
+CREATE TEMP TABLE classifications_old AS SELECT * FROM classifications;
+
+DROP TABLE masks;
+DROP TABLE keypoints;
+DROP TABLE segmentations;
+DROP TABLE bboxes;
+DROP TABLE classifications;
+
+CREATE TABLE bboxes (
+    id INTEGER PRIMARY KEY DEFAULT nextval('bboxes_id_seq'),
+    image_id INTEGER NOT NULL,
+    label_id INTEGER NOT NULL,
+    -- Store corners
+    x1 REAL NOT NULL,
+    y1 REAL NOT NULL,
+    x2 REAL NOT NULL,
+    y2 REAL NOT NULL,
+    -- Computed values (calculated during insertion)
+    cx REAL NOT NULL,  -- center x
+    cy REAL NOT NULL,  -- center y
+    w REAL NOT NULL,   -- width
+    h REAL NOT NULL,   -- height
+    area REAL NOT NULL,
+    angle REAL DEFAULT 0,
+    confidence REAL,
+    attributes TEXT
+);
+
+CREATE TABLE segmentations (
+    id INTEGER PRIMARY KEY DEFAULT nextval('segmentations_id_seq'),
+    bbox_id INTEGER NOT NULL,
+    vertices TEXT NOT NULL,
+    vertex_count INTEGER NOT NULL
+);
+
+CREATE TABLE keypoints (
+    id INTEGER PRIMARY KEY DEFAULT nextval('keypoints_id_seq'),
+    bbox_id INTEGER NOT NULL,
+    points TEXT NOT NULL,
+    point_count INTEGER NOT NULL,
+    has_visibility INTEGER NOT NULL CHECK(has_visibility IN (0, 1))
+);
+
+CREATE TABLE masks (
+    id INTEGER PRIMARY KEY DEFAULT nextval('masks_id_seq'),
+    bbox_id INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    width INTEGER NOT NULL,
+    counts TEXT NOT NULL,
+    area INTEGER NOT NULL,
+    is_crowd INTEGER NOT NULL DEFAULT 0 CHECK(is_crowd IN (0, 1))
+);
+
+CREATE TABLE classifications (
+    id INTEGER PRIMARY KEY DEFAULT nextval('classifications_id_seq'),
+    image_id INTEGER NOT NULL,
+    label_id INTEGER NOT NULL,
+    confidence REAL
+);
+
+INSERT INTO bboxes SELECT * FROM bboxes_old;
+INSERT INTO segmentations SELECT * FROM segmentations_old;
+INSERT INTO keypoints SELECT * FROM keypoints_old;
+INSERT INTO masks SELECT * FROM masks_old;
+INSERT INTO classifications SELECT * FROM classifications_old;
+
+DROP TABLE bboxes_old;
+DROP TABLE segmentations_old;
+DROP TABLE keypoints_old;
+DROP TABLE masks_old;
+DROP TABLE classifications_old;
+
+CREATE INDEX idx_bboxes_image ON bboxes(image_id);
+CREATE INDEX idx_bboxes_label ON bboxes(label_id);
+CREATE INDEX idx_bboxes_spatial ON bboxes(x1, y1, x2, y2);
+CREATE INDEX idx_masks_bbox ON masks(bbox_id);
+CREATE INDEX idx_classifications_image ON classifications(image_id);
+
+-- Size and modification time of each label file when it was read, so a
+-- refresh only parses the files that changed
+ALTER TABLE label_files ADD COLUMN IF NOT EXISTS file_size BIGINT;
+ALTER TABLE label_files ADD COLUMN IF NOT EXISTS file_mtime BIGINT;
+
+-- Fingerprint of the dataset-level annotation files (data.yaml, split lists,
+-- COCO json); any change to them re-imports every annotation
+ALTER TABLE cache_metadata ADD COLUMN IF NOT EXISTS annotation_sources TEXT;
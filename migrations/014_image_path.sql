@@ -0,0 +1,4 @@
+-- Dataset-relative path of an image file for queries and findings. Images at
+-- the dataset root have an empty relative_path and take no leading slash.
+CREATE OR REPLACE MACRO image_path(relative_path, filename) AS
+    CASE WHEN relative_path = '' THEN filename ELSE relative_path || '/' || filename END;
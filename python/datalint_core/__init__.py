@@ -8,15 +8,55 @@ implemented in Rust with Python bindings via PyO3.
 from __future__ import annotations
 
 from ._datalint_core import (
+    ArrowTable,
+    Bbox,
+    Cache,
+    CacheMetadata,
+    CacheTask,
     DatasetTask,
     DatasetType,
+    DetectedFormat,
+    DuplicateCluster,
+    Finding,
+    Image,
+    Label,
+    LintConfig,
+    NearDuplicateGroup,
+    RuleInfo,
+    ScanError,
+    Severity,
     create_cache,
+    create_cache_async,
+    detect_dataset_type,
+    list_rules,
+    update_cache,
+    update_cache_async,
     __version__,
 )
 
 __all__ = [
+    "ArrowTable",
+    "Bbox",
+    "Cache",
+    "CacheMetadata",
+    "CacheTask",
     "DatasetTask",
     "DatasetType",
+    "DetectedFormat",
+    "DuplicateCluster",
+    "Finding",
+    "Image",
+    "Label",
+    "LintConfig",
+    "NearDuplicateGroup",
+    "RuleInfo",
+    "ScanError",
+    "Severity",
     "create_cache",
+    "create_cache_async",
+    "detect_dataset_type",
+    "list_rules",
+    "update_cache",
+    "update_cache_async",
     "__version__",
 ]
@@ -1,15 +1,421 @@
 """Type stubs for datalint_core."""
 
-from typing import ClassVar
+from typing import Any, Callable, ClassVar, Generator, Generic, TypeVar
 
 __version__: str
 
+# Receives (stage, done, total); stage is "scan", "hash", "annotations" or
+# "lint", and total is None while still unknown. A tqdm-compatible bar works too.
+ProgressCallback = Callable[[str, int, int | None], Any]
+
+_T = TypeVar("_T")
+
 def create_cache(
     cache_path: str,
     dataset_path: str,
     dataset_type: DatasetType,
     dataset_task: DatasetTask,
+    perceptual_hashes: bool = False,
+    full_decode: bool = False,
+    progress: ProgressCallback | Any | None = None,
+) -> str: ...
+def create_cache_async(
+    cache_path: str,
+    dataset_path: str,
+    dataset_type: DatasetType,
+    dataset_task: DatasetTask,
+    perceptual_hashes: bool = False,
+    full_decode: bool = False,
+    progress: ProgressCallback | Any | None = None,
+) -> CacheTask[str]: ...
+def update_cache(
+    cache_path: str,
+    dataset_path: str,
+    dataset_type: DatasetType,
+    dataset_task: DatasetTask,
+    perceptual_hashes: bool = False,
+    full_decode: bool = False,
+    progress: ProgressCallback | Any | None = None,
 ) -> str: ...
+def update_cache_async(
+    cache_path: str,
+    dataset_path: str,
+    dataset_type: DatasetType,
+    dataset_task: DatasetTask,
+    perceptual_hashes: bool = False,
+    full_decode: bool = False,
+    progress: ProgressCallback | Any | None = None,
+) -> CacheTask[str]: ...
+def detect_dataset_type(dataset_path: str) -> list[DetectedFormat]: ...
+def list_rules() -> list[RuleInfo]: ...
+
+class CacheTask(Generic[_T]):
+    """Cache build or lint running on a background thread.
+
+    Poll it with `done()` and `progress`, block on `result()`, or `await` it.
+    `cancel()` stops the task at its next progress update.
+    """
+
+    def done(self) -> bool: ...
+    def result(self, timeout: float | None = None) -> _T: ...
+    def cancel(self) -> bool:
+        """Ask the task to stop at its next progress update.
+
+        Returns False if it already finished. True only means the request was
+        made while it ran: a task past its last progress update still
+        completes, which `result()` tells.
+        """
+    @property
+    def progress(self) -> tuple[str, int, int | None] | None: ...
+    def __await__(self) -> Generator[Any, None, _T]: ...
+
+class Cache:
+    """An open cache database."""
+
+    @staticmethod
+    def open(path: str) -> Cache: ...
+    @staticmethod
+    def create(
+        cache_path: str,
+        dataset_path: str,
+        dataset_type: DatasetType,
+        dataset_task: DatasetTask,
+        perceptual_hashes: bool = False,
+        full_decode: bool = False,
+        progress: ProgressCallback | Any | None = None,
+    ) -> Cache: ...
+    @staticmethod
+    def create_async(
+        cache_path: str,
+        dataset_path: str,
+        dataset_type: DatasetType,
+        dataset_task: DatasetTask,
+        perceptual_hashes: bool = False,
+        full_decode: bool = False,
+        progress: ProgressCallback | Any | None = None,
+    ) -> CacheTask[Cache]: ...
+    @property
+    def path(self) -> str: ...
+    @property
+    def metadata(self) -> CacheMetadata: ...
+    def labels(self) -> list[Label]: ...
+    def images(self, split: str | None = None) -> list[Image]: ...
+    def scan_errors(self, stage: str | None = None) -> list[ScanError]:
+        """Files the latest scan could not list, read or insert, and annotation
+        problems such as annotations referencing missing images."""
+    def bboxes(self, image_id: int) -> list[Bbox]: ...
+    def count_by_split(self) -> dict[str, int]: ...
+    def count_by_label(self) -> dict[str, int]: ...
+    def duplicate_images(
+        self, cross_split_only: bool = False
+    ) -> list[DuplicateCluster]:
+        """Groups of byte-identical images with a suggested keep/remove plan.
+
+        The kept copy prefers test, then val, then train, so evaluation sets
+        stay unchanged when the others are removed.
+        """
+    def near_duplicate_images(
+        self,
+        hash: str = "phash",
+        max_distance: int = 6,
+        cross_split_only: bool = False,
+    ) -> list[NearDuplicateGroup]:
+        """Groups of visually similar images by perceptual hash distance.
+
+        `hash` is one of ahash, dhash or phash. Needs a cache built with
+        `perceptual_hashes=True`.
+        """
+    def lint(
+        self,
+        rules: list[str] | None = None,
+        config: LintConfig | None = None,
+        progress: ProgressCallback | Any | None = None,
+    ) -> dict[str, int]:
+        """Run lint rules and store their findings, returning the count per rule."""
+    def lint_async(
+        self,
+        rules: list[str] | None = None,
+        config: LintConfig | None = None,
+        progress: ProgressCallback | Any | None = None,
+    ) -> CacheTask[dict[str, int]]:
+        """Run lint rules on a background thread."""
+    def findings(
+        self, rule_id: str | None = None, image_id: int | None = None
+    ) -> list[Finding]: ...
+    def count_by_rule(self) -> dict[str, int]: ...
+    def to_arrow(self, table: str) -> ArrowTable:
+        """Export a table as Arrow.
+
+        `table` is one of images, labels, bboxes, segmentations, masks,
+        keypoints, classifications, findings, label_files, scan_errors, or the
+        joined bbox_details and classification_details views.
+        """
+    def __repr__(self) -> str: ...
+
+class ArrowTable:
+    """Query result held as Arrow record batches (Arrow PyCapsule interface)."""
+
+    def __arrow_c_stream__(self, requested_schema: object | None = None) -> object: ...
+    def __arrow_c_schema__(self) -> object: ...
+    @property
+    def column_names(self) -> list[str]: ...
+    @property
+    def num_rows(self) -> int: ...
+    def to_pyarrow(self) -> Any: ...
+    def to_polars(self) -> Any: ...
+    def to_pandas(self) -> Any: ...
+    def __len__(self) -> int: ...
+    def __repr__(self) -> str: ...
+
+class CacheMetadata:
+    """Dataset information recorded when a cache was created."""
+
+    @property
+    def id(self) -> int: ...
+    @property
+    def created_at(self) -> str: ...
+    @property
+    def updated_at(self) -> str: ...
+    @property
+    def datalint_version(self) -> str: ...
+    @property
+    def dataset_path(self) -> str: ...
+    @property
+    def dataset_type(self) -> str: ...
+    @property
+    def dataset_task(self) -> str: ...
+    @property
+    def keypoint_names(self) -> str | None: ...
+    @property
+    def keypoint_skeleton(self) -> str | None: ...
+
+class Label:
+    """A class label."""
+
+    @property
+    def id(self) -> int | None: ...
+    @property
+    def name(self) -> str: ...
+    @property
+    def color(self) -> str | None: ...
+    @property
+    def supercategory(self) -> str | None: ...
+
+class Image:
+    """A cached image file."""
+
+    @property
+    def id(self) -> int | None: ...
+    @property
+    def name(self) -> str: ...
+    @property
+    def filename(self) -> str: ...
+    @property
+    def extension(self) -> str | None: ...
+    @property
+    def relative_path(self) -> str: ...
+    @property
+    def split(self) -> str | None: ...
+    @property
+    def width(self) -> int | None: ...
+    @property
+    def height(self) -> int | None: ...
+    @property
+    def channels(self) -> int | None: ...
+    @property
+    def file_size(self) -> int | None: ...
+    @property
+    def file_mtime(self) -> int | None: ...
+    @property
+    def file_hash(self) -> str: ...
+    @property
+    def is_corrupted(self) -> bool: ...
+    @property
+    def ahash(self) -> int | None:
+        """Average hash as a signed 64-bit integer, if computed."""
+    @property
+    def dhash(self) -> int | None:
+        """Difference hash as a signed 64-bit integer, if computed."""
+    @property
+    def phash(self) -> int | None:
+        """DCT hash as a signed 64-bit integer, if computed."""
+    @property
+    def is_decoded(self) -> bool:
+        """Whether the pixel data was decoded, not only the header read."""
+
+class ScanError:
+    """A file the scanner could not list, read or insert, or an annotation problem."""
+
+    @property
+    def id(self) -> int | None: ...
+    @property
+    def path(self) -> str:
+        """Path relative to the dataset root."""
+    @property
+    def stage(self) -> str:
+        """walk, read, insert or annotation."""
+    @property
+    def kind(self) -> str:
+        """not_found, permission_denied, loop, io, database or invalid; at the
+        annotation stage unreadable, invalid, missing_image, unknown_category,
+        no_dimensions, size_mismatch or no_class."""
+    @property
+    def message(self) -> str: ...
+    @property
+    def created_at(self) -> str: ...
+    def __repr__(self) -> str: ...
+
+class Bbox:
+    """An annotated box in pixel coordinates."""
+
+    @property
+    def id(self) -> int | None: ...
+    @property
+    def image_id(self) -> int: ...
+    @property
+    def label_id(self) -> int: ...
+    @property
+    def x1(self) -> float: ...
+    @property
+    def y1(self) -> float: ...
+    @property
+    def x2(self) -> float: ...
+    @property
+    def y2(self) -> float: ...
+    @property
+    def cx(self) -> float | None: ...
+    @property
+    def cy(self) -> float | None: ...
+    @property
+    def w(self) -> float | None: ...
+    @property
+    def h(self) -> float | None: ...
+    @property
+    def area(self) -> float | None: ...
+    @property
+    def angle(self) -> float | None: ...
+    @property
+    def confidence(self) -> float | None: ...
+    @property
+    def attributes(self) -> str | None:
+        """Format-specific attributes as a JSON string."""
+    def __repr__(self) -> str: ...
+
+class Finding:
+    """A lint rule result."""
+
+    @property
+    def id(self) -> int | None: ...
+    @property
+    def rule_id(self) -> str: ...
+    @property
+    def severity(self) -> Severity: ...
+    @property
+    def image_id(self) -> int | None: ...
+    @property
+    def bbox_id(self) -> int | None: ...
+    @property
+    def message(self) -> str: ...
+    @property
+    def extra(self) -> str | None:
+        """Rule-specific details as a JSON string."""
+    @property
+    def path(self) -> str | None:
+        """File the finding is about, relative to the dataset root."""
+    def __repr__(self) -> str: ...
+
+class LintConfig:
+    """Thresholds used by the configurable lint rules, checked once on creation."""
+
+    @property
+    def min_box_area(self) -> float: ...
+    @property
+    def min_box_relative_area(self) -> float: ...
+    @property
+    def max_aspect_ratio(self) -> float: ...
+    @property
+    def duplicate_iou(self) -> float: ...
+    @property
+    def conflict_iou(self) -> float: ...
+    @property
+    def near_duplicate_distance(self) -> int: ...
+
+    def __init__(
+        self,
+        min_box_area: float = 16.0,
+        min_box_relative_area: float = 1e-5,
+        max_aspect_ratio: float = 20.0,
+        duplicate_iou: float = 0.9,
+        conflict_iou: float = 0.8,
+        near_duplicate_distance: int = 6,
+    ) -> None: ...
+    def __repr__(self) -> str: ...
+
+class DuplicateCluster:
+    """Images with identical file contents."""
+
+    @property
+    def file_hash(self) -> str: ...
+    @property
+    def images(self) -> list[Image]: ...
+    @property
+    def splits(self) -> list[str]: ...
+    @property
+    def keep(self) -> int:
+        """Image id to keep."""
+    @property
+    def remove(self) -> list[int]:
+        """Image ids to remove."""
+    @property
+    def crosses_splits(self) -> bool:
+        """Whether the copies span more than one split (train/val leakage)."""
+    def __repr__(self) -> str: ...
+
+class NearDuplicateGroup:
+    """Visually similar images, linked by perceptual hash distance."""
+
+    @property
+    def images(self) -> list[Image]: ...
+    @property
+    def splits(self) -> list[str]: ...
+    @property
+    def distance(self) -> int:
+        """Largest Hamming distance of a link within the group."""
+    @property
+    def keep(self) -> int:
+        """Image id to keep."""
+    @property
+    def remove(self) -> list[int]:
+        """Image ids to remove."""
+    @property
+    def crosses_splits(self) -> bool:
+        """Whether the members span more than one split."""
+    def __repr__(self) -> str: ...
+
+class RuleInfo:
+    """Description of a lint rule."""
+
+    @property
+    def id(self) -> str: ...
+    @property
+    def severity(self) -> Severity: ...
+    @property
+    def description(self) -> str: ...
+    @property
+    def tasks(self) -> list[str]:
+        """Tasks the rule applies to, empty for every task."""
+    def __repr__(self) -> str: ...
+
+class DetectedFormat:
+    """A candidate dataset format with its confidence and evidence."""
+
+    @property
+    def dataset_type(self) -> DatasetType: ...
+    @property
+    def confidence(self) -> float: ...
+    @property
+    def evidence(self) -> list[str]: ...
+    def __repr__(self) -> str: ...
 
 class DatasetTask:
     """Dataset task types for computer vision."""
@@ -55,3 +461,20 @@ class DatasetType:
     def __repr__(self) -> str: ...
     def __eq__(self, other: object) -> bool: ...
     def __hash__(self) -> int: ...
+
+class Severity:
+    """Severity of a lint finding."""
+
+    INFO: ClassVar[Severity]
+    WARNING: ClassVar[Severity]
+    ERROR: ClassVar[Severity]
+
+    def __new__(cls, value: str) -> Severity: ...
+    @property
+    def value(self) -> str: ...
+    @staticmethod
+    def as_list() -> list[str]: ...
+    def __str__(self) -> str: ...
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
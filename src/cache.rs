@@ -1,22 +1,206 @@
+use crate::db::models::{Image, ScanError};
+use crate::db::queries::{
+    AnnotationQueries, FindingQueries, ImageQueries, MetadataQueries, ScanErrorQueries,
+};
 use crate::db::Database;
 use crate::enums::{DatasetTask, DatasetType};
-use crate::errors::DatalintResult;
-use crate::scanner::{insert_images_batch, scan_images};
+use crate::errors::{DatalintError, DatalintResult};
+use crate::formats::data_yaml::DataYaml;
+use crate::formats::detect::{self, DetectedFormat};
+use crate::formats::{cls, coco, voc, yolo, ImportScope};
+use crate::progress::{ProgressSink, Stage};
+use crate::scanner::{
+    file_mtime, find_image_paths, image_key, process_images, scan_images_into, ScanOptions,
+};
+use duckdb::Connection;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use xxhash_rust::xxh3::xxh3_64;
 
-/// Creates a cache database with full schema for dataset caching
+/// Image files read and written per batch
+const SCAN_BATCH: usize = 1000;
+
+/// Outcome of building a cache
+#[derive(Debug, Clone, Default)]
+pub struct CacheCreate {
+    pub images: usize,
+    pub scan_errors: usize,
+    pub annotation_errors: usize,
+    /// Format detected for a dataset given as `Unknown`, `None` when the
+    /// format was given or could not be detected
+    pub detected: Option<DetectedFormat>,
+}
+
+/// Outcome of an incremental cache refresh
+#[derive(Debug, Clone, Default)]
+pub struct CacheUpdate {
+    pub added: usize,
+    pub changed: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+    pub scan_errors: usize,
+    pub annotation_errors: usize,
+    /// Format detected for a dataset given as `Unknown`, `None` when the
+    /// format was given or could not be detected
+    pub detected: Option<DetectedFormat>,
+}
+
+/// Replace the walk, read and insert failures with those of the latest scan
+fn record_scan_errors(conn: &Connection, errors: &mut [ScanError]) -> DatalintResult<()> {
+    ScanErrorQueries::delete_scan_stages(conn)?;
+    ScanErrorQueries::append(conn, errors)
+}
+
+/// Resolve an unknown layout to the most likely format, along with the
+/// detection it came from. Without one the images are cached alone.
+fn resolve_dataset_type(
+    dataset_path: &Path,
+    dataset_type: &DatasetType,
+) -> (DatasetType, Option<DetectedFormat>) {
+    match dataset_type {
+        DatasetType::Unknown => match detect::best_guess(dataset_path) {
+            Some(guess) => (guess.dataset_type, Some(guess)),
+            None => (DatasetType::Unknown, None),
+        },
+        other => (*other, None),
+    }
+}
+
+/// Fingerprint of the dataset-level annotation files (data.yaml, split lists,
+/// COCO json) and of the format they are read as. Label files are tracked one
+/// by one, a change to these re-imports every annotation.
+fn annotation_sources(
+    dataset_path: &Path,
+    dataset_type: &DatasetType,
+    dataset_task: &DatasetTask,
+) -> String {
+    let files: Vec<PathBuf> = match dataset_type {
+        DatasetType::Yolo => match DataYaml::find(dataset_path) {
+            Some(path) => {
+                let lists = DataYaml::load(&path)
+                    .map(|config| config.split_lists(dataset_path))
+                    .unwrap_or_default();
+                std::iter::once(path)
+                    .chain(lists.into_iter().map(|list| dataset_path.join(list)))
+                    .collect()
+            }
+            None => Vec::new(),
+        },
+        DatasetType::Coco | DatasetType::CocoClassic => coco::find_annotation_files(dataset_path),
+        DatasetType::Voc => voc::find_split_lists(dataset_path),
+        _ => Vec::new(),
+    };
+
+    let mut sources = format!("{}/{}", dataset_type.as_str(), dataset_task.as_str());
+    for file in files {
+        let metadata = fs::metadata(&file).ok();
+        sources.push_str(&format!(
+            "\n{}:{:?}:{:?}",
+            file.display(),
+            metadata.as_ref().map(|meta| meta.len()),
+            metadata.as_ref().and_then(file_mtime)
+        ));
+    }
+    format!("{:016x}", xxh3_64(sources.as_bytes()))
+}
+
+/// Whether two spellings of a path (`./data`, `data/`, a symlink) name the
+/// same directory. Paths that cannot be resolved are compared as given.
+fn same_path(a: &Path, b: &Path) -> bool {
+    let resolve = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    resolve(a) == resolve(b)
+}
+
+/// Parse the annotations of the images in `scope` and log the problems found
+/// with the scan errors. Returns the number of annotation errors logged.
+fn import_annotations(
+    conn: &Connection,
+    dataset_path: &Path,
+    dataset_type: &DatasetType,
+    dataset_task: &DatasetTask,
+    scope: ImportScope,
+    progress: &dyn ProgressSink,
+) -> DatalintResult<usize> {
+    let stats = match dataset_type {
+        DatasetType::Yolo => Some(yolo::import_dataset(
+            conn,
+            dataset_path,
+            dataset_task,
+            scope,
+            progress,
+        )?),
+        DatasetType::Coco | DatasetType::CocoClassic => {
+            Some(coco::import_dataset(conn, dataset_path, scope, progress)?)
+        }
+        DatasetType::Voc => Some(voc::import_dataset(conn, dataset_path, scope, progress)?),
+        DatasetType::Cls => Some(cls::import_dataset(conn, scope, progress)?),
+        _ => None,
+    };
+
+    if let Some(mut stats) = stats {
+        ScanErrorQueries::append(conn, &mut stats.errors)?;
+    }
+
+    // A refresh keeps the errors of the files it did not read again
+    Ok(ScanErrorQueries::get_by_stage(conn, "annotation")?.len())
+}
+
+/// Creates a cache database with full schema for dataset caching.
+///
+/// An existing file at `cache_path` is refused, refresh it with
+/// [`update_cache_db`] instead. When the build fails or is cancelled through
+/// `progress`, the partial cache is removed, so a cache file is always
+/// complete.
 pub fn create_cache_db(
     cache_path: &Path,
     dataset_path: &Path,
     dataset_type: &DatasetType,
     dataset_task: &DatasetTask,
-) -> DatalintResult<usize> {
+    options: &ScanOptions,
+    progress: &dyn ProgressSink,
+) -> DatalintResult<CacheCreate> {
+    if cache_path.exists() {
+        return Err(DatalintError::Core(format!(
+            "Cache already exists, refresh it with update_cache or remove it first: {}",
+            cache_path.display()
+        )));
+    }
+
     // Create parent directories if they don't exist
     if let Some(parent) = cache_path.parent() {
         fs::create_dir_all(parent)?;
     }
 
+    let created = write_cache_db(
+        cache_path,
+        dataset_path,
+        dataset_type,
+        dataset_task,
+        options,
+        progress,
+    );
+    if created.is_err() {
+        let mut wal = cache_path.as_os_str().to_owned();
+        wal.push(".wal");
+        let _ = fs::remove_file(cache_path);
+        let _ = fs::remove_file(wal);
+    }
+    created
+}
+
+/// Build a new cache file, committing image batches as the scan writes them
+fn write_cache_db(
+    cache_path: &Path,
+    dataset_path: &Path,
+    dataset_type: &DatasetType,
+    dataset_task: &DatasetTask,
+    options: &ScanOptions,
+    progress: &dyn ProgressSink,
+) -> DatalintResult<CacheCreate> {
+    let (dataset_type, detected) = resolve_dataset_type(dataset_path, dataset_type);
+
     // Create database and initialize with metadata
     let mut db = Database::open(cache_path)?;
     db.init_cache_metadata(
@@ -26,14 +210,245 @@ pub fn create_cache_db(
         env!("CARGO_PKG_VERSION"),
     )?;
 
-    // Scan and insert images
-    let images = scan_images(dataset_path)?;
-    let image_count = images.len();
+    // Stream images into the cache, committing every batch as it is written
+    let (image_count, mut scan_errors) =
+        scan_images_into(&db, dataset_path, options, SCAN_BATCH, progress)?;
+
+    let tx = db.transaction()?;
+    record_scan_errors(&tx, &mut scan_errors)?;
+    let annotation_errors = import_annotations(
+        &tx,
+        dataset_path,
+        &dataset_type,
+        dataset_task,
+        ImportScope::All,
+        progress,
+    )?;
+    let sources = annotation_sources(dataset_path, &dataset_type, dataset_task);
+    MetadataQueries::set_annotation_sources(&tx, &sources)?;
+    tx.commit()?;
+
+    Ok(CacheCreate {
+        images: image_count,
+        scan_errors: scan_errors.len(),
+        annotation_errors,
+        detected,
+    })
+}
+
+/// Directory, relative to the dataset root, whose cached images must be kept
+/// because the walk failed on `path`: the directory itself when it could not
+/// be listed, otherwise the one holding the entry. The root protects everything.
+fn unwalked_dir(dataset_path: &Path, path: &str) -> PathBuf {
+    let path = Path::new(path);
+    if dataset_path.join(path).is_dir() {
+        path.to_path_buf()
+    } else {
+        path.parent().map(Path::to_path_buf).unwrap_or_default()
+    }
+}
+
+/// Refresh an existing cache against the dataset on disk.
+///
+/// Only files whose size or modification time changed are hashed and read
+/// again; rows for removed files are deleted and new files inserted. Cached
+/// images under a directory the walk could not list are kept. Annotations are
+/// re-imported for added and changed images and for label files that changed,
+/// or for every image when data.yaml or a COCO file changed. A missing cache
+/// is created from scratch.
+/// With perceptual hashes requested, images cached without them count as
+/// changed, and with a full decode requested, images whose header only was read.
+/// The cache is written in a single transaction, so cancelling or failing
+/// part way leaves it untouched.
+pub fn update_cache_db(
+    cache_path: &Path,
+    dataset_path: &Path,
+    dataset_type: &DatasetType,
+    dataset_task: &DatasetTask,
+    options: &ScanOptions,
+    progress: &dyn ProgressSink,
+) -> DatalintResult<CacheUpdate> {
+    if !cache_path.exists() {
+        let created = create_cache_db(
+            cache_path,
+            dataset_path,
+            dataset_type,
+            dataset_task,
+            options,
+            progress,
+        )?;
+        return Ok(CacheUpdate {
+            added: created.images,
+            scan_errors: created.scan_errors,
+            annotation_errors: created.annotation_errors,
+            detected: created.detected,
+            ..CacheUpdate::default()
+        });
+    }
+
+    let mut db = Database::open(cache_path)?;
+    let metadata = db.get_cache_metadata()?.ok_or_else(|| {
+        DatalintError::Core(format!(
+            "Cache has no metadata, recreate it: {}",
+            cache_path.display()
+        ))
+    })?;
 
-    if image_count > 0 {
-        println!("Found {} images, caching...", image_count);
-        insert_images_batch(&mut db, &images, 10000)?;
+    if !same_path(Path::new(&metadata.dataset_path), dataset_path) {
+        return Err(DatalintError::Core(format!(
+            "Cache was built for {}, not {}",
+            metadata.dataset_path,
+            dataset_path.display()
+        )));
     }
 
-    Ok(image_count)
+    let (dataset_type, detected) = resolve_dataset_type(dataset_path, dataset_type);
+
+    // Compare the files on disk with the cached size and mtime
+    let mut cached: HashMap<(String, String), Image> = ImageQueries::get_all(db.conn())?
+        .into_iter()
+        .map(|image| ((image.relative_path.clone(), image.filename.clone()), image))
+        .collect();
+
+    let (image_paths, mut scan_errors) = find_image_paths(dataset_path, progress)?;
+
+    // Images missing from the walk are only removed from directories it
+    // listed, and that hold no file it could not key
+    let mut unwalked: Vec<PathBuf> = scan_errors
+        .iter()
+        .filter(|error| error.stage == "walk")
+        .map(|error| unwalked_dir(dataset_path, &error.path))
+        .collect();
+    let mut keyed: Vec<((String, String), PathBuf)> = Vec::with_capacity(image_paths.len());
+    for path in image_paths {
+        match image_key(&path, dataset_path) {
+            Ok(key) => keyed.push((key, path)),
+            Err(_) => unwalked.push(
+                path.parent()
+                    .and_then(|dir| dir.strip_prefix(dataset_path).ok())
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default(),
+            ),
+        }
+    }
+
+    let is_stale: Vec<bool> = keyed
+        .par_iter()
+        .map(|(key, path)| match cached.get(key) {
+            Some(image) => fs::metadata(path)
+                .map(|meta| {
+                    image.file_size != Some(meta.len() as i64)
+                        || image.file_mtime.is_none()
+                        || image.file_mtime != file_mtime(&meta)
+                        || (options.perceptual_hashes
+                            && image.phash.is_none()
+                            && !image.is_corrupted)
+                        || (options.full_decode && !image.is_decoded && !image.is_corrupted)
+                })
+                .unwrap_or(true),
+            None => true,
+        })
+        .collect();
+
+    let mut update = CacheUpdate {
+        detected,
+        ..CacheUpdate::default()
+    };
+    let mut stale_paths = Vec::new();
+    let mut stale_ids: HashMap<(String, String), i32> = HashMap::new();
+    for ((key, path), stale) in keyed.into_iter().zip(is_stale) {
+        let image = cached.remove(&key);
+        if !stale {
+            update.unchanged += 1;
+            continue;
+        }
+        if let Some(id) = image.and_then(|image| image.id) {
+            stale_ids.insert(key, id);
+        }
+        stale_paths.push(path);
+    }
+
+    // Read the changed files before touching the cache
+    let mut processed = Vec::new();
+    for (i, chunk) in stale_paths.chunks(SCAN_BATCH).enumerate() {
+        let (images, read_errors) = process_images(chunk, dataset_path, options);
+        processed.extend(images);
+        scan_errors.extend(read_errors);
+        let done = i * SCAN_BATCH + chunk.len();
+        progress.update(Stage::Hash, done, Some(stale_paths.len()))?;
+    }
+
+    // Whatever is left in the cache no longer exists on disk, unless the walk
+    // could not see it
+    let removed: Vec<Image> = cached
+        .into_values()
+        .filter(|image| {
+            let dir = Path::new(&image.relative_path);
+            !unwalked.iter().any(|unwalked| dir.starts_with(unwalked))
+        })
+        .collect();
+    let removed_ids: Vec<i32> = removed.iter().filter_map(|image| image.id).collect();
+    // Errors logged under the image path rather than a label file
+    let removed_sources: Vec<String> = removed
+        .iter()
+        .map(|image| {
+            Path::new(&image.relative_path)
+                .join(&image.filename)
+                .display()
+                .to_string()
+        })
+        .collect();
+    update.removed = removed_ids.len();
+
+    let sources = annotation_sources(dataset_path, &dataset_type, dataset_task);
+    let reimport = MetadataQueries::annotation_sources(db.conn())?.as_deref() != Some(&sources);
+
+    let tx = db.transaction()?;
+    ImageQueries::delete_by_ids(&tx, &removed_ids)?;
+    ScanErrorQueries::delete_annotation_errors(&tx, &removed_sources)?;
+
+    let mut touched: HashSet<i32> = HashSet::new();
+    let mut new_images = Vec::new();
+    for image in processed {
+        let key = (image.relative_path.clone(), image.filename.clone());
+        match stale_ids.get(&key) {
+            Some(&id) => {
+                ImageQueries::update_file(&tx, id, &image)?;
+                touched.insert(id);
+            }
+            None => new_images.push(image),
+        }
+    }
+    ImageQueries::append(&tx, &mut new_images)?;
+    touched.extend(new_images.iter().filter_map(|image| image.id));
+    update.changed = touched.len() - new_images.len();
+    update.added = new_images.len();
+
+    update.scan_errors = scan_errors.len();
+    record_scan_errors(&tx, &mut scan_errors)?;
+
+    // Findings are derived from the whole cache, the next lint run redoes them
+    FindingQueries::delete_all(&tx)?;
+    let scope = if reimport {
+        AnnotationQueries::delete_all(&tx)?;
+        ImportScope::All
+    } else {
+        ImportScope::Changed {
+            images: &touched,
+            images_removed: !removed_ids.is_empty(),
+        }
+    };
+    update.annotation_errors = import_annotations(
+        &tx,
+        dataset_path,
+        &dataset_type,
+        dataset_task,
+        scope,
+        progress,
+    )?;
+    MetadataQueries::set_annotation_sources(&tx, &sources)?;
+    MetadataQueries::touch(&tx)?;
+    tx.commit()?;
+
+    Ok(update)
 }
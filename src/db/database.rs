@@ -1,4 +1,4 @@
-use crate::errors::DatalintResult;
+use crate::errors::{DatalintError, DatalintResult};
 use chrono::Utc;
 use duckdb::Connection;
 use std::path::Path;
@@ -17,26 +17,73 @@ impl Database {
     pub fn new_memory() -> DatalintResult<Self> {
         let conn = Connection::open_in_memory()?;
         let mut db = Self { conn };
-        db.init_schema()?;
+        db.migrate()?;
         Ok(db)
     }
 
-    /// Open or create a database file
+    /// Open or create a database file, upgrading its schema to the current version
     pub fn open(path: &Path) -> DatalintResult<Self> {
         let conn = Connection::open(path)?;
         let mut db = Self { conn };
+        db.migrate()?;
+        Ok(db)
+    }
+
+    /// Apply pending migrations in version order, each in its own transaction.
+    ///
+    /// Caches written by a newer release are refused rather than guessed at.
+    /// Returns the schema version after migrating.
+    pub fn migrate(&mut self) -> DatalintResult<u32> {
+        self.conn.execute_batch(schema::CREATE_SCHEMA_VERSION)?;
 
-        // Initialize schema if tables don't exist
-        if !db.tables_exist()? {
-            db.init_schema()?;
+        let mut current = self.schema_version()?;
+
+        // Caches from before versioning carry the initial schema only
+        if current == 0 && self.tables_exist()? {
+            Self::record_migration(&self.conn, &schema::MIGRATIONS[0])?;
+            current = schema::MIGRATIONS[0].version;
         }
 
-        Ok(db)
+        if current > schema::SCHEMA_VERSION {
+            return Err(DatalintError::Core(format!(
+                "Cache schema version {} is newer than the supported version {}; \
+                 upgrade datalint-core or recreate the cache",
+                current,
+                schema::SCHEMA_VERSION
+            )));
+        }
+
+        for migration in schema::MIGRATIONS {
+            if migration.version <= current {
+                continue;
+            }
+
+            let tx = self.conn.transaction()?;
+            tx.execute_batch(migration.sql)?;
+            Self::record_migration(&tx, migration)?;
+            tx.commit()?;
+            current = migration.version;
+        }
+
+        Ok(current)
+    }
+
+    /// Highest applied migration, 0 for an empty database
+    pub fn schema_version(&self) -> DatalintResult<u32> {
+        let version: Option<u32> =
+            self.conn
+                .query_row("SELECT MAX(version) FROM schema_version", [], |row| {
+                    row.get(0)
+                })?;
+        Ok(version.unwrap_or(0))
     }
 
-    /// Initialize database schema
-    pub fn init_schema(&mut self) -> DatalintResult<()> {
-        self.conn.execute_batch(schema::SCHEMA)?;
+    /// Mark a migration as applied
+    fn record_migration(conn: &Connection, migration: &schema::Migration) -> DatalintResult<()> {
+        conn.execute(
+            "INSERT INTO schema_version (version, name, applied_at) VALUES (?, ?, ?)",
+            duckdb::params![migration.version, migration.name, Utc::now().to_rfc3339()],
+        )?;
         Ok(())
     }
 
@@ -116,14 +163,16 @@ impl Database {
 
     /// Update cache metadata timestamp
     pub fn touch_cache_metadata(&mut self) -> DatalintResult<()> {
-        let now = Utc::now().to_rfc3339();
-
-        self.conn.execute(
-            "UPDATE cache_metadata SET updated_at = ? WHERE id = 1",
-            duckdb::params![&now],
-        )?;
+        MetadataQueries::touch(&self.conn)
+    }
 
-        Ok(())
+    /// Store keypoint names and skeleton edges as JSON
+    pub fn set_keypoint_metadata(
+        &mut self,
+        keypoint_names: Option<&str>,
+        keypoint_skeleton: Option<&str>,
+    ) -> DatalintResult<()> {
+        MetadataQueries::set_keypoints(&self.conn, keypoint_names, keypoint_skeleton)
     }
 
     /// Get a reference to the connection for direct queries
@@ -136,59 +185,46 @@ impl Database {
         Ok(self.conn.transaction()?)
     }
 
-    /// Execute a batch insert for better performance
-    pub fn batch_insert_images(&mut self, images: &[Image]) -> DatalintResult<Vec<i64>> {
+    /// Bulk insert images through the appender, filling in their ids
+    pub fn batch_insert_images(&mut self, images: &mut [Image]) -> DatalintResult<Vec<i64>> {
         let tx = self.transaction()?;
-        let mut ids = Vec::with_capacity(images.len());
-
-        for image in images {
-            let id = ImageQueries::insert(&tx, image)?;
-            ids.push(id);
-        }
-
+        ImageQueries::append(&tx, images)?;
         tx.commit()?;
 
-        Ok(ids)
+        Ok(images
+            .iter()
+            .filter_map(|image| image.id)
+            .map(i64::from)
+            .collect())
     }
 
-    /// Batch insert bboxes
+    /// Bulk insert bboxes through the appender, filling in their ids
     pub fn batch_insert_bboxes(&mut self, bboxes: &mut [Bbox]) -> DatalintResult<Vec<i64>> {
         let tx = self.transaction()?;
-        let mut ids = Vec::with_capacity(bboxes.len());
-
-        for bbox in bboxes {
-            let id = BboxQueries::insert(&tx, bbox)?;
-            ids.push(id);
-        }
-
+        BboxQueries::append(&tx, bboxes)?;
         tx.commit()?;
 
-        Ok(ids)
+        Ok(bboxes
+            .iter()
+            .filter_map(|bbox| bbox.id)
+            .map(i64::from)
+            .collect())
     }
 
     /// Delete an image and cascade to related records
     pub fn delete_image(&mut self, image_id: i32) -> DatalintResult<()> {
         let tx = self.transaction()?;
+        ImageQueries::delete_by_ids(&tx, &[image_id])?;
+        tx.commit()?;
+        Ok(())
+    }
 
-        // Delete in cascade order (children first)
-        tx.execute(
-            "DELETE FROM keypoints WHERE bbox_id IN (SELECT id FROM bboxes WHERE image_id = ?)",
-            duckdb::params![image_id],
-        )?;
-        tx.execute(
-            "DELETE FROM segmentations WHERE bbox_id IN (SELECT id FROM bboxes WHERE image_id = ?)",
-            duckdb::params![image_id],
-        )?;
-        tx.execute(
-            "DELETE FROM bboxes WHERE image_id = ?",
-            duckdb::params![image_id],
-        )?;
-        tx.execute(
-            "DELETE FROM classifications WHERE image_id = ?",
-            duckdb::params![image_id],
-        )?;
-        tx.execute("DELETE FROM images WHERE id = ?", duckdb::params![image_id])?;
-
+    /// Delete every annotation, label, label file record and lint finding,
+    /// keeping images, so a dataset can be re-imported on top of an existing cache
+    pub fn clear_annotations(&mut self) -> DatalintResult<()> {
+        let tx = self.transaction()?;
+        AnnotationQueries::delete_all(&tx)?;
+        FindingQueries::delete_all(&tx)?;
         tx.commit()?;
         Ok(())
     }
@@ -1,6 +1,20 @@
+use crate::enums::Severity;
+use crate::errors::DatalintResult;
+use crate::rle::Rle;
+use pyo3::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::f64::consts::{FRAC_PI_2, PI};
+
+/// Optional value as Python would print it
+fn py_repr<T: std::fmt::Display>(value: &Option<T>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "None".to_string(),
+    }
+}
 
 /// Cache metadata - single row configuration
+#[pyclass(frozen, get_all)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheMetadata {
     pub id: i32,
@@ -14,15 +28,35 @@ pub struct CacheMetadata {
     pub keypoint_skeleton: Option<String>,
 }
 
+#[pymethods]
+impl CacheMetadata {
+    fn __repr__(&self) -> String {
+        format!(
+            "CacheMetadata(dataset_path='{}', dataset_type='{}', dataset_task='{}')",
+            self.dataset_path, self.dataset_type, self.dataset_task
+        )
+    }
+}
+
 /// Label information
+#[pyclass(frozen, get_all)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Label {
     pub id: Option<i32>,
     pub name: String,
     pub color: Option<String>,
+    pub supercategory: Option<String>,
+}
+
+#[pymethods]
+impl Label {
+    fn __repr__(&self) -> String {
+        format!("Label(id={}, name='{}')", py_repr(&self.id), self.name)
+    }
 }
 
 /// Image information
+#[pyclass(frozen, get_all)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Image {
     pub id: Option<i32>,
@@ -35,32 +69,173 @@ pub struct Image {
     pub height: Option<i32>,
     pub channels: Option<i32>,
     pub file_size: Option<i64>,
+    pub file_mtime: Option<i64>, // Nanoseconds since the Unix epoch
     pub file_hash: String,
     pub is_corrupted: bool,
+    pub ahash: Option<i64>, // Perceptual hashes, see crate::phash
+    pub dhash: Option<i64>,
+    pub phash: Option<i64>,
+    pub is_decoded: bool, // Pixel data decoded, not only the header read
+}
+
+impl Image {
+    /// Path relative to the dataset root, without a leading slash for
+    /// images at the root
+    pub fn path(&self) -> String {
+        if self.relative_path.is_empty() {
+            self.filename.clone()
+        } else {
+            format!("{}/{}", self.relative_path, self.filename)
+        }
+    }
+}
+
+#[pymethods]
+impl Image {
+    fn __repr__(&self) -> String {
+        format!(
+            "Image(id={}, relative_path='{}', filename='{}', split={})",
+            py_repr(&self.id),
+            self.relative_path,
+            self.filename,
+            py_repr(&self.split.as_ref().map(|split| format!("'{}'", split)))
+        )
+    }
 }
 
 /// Bounding box
+#[pyclass(frozen)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Bbox {
+    #[pyo3(get)]
     pub id: Option<i32>,
+    #[pyo3(get)]
     pub image_id: i32,
+    #[pyo3(get)]
     pub label_id: i32,
+    #[pyo3(get)]
     pub x1: f64,
+    #[pyo3(get)]
     pub y1: f64,
+    #[pyo3(get)]
     pub x2: f64,
+    #[pyo3(get)]
     pub y2: f64,
-    pub cx: Option<f64>,   // Computed on insert
-    pub cy: Option<f64>,   // Computed on insert
-    pub w: Option<f64>,    // Computed on insert
-    pub h: Option<f64>,    // Computed on insert
+    #[pyo3(get)]
+    pub cx: Option<f64>, // Computed on insert
+    #[pyo3(get)]
+    pub cy: Option<f64>, // Computed on insert
+    #[pyo3(get)]
+    pub w: Option<f64>, // Computed on insert
+    #[pyo3(get)]
+    pub h: Option<f64>, // Computed on insert
+    #[pyo3(get)]
     pub area: Option<f64>, // Computed on insert
+    #[pyo3(get)]
     pub angle: Option<f64>,
+    #[pyo3(get)]
     pub confidence: Option<f64>,
+    pub attributes: Option<serde_json::Value>, // Format-specific flags (VOC difficult, ...)
 }
 
 impl Bbox {
-    /// Compute derived values (cx, cy, w, h, area)
+    /// Create an axis-aligned box from corners, derived values are computed on insert
+    pub fn new(image_id: i32, label_id: i32, x1: f64, y1: f64, x2: f64, y2: f64) -> Self {
+        Self {
+            id: None,
+            image_id,
+            label_id,
+            x1,
+            y1,
+            x2,
+            y2,
+            cx: None,
+            cy: None,
+            w: None,
+            h: None,
+            area: None,
+            angle: None,
+            confidence: None,
+            attributes: None,
+        }
+    }
+
+    /// Create a rotated box from its four corners, given in order around the box.
+    /// x1..y2 hold the axis-aligned envelope while cx, cy, w, h and angle (radians,
+    /// in [0, pi)) describe the rotated rectangle.
+    pub fn from_obb_corners(image_id: i32, label_id: i32, corners: &[(f64, f64); 4]) -> Self {
+        let dist = |a: (f64, f64), b: (f64, f64)| (b.0 - a.0).hypot(b.1 - a.1);
+        let [p0, p1, p2, p3] = *corners;
+
+        // Average opposite edges so slightly skewed quads still give a rectangle
+        let w = (dist(p0, p1) + dist(p3, p2)) / 2.0;
+        let h = (dist(p1, p2) + dist(p0, p3)) / 2.0;
+        // A collapsed first edge has no direction, the next edge is a quarter turn on
+        let angle = if dist(p0, p1) > 0.0 || dist(p1, p2) == 0.0 {
+            (p1.1 - p0.1).atan2(p1.0 - p0.0)
+        } else {
+            (p2.1 - p1.1).atan2(p2.0 - p1.0) - FRAC_PI_2
+        };
+        // A rectangle turned by half a turn is the same rectangle: keep [0, pi)
+        let angle = match angle.rem_euclid(PI) {
+            a if a >= PI => 0.0,
+            a => a,
+        };
+
+        let xs = corners.map(|p| p.0);
+        let ys = corners.map(|p| p.1);
+        let mut bbox = Self::new(
+            image_id,
+            label_id,
+            xs.iter().copied().fold(f64::INFINITY, f64::min),
+            ys.iter().copied().fold(f64::INFINITY, f64::min),
+            xs.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            ys.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        );
+        bbox.cx = Some(xs.iter().sum::<f64>() / 4.0);
+        bbox.cy = Some(ys.iter().sum::<f64>() / 4.0);
+        bbox.w = Some(w);
+        bbox.h = Some(h);
+        bbox.angle = Some(angle);
+        bbox
+    }
+
+    /// Whether the box carries a non-zero rotation
+    pub fn is_rotated(&self) -> bool {
+        self.angle.map(|a| a != 0.0).unwrap_or(false)
+    }
+
+    /// Corners of the rotated rectangle (clockwise from the first corner),
+    /// or of the axis-aligned box when not rotated
+    pub fn obb_corners(&self) -> [(f64, f64); 4] {
+        let (Some(cx), Some(cy), Some(w), Some(h), true) =
+            (self.cx, self.cy, self.w, self.h, self.is_rotated())
+        else {
+            return [
+                (self.x1, self.y1),
+                (self.x2, self.y1),
+                (self.x2, self.y2),
+                (self.x1, self.y2),
+            ];
+        };
+
+        let (sin, cos) = self.angle.unwrap().sin_cos();
+        [(-w, -h), (w, -h), (w, h), (-w, h)].map(|(dx, dy)| {
+            let (dx, dy) = (dx / 2.0, dy / 2.0);
+            (cx + dx * cos - dy * sin, cy + dx * sin + dy * cos)
+        })
+    }
+
+    /// Compute derived values (cx, cy, w, h, area).
+    /// Rotated boxes keep their center and size, only the area is derived.
     pub fn compute_derived(&mut self) {
+        if let (true, Some(w), Some(h)) = (self.is_rotated(), self.w, self.h) {
+            if self.cx.is_some() && self.cy.is_some() {
+                self.area = Some(w * h);
+                return;
+            }
+        }
+
         self.cx = Some((self.x1 + self.x2) / 2.0);
         self.cy = Some((self.y1 + self.y2) / 2.0);
         self.w = Some(self.x2 - self.x1);
@@ -69,6 +244,28 @@ impl Bbox {
     }
 }
 
+#[pymethods]
+impl Bbox {
+    /// Format-specific attributes as a JSON string
+    #[getter(attributes)]
+    fn attributes_json(&self) -> Option<String> {
+        self.attributes.as_ref().map(|value| value.to_string())
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Bbox(id={}, image_id={}, label_id={}, x1={}, y1={}, x2={}, y2={})",
+            py_repr(&self.id),
+            self.image_id,
+            self.label_id,
+            self.x1,
+            self.y1,
+            self.x2,
+            self.y2
+        )
+    }
+}
+
 /// Segmentation vertices
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Segmentation {
@@ -78,6 +275,45 @@ pub struct Segmentation {
     pub vertex_count: i32,
 }
 
+impl Segmentation {
+    /// Rasterize the outline into a mask of the image size
+    pub fn to_rle(&self, height: u32, width: u32) -> Rle {
+        Rle::from_polygons(std::slice::from_ref(&self.vertices), height, width)
+    }
+}
+
+/// Run-length encoded mask stored with COCO compressed counts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mask {
+    pub id: Option<i32>,
+    pub bbox_id: i32,
+    pub height: i32,
+    pub width: i32,
+    pub counts: String,
+    pub area: i64,
+    pub is_crowd: bool,
+}
+
+impl Mask {
+    /// Build a mask row from a decoded RLE
+    pub fn from_rle(bbox_id: i32, rle: &Rle, is_crowd: bool) -> Self {
+        Self {
+            id: None,
+            bbox_id,
+            height: rle.height as i32,
+            width: rle.width as i32,
+            counts: rle.to_compressed(),
+            area: rle.area() as i64,
+            is_crowd,
+        }
+    }
+
+    /// Decode the stored counts
+    pub fn to_rle(&self) -> DatalintResult<Rle> {
+        Rle::from_compressed(&self.counts, self.height as u32, self.width as u32)
+    }
+}
+
 /// Point structure for keypoints
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Point {
@@ -104,3 +340,94 @@ pub struct Classification {
     pub label_id: i32,
     pub confidence: Option<f64>,
 }
+
+/// A lint rule result, tied to an image and/or box when it has one
+#[pyclass(frozen)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Finding {
+    #[pyo3(get)]
+    pub id: Option<i32>,
+    #[pyo3(get)]
+    pub rule_id: String,
+    #[pyo3(get)]
+    pub severity: Severity,
+    #[pyo3(get)]
+    pub image_id: Option<i32>,
+    #[pyo3(get)]
+    pub bbox_id: Option<i32>,
+    #[pyo3(get)]
+    pub message: String,
+    pub extra: Option<serde_json::Value>, // Rule-specific details
+    #[pyo3(get)]
+    pub path: Option<String>, // File the finding is about, relative to the dataset root
+}
+
+impl Finding {
+    /// Create a dataset-level finding, attach ids and extra details as needed
+    pub fn new(rule_id: &str, severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            id: None,
+            rule_id: rule_id.to_string(),
+            severity,
+            image_id: None,
+            bbox_id: None,
+            message: message.into(),
+            extra: None,
+            path: None,
+        }
+    }
+}
+
+#[pymethods]
+impl Finding {
+    /// Rule-specific details as a JSON string
+    #[getter(extra)]
+    fn extra_json(&self) -> Option<String> {
+        self.extra.as_ref().map(|value| value.to_string())
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Finding(rule_id='{}', severity={}, image_id={}, bbox_id={}, message='{}')",
+            self.rule_id,
+            self.severity,
+            py_repr(&self.image_id),
+            py_repr(&self.bbox_id),
+            self.message
+        )
+    }
+}
+
+/// Annotation file seen during import, matched to an image or orphaned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabelFile {
+    pub id: Option<i32>,
+    pub path: String, // Relative to the dataset root
+    pub image_id: Option<i32>,
+    pub annotation_count: i32, // Non-empty lines or objects, valid or not
+    pub file_size: Option<i64>,
+    pub file_mtime: Option<i64>, // Nanoseconds since the Unix epoch, like images
+}
+
+/// A file the scanner could not list, read or insert, or an annotation
+/// problem found while importing it
+#[pyclass(frozen, get_all)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanError {
+    pub id: Option<i32>,
+    pub path: String,  // Relative to the dataset root when inside it
+    pub stage: String, // walk, read, insert or annotation
+    pub kind: String,  // not_found, permission_denied, io, database, missing_image, ...
+    pub message: String,
+    pub created_at: String, // RFC 3339
+}
+
+#[pymethods]
+impl ScanError {
+    fn __repr__(&self) -> String {
+        format!(
+            "ScanError(path='{}', stage='{}', kind='{}', message='{}')",
+            self.path, self.stage, self.kind, self.message
+        )
+    }
+}
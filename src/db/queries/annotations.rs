@@ -0,0 +1,43 @@
+use crate::db::queries::ids::id_list;
+use crate::errors::DatalintResult;
+use duckdb::Connection;
+
+pub struct AnnotationQueries;
+
+impl AnnotationQueries {
+    /// `{ids}` is replaced by the image ids, children before their boxes
+    const DELETE_BY_IMAGES: &'static str = r#"
+        DELETE FROM keypoints WHERE bbox_id IN (SELECT id FROM bboxes WHERE image_id IN ({ids}));
+        DELETE FROM segmentations WHERE bbox_id IN (SELECT id FROM bboxes WHERE image_id IN ({ids}));
+        DELETE FROM masks WHERE bbox_id IN (SELECT id FROM bboxes WHERE image_id IN ({ids}));
+        DELETE FROM bboxes WHERE image_id IN ({ids});
+        DELETE FROM classifications WHERE image_id IN ({ids});
+    "#;
+
+    const DELETE_ALL: &'static str = r#"
+        DELETE FROM keypoints;
+        DELETE FROM segmentations;
+        DELETE FROM masks;
+        DELETE FROM bboxes;
+        DELETE FROM classifications;
+        DELETE FROM label_files;
+        DELETE FROM scan_errors WHERE stage = 'annotation';
+        DELETE FROM labels;
+    "#;
+
+    /// Delete the boxes (with their segmentations, masks and keypoints) and
+    /// classifications of some images
+    pub fn delete_by_images(conn: &Connection, image_ids: &[i32]) -> DatalintResult<()> {
+        if image_ids.is_empty() {
+            return Ok(());
+        }
+        let sql = Self::DELETE_BY_IMAGES.replace("{ids}", &id_list(image_ids));
+        conn.execute_batch(&sql).map_err(Into::into)
+    }
+
+    /// Delete every annotation, label, label file record and annotation
+    /// error, so the dataset can be imported again from scratch
+    pub fn delete_all(conn: &Connection) -> DatalintResult<()> {
+        conn.execute_batch(Self::DELETE_ALL).map_err(Into::into)
+    }
+}
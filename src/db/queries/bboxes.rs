@@ -1,4 +1,5 @@
-use crate::db::models::{Bbox, Keypoint, Segmentation};
+use crate::db::models::{Bbox, Keypoint, Mask, Segmentation};
+use crate::db::queries::ids::assign_ids;
 use crate::errors::DatalintResult;
 use duckdb::{params, Connection};
 use serde_json;
@@ -7,13 +8,13 @@ pub struct BboxQueries;
 
 impl BboxQueries {
     const INSERT: &'static str = r#"
-        INSERT INTO bboxes (image_id, label_id, x1, y1, x2, y2, cx, cy, w, h, area, angle, confidence)
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        INSERT INTO bboxes (image_id, label_id, x1, y1, x2, y2, cx, cy, w, h, area, angle, confidence, attributes)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         RETURNING id
     "#;
 
     const SELECT_BY_IMAGE: &'static str = r#"
-        SELECT id, image_id, label_id, x1, y1, x2, y2, cx, cy, w, h, area, angle, confidence
+        SELECT id, image_id, label_id, x1, y1, x2, y2, cx, cy, w, h, area, angle, confidence, attributes
         FROM bboxes WHERE image_id = ?
     "#;
 
@@ -29,6 +30,22 @@ impl BboxQueries {
         RETURNING id
     "#;
 
+    const SELECT_SEGMENTATIONS_BY_BBOX: &'static str = r#"
+        SELECT id, bbox_id, CAST(vertices AS VARCHAR), vertex_count
+        FROM segmentations WHERE bbox_id = ?
+    "#;
+
+    const INSERT_MASK: &'static str = r#"
+        INSERT INTO masks (bbox_id, height, width, counts, area, is_crowd)
+        VALUES (?, ?, ?, ?, ?, ?)
+        RETURNING id
+    "#;
+
+    const SELECT_MASKS_BY_BBOX: &'static str = r#"
+        SELECT id, bbox_id, height, width, counts, area, is_crowd
+        FROM masks WHERE bbox_id = ?
+    "#;
+
     const INSERT_KEYPOINT: &'static str = r#"
         INSERT INTO keypoints (bbox_id, points, point_count, has_visibility)
         VALUES (?, ?, ?, ?)
@@ -38,6 +55,7 @@ impl BboxQueries {
     /// Insert a bounding box (computes derived values)
     pub fn insert(conn: &Connection, bbox: &mut Bbox) -> DatalintResult<i64> {
         bbox.compute_derived();
+        let attributes_json = bbox.attributes.as_ref().map(|a| a.to_string());
 
         conn.query_row(
             Self::INSERT,
@@ -55,12 +73,49 @@ impl BboxQueries {
                 bbox.area.unwrap(),
                 bbox.angle.unwrap_or(0.0),
                 bbox.confidence,
+                attributes_json,
             ],
             |row| row.get(0),
         )
         .map_err(Into::into)
     }
 
+    /// Append bounding boxes through the bulk appender (computes derived
+    /// values), giving rows without an id one from the sequence
+    pub fn append(conn: &Connection, bboxes: &mut [Bbox]) -> DatalintResult<()> {
+        assign_ids(
+            conn,
+            "bboxes_id_seq",
+            bboxes.iter_mut().map(|bbox| &mut bbox.id),
+        )?;
+        let mut appender = conn.appender("bboxes")?;
+
+        for bbox in bboxes.iter_mut() {
+            bbox.compute_derived();
+            let attributes_json = bbox.attributes.as_ref().map(|a| a.to_string());
+
+            appender.append_row(params![
+                bbox.id,
+                bbox.image_id,
+                bbox.label_id,
+                bbox.x1,
+                bbox.y1,
+                bbox.x2,
+                bbox.y2,
+                bbox.cx.unwrap(),
+                bbox.cy.unwrap(),
+                bbox.w.unwrap(),
+                bbox.h.unwrap(),
+                bbox.area.unwrap(),
+                bbox.angle.unwrap_or(0.0),
+                bbox.confidence,
+                attributes_json,
+            ])?;
+        }
+
+        appender.flush().map_err(Into::into)
+    }
+
     /// Insert segmentation for a bbox
     pub fn insert_segmentation(conn: &Connection, seg: &Segmentation) -> DatalintResult<i64> {
         let vertices_json = serde_json::to_string(&seg.vertices)
@@ -74,6 +129,124 @@ impl BboxQueries {
         .map_err(Into::into)
     }
 
+    /// Append segmentations through the bulk appender
+    pub fn append_segmentations(
+        conn: &Connection,
+        segmentations: &mut [Segmentation],
+    ) -> DatalintResult<()> {
+        assign_ids(
+            conn,
+            "segmentations_id_seq",
+            segmentations.iter_mut().map(|seg| &mut seg.id),
+        )?;
+        let mut appender = conn.appender("segmentations")?;
+
+        for seg in segmentations.iter() {
+            let vertices_json = serde_json::to_string(&seg.vertices)
+                .map_err(|e| crate::errors::DatalintError::Generic(e.to_string()))?;
+            appender.append_row(params![
+                seg.id,
+                seg.bbox_id,
+                vertices_json,
+                seg.vertex_count
+            ])?;
+        }
+
+        appender.flush().map_err(Into::into)
+    }
+
+    /// Get polygon segmentations for a bbox
+    pub fn get_segmentations(conn: &Connection, bbox_id: i32) -> DatalintResult<Vec<Segmentation>> {
+        let mut stmt = conn.prepare(Self::SELECT_SEGMENTATIONS_BY_BBOX)?;
+
+        let results = stmt.query_map(params![bbox_id], |row| {
+            Ok((
+                row.get::<_, i32>(0)?,
+                row.get::<_, i32>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i32>(3)?,
+            ))
+        })?;
+
+        let mut vec = Vec::new();
+        for result in results {
+            let (id, bbox_id, vertices_json, vertex_count) = result?;
+            let vertices = serde_json::from_str(&vertices_json)
+                .map_err(|e| crate::errors::DatalintError::Generic(e.to_string()))?;
+            vec.push(Segmentation {
+                id: Some(id),
+                bbox_id,
+                vertices,
+                vertex_count,
+            });
+        }
+        Ok(vec)
+    }
+
+    /// Insert an RLE mask for a bbox
+    pub fn insert_mask(conn: &Connection, mask: &Mask) -> DatalintResult<i64> {
+        conn.query_row(
+            Self::INSERT_MASK,
+            params![
+                mask.bbox_id,
+                mask.height,
+                mask.width,
+                mask.counts,
+                mask.area,
+                mask.is_crowd as i32
+            ],
+            |row| row.get(0),
+        )
+        .map_err(Into::into)
+    }
+
+    /// Append RLE masks through the bulk appender
+    pub fn append_masks(conn: &Connection, masks: &mut [Mask]) -> DatalintResult<()> {
+        assign_ids(
+            conn,
+            "masks_id_seq",
+            masks.iter_mut().map(|mask| &mut mask.id),
+        )?;
+        let mut appender = conn.appender("masks")?;
+
+        for mask in masks.iter() {
+            appender.append_row(params![
+                mask.id,
+                mask.bbox_id,
+                mask.height,
+                mask.width,
+                mask.counts,
+                mask.area,
+                mask.is_crowd as i32
+            ])?;
+        }
+
+        appender.flush().map_err(Into::into)
+    }
+
+    /// Get RLE masks for a bbox
+    pub fn get_masks(conn: &Connection, bbox_id: i32) -> DatalintResult<Vec<Mask>> {
+        let mut stmt = conn.prepare(Self::SELECT_MASKS_BY_BBOX)?;
+
+        let results = stmt.query_map(params![bbox_id], |row| {
+            Ok(Mask {
+                id: Some(row.get(0)?),
+                bbox_id: row.get(1)?,
+                height: row.get(2)?,
+                width: row.get(3)?,
+                counts: row.get(4)?,
+                area: row.get(5)?,
+                is_crowd: row.get::<_, i32>(6)? != 0,
+            })
+        })?;
+
+        let mut vec = Vec::new();
+        for result in results {
+            vec.push(result?);
+        }
+        Ok(vec)
+    }
+
     /// Insert keypoints for a bbox
     pub fn insert_keypoint(conn: &Connection, kp: &Keypoint) -> DatalintResult<i64> {
         let points_json = serde_json::to_string(&kp.points)
@@ -92,6 +265,30 @@ impl BboxQueries {
         .map_err(Into::into)
     }
 
+    /// Append keypoints through the bulk appender
+    pub fn append_keypoints(conn: &Connection, keypoints: &mut [Keypoint]) -> DatalintResult<()> {
+        assign_ids(
+            conn,
+            "keypoints_id_seq",
+            keypoints.iter_mut().map(|kp| &mut kp.id),
+        )?;
+        let mut appender = conn.appender("keypoints")?;
+
+        for kp in keypoints.iter() {
+            let points_json = serde_json::to_string(&kp.points)
+                .map_err(|e| crate::errors::DatalintError::Generic(e.to_string()))?;
+            appender.append_row(params![
+                kp.id,
+                kp.bbox_id,
+                points_json,
+                kp.point_count,
+                kp.has_visibility as i32
+            ])?;
+        }
+
+        appender.flush().map_err(Into::into)
+    }
+
     /// Get bboxes for an image
     pub fn get_by_image(conn: &Connection, image_id: i32) -> DatalintResult<Vec<Bbox>> {
         let mut stmt = conn.prepare(Self::SELECT_BY_IMAGE)?;
@@ -112,6 +309,9 @@ impl BboxQueries {
                 area: Some(row.get(11)?),
                 angle: row.get(12)?,
                 confidence: row.get(13)?,
+                attributes: row
+                    .get::<_, Option<String>>(14)?
+                    .and_then(|a| serde_json::from_str(&a).ok()),
             })
         })?;
 
@@ -0,0 +1,95 @@
+use crate::db::models::Classification;
+use crate::db::queries::ids::assign_ids;
+use crate::errors::DatalintResult;
+use duckdb::{params, Connection};
+
+pub struct ClassificationQueries;
+
+impl ClassificationQueries {
+    const INSERT: &'static str = r#"
+        INSERT INTO classifications (image_id, label_id, confidence)
+        VALUES (?, ?, ?)
+        RETURNING id
+    "#;
+
+    const SELECT_BY_IMAGE: &'static str = r#"
+        SELECT id, image_id, label_id, confidence
+        FROM classifications WHERE image_id = ?
+    "#;
+
+    const COUNT_BY_LABEL: &'static str = r#"
+        SELECT label_id, COUNT(*) as count
+        FROM classifications
+        GROUP BY label_id
+    "#;
+
+    /// Insert an image-level label
+    pub fn insert(conn: &Connection, classification: &Classification) -> DatalintResult<i64> {
+        conn.query_row(
+            Self::INSERT,
+            params![
+                classification.image_id,
+                classification.label_id,
+                classification.confidence
+            ],
+            |row| row.get(0),
+        )
+        .map_err(Into::into)
+    }
+
+    /// Append image-level labels through the bulk appender
+    pub fn append(conn: &Connection, classifications: &mut [Classification]) -> DatalintResult<()> {
+        assign_ids(
+            conn,
+            "classifications_id_seq",
+            classifications.iter_mut().map(|c| &mut c.id),
+        )?;
+        let mut appender = conn.appender("classifications")?;
+
+        for classification in classifications.iter() {
+            appender.append_row(params![
+                classification.id,
+                classification.image_id,
+                classification.label_id,
+                classification.confidence
+            ])?;
+        }
+
+        appender.flush().map_err(Into::into)
+    }
+
+    /// Get classifications for an image
+    pub fn get_by_image(conn: &Connection, image_id: i32) -> DatalintResult<Vec<Classification>> {
+        let mut stmt = conn.prepare(Self::SELECT_BY_IMAGE)?;
+
+        let results = stmt.query_map(params![image_id], |row| {
+            Ok(Classification {
+                id: Some(row.get(0)?),
+                image_id: row.get(1)?,
+                label_id: row.get(2)?,
+                confidence: row.get(3)?,
+            })
+        })?;
+
+        let mut vec = Vec::new();
+        for result in results {
+            vec.push(result?);
+        }
+        Ok(vec)
+    }
+
+    /// Count classifications by label
+    pub fn count_by_label(conn: &Connection) -> DatalintResult<Vec<(i32, i32)>> {
+        let mut stmt = conn.prepare(Self::COUNT_BY_LABEL)?;
+
+        let results = stmt.query_map(params![], |row| {
+            Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?))
+        })?;
+
+        let mut vec = Vec::new();
+        for result in results {
+            vec.push(result?);
+        }
+        Ok(vec)
+    }
+}
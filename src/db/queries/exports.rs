@@ -0,0 +1,107 @@
+use crate::errors::{DatalintError, DatalintResult};
+use duckdb::arrow::datatypes::SchemaRef;
+use duckdb::arrow::record_batch::RecordBatch;
+use duckdb::{params, Connection};
+
+pub struct ExportQueries;
+
+impl ExportQueries {
+    const IMAGES: &'static str = r#"
+        SELECT * FROM images ORDER BY id
+    "#;
+
+    const LABELS: &'static str = r#"
+        SELECT * FROM labels ORDER BY id
+    "#;
+
+    const BBOXES: &'static str = r#"
+        SELECT * FROM bboxes ORDER BY id
+    "#;
+
+    const SEGMENTATIONS: &'static str = r#"
+        SELECT * FROM segmentations ORDER BY id
+    "#;
+
+    const MASKS: &'static str = r#"
+        SELECT * FROM masks ORDER BY id
+    "#;
+
+    const KEYPOINTS: &'static str = r#"
+        SELECT * FROM keypoints ORDER BY id
+    "#;
+
+    const CLASSIFICATIONS: &'static str = r#"
+        SELECT * FROM classifications ORDER BY id
+    "#;
+
+    const FINDINGS: &'static str = r#"
+        SELECT * FROM findings ORDER BY id
+    "#;
+
+    const LABEL_FILES: &'static str = r#"
+        SELECT * FROM label_files ORDER BY path
+    "#;
+
+    const SCAN_ERRORS: &'static str = r#"
+        SELECT * FROM scan_errors ORDER BY id
+    "#;
+
+    const BBOX_DETAILS: &'static str = r#"
+        SELECT b.id, b.image_id, i.relative_path, i.filename, i.split,
+               i.width AS image_width, i.height AS image_height,
+               b.label_id, l.name AS label, l.supercategory,
+               b.x1, b.y1, b.x2, b.y2, b.cx, b.cy, b.w, b.h, b.area, b.angle,
+               b.confidence, b.attributes
+        FROM bboxes b
+        JOIN images i ON i.id = b.image_id
+        JOIN labels l ON l.id = b.label_id
+        ORDER BY b.id
+    "#;
+
+    const CLASSIFICATION_DETAILS: &'static str = r#"
+        SELECT c.id, c.image_id, i.relative_path, i.filename, i.split,
+               c.label_id, l.name AS label, c.confidence
+        FROM classifications c
+        JOIN images i ON i.id = c.image_id
+        JOIN labels l ON l.id = c.label_id
+        ORDER BY c.id
+    "#;
+
+    /// Exportable tables and joined views, by name
+    pub const TABLES: &'static [(&'static str, &'static str)] = &[
+        ("images", Self::IMAGES),
+        ("labels", Self::LABELS),
+        ("bboxes", Self::BBOXES),
+        ("segmentations", Self::SEGMENTATIONS),
+        ("masks", Self::MASKS),
+        ("keypoints", Self::KEYPOINTS),
+        ("classifications", Self::CLASSIFICATIONS),
+        ("findings", Self::FINDINGS),
+        ("label_files", Self::LABEL_FILES),
+        ("scan_errors", Self::SCAN_ERRORS),
+        ("bbox_details", Self::BBOX_DETAILS),
+        ("classification_details", Self::CLASSIFICATION_DETAILS),
+    ];
+
+    /// Fetch a table or view as Arrow record batches
+    pub fn fetch(conn: &Connection, name: &str) -> DatalintResult<(SchemaRef, Vec<RecordBatch>)> {
+        let sql = Self::TABLES
+            .iter()
+            .find(|(table, _)| *table == name)
+            .map(|(_, sql)| *sql)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::TABLES.iter().map(|(table, _)| *table).collect();
+                DatalintError::Core(format!(
+                    "Unknown table '{}', expected one of: {}",
+                    name,
+                    names.join(", ")
+                ))
+            })?;
+
+        let mut stmt = conn.prepare(sql)?;
+        let arrow = stmt.query_arrow(params![])?;
+        let schema = arrow.get_schema();
+        let batches = arrow.collect();
+        Ok((schema, batches))
+    }
+}
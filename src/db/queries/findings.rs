@@ -0,0 +1,164 @@
+use crate::db::models::Finding;
+use crate::db::queries::ids::assign_ids;
+use crate::enums::Severity;
+use crate::errors::DatalintResult;
+use duckdb::{params, Connection};
+
+pub struct FindingQueries;
+
+impl FindingQueries {
+    const INSERT: &'static str = r#"
+        INSERT INTO findings (rule_id, severity, image_id, bbox_id, message, extra, path)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        RETURNING id
+    "#;
+
+    const DELETE_BY_RULE: &'static str = r#"
+        DELETE FROM findings WHERE rule_id = ?
+    "#;
+
+    const DELETE_ALL: &'static str = r#"
+        DELETE FROM findings
+    "#;
+
+    const SELECT_ALL: &'static str = r#"
+        SELECT id, rule_id, severity, image_id, bbox_id, message, extra, path
+        FROM findings ORDER BY id
+    "#;
+
+    const SELECT_BY_RULE: &'static str = r#"
+        SELECT id, rule_id, severity, image_id, bbox_id, message, extra, path
+        FROM findings WHERE rule_id = ? ORDER BY id
+    "#;
+
+    const SELECT_BY_IMAGE: &'static str = r#"
+        SELECT id, rule_id, severity, image_id, bbox_id, message, extra, path
+        FROM findings WHERE image_id = ? ORDER BY id
+    "#;
+
+    const COUNT_BY_RULE: &'static str = r#"
+        SELECT rule_id, COUNT(*) as count
+        FROM findings
+        GROUP BY rule_id
+        ORDER BY rule_id
+    "#;
+
+    /// Insert a finding
+    pub fn insert(conn: &Connection, finding: &Finding) -> DatalintResult<i64> {
+        let extra_json = finding.extra.as_ref().map(|e| e.to_string());
+
+        conn.query_row(
+            Self::INSERT,
+            params![
+                finding.rule_id,
+                finding.severity.as_str(),
+                finding.image_id,
+                finding.bbox_id,
+                finding.message,
+                extra_json,
+                finding.path,
+            ],
+            |row| row.get(0),
+        )
+        .map_err(Into::into)
+    }
+
+    /// Append findings through the bulk appender
+    pub fn append(conn: &Connection, findings: &mut [Finding]) -> DatalintResult<()> {
+        assign_ids(
+            conn,
+            "findings_id_seq",
+            findings.iter_mut().map(|f| &mut f.id),
+        )?;
+        let mut appender = conn.appender("findings")?;
+
+        for finding in findings.iter() {
+            let extra_json = finding.extra.as_ref().map(|e| e.to_string());
+            appender.append_row(params![
+                finding.id,
+                finding.rule_id,
+                finding.severity.as_str(),
+                finding.image_id,
+                finding.bbox_id,
+                finding.message,
+                extra_json,
+                finding.path,
+            ])?;
+        }
+
+        appender.flush().map_err(Into::into)
+    }
+
+    /// Remove every finding once the data they were derived from changed
+    pub fn delete_all(conn: &Connection) -> DatalintResult<usize> {
+        conn.execute(Self::DELETE_ALL, params![])
+            .map_err(Into::into)
+    }
+
+    /// Remove the findings of a rule before it runs again
+    pub fn delete_by_rule(conn: &Connection, rule_id: &str) -> DatalintResult<usize> {
+        conn.execute(Self::DELETE_BY_RULE, params![rule_id])
+            .map_err(Into::into)
+    }
+
+    /// Get all findings
+    pub fn get_all(conn: &Connection) -> DatalintResult<Vec<Finding>> {
+        Self::select(conn, Self::SELECT_ALL, params![])
+    }
+
+    /// Get the findings of one rule
+    pub fn get_by_rule(conn: &Connection, rule_id: &str) -> DatalintResult<Vec<Finding>> {
+        Self::select(conn, Self::SELECT_BY_RULE, params![rule_id])
+    }
+
+    /// Get the findings on one image
+    pub fn get_by_image(conn: &Connection, image_id: i32) -> DatalintResult<Vec<Finding>> {
+        Self::select(conn, Self::SELECT_BY_IMAGE, params![image_id])
+    }
+
+    /// Count findings by rule
+    pub fn count_by_rule(conn: &Connection) -> DatalintResult<Vec<(String, i32)>> {
+        let mut stmt = conn.prepare(Self::COUNT_BY_RULE)?;
+
+        let results = stmt.query_map(params![], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i32>(1)?))
+        })?;
+
+        let mut vec = Vec::new();
+        for result in results {
+            vec.push(result?);
+        }
+        Ok(vec)
+    }
+
+    /// Run a select over the full finding column list
+    fn select(
+        conn: &Connection,
+        sql: &str,
+        params: &[&dyn duckdb::ToSql],
+    ) -> DatalintResult<Vec<Finding>> {
+        let mut stmt = conn.prepare(sql)?;
+
+        let results = stmt.query_map(params, |row| {
+            let severity: String = row.get(2)?;
+            Ok(Finding {
+                id: Some(row.get(0)?),
+                rule_id: row.get(1)?,
+                severity: Severity::from_str(&severity).unwrap_or(Severity::Warning),
+                image_id: row.get(3)?,
+                bbox_id: row.get(4)?,
+                message: row.get(5)?,
+                extra: row
+                    .get::<_, Option<String>>(6)?
+                    .and_then(|e| serde_json::from_str(&e).ok()),
+                path: row.get(7)?,
+            })
+        })?;
+
+        let mut vec = Vec::new();
+        for result in results {
+            vec.push(result?);
+        }
+        Ok(vec)
+    }
+}
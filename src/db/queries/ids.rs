@@ -0,0 +1,83 @@
+use crate::errors::{DatalintError, DatalintResult};
+use duckdb::{params, Connection};
+
+/// Reserve `count` ids from a sequence, in ascending order.
+///
+/// The appender writes every column as given and never evaluates the
+/// `nextval` defaults, so rows appended with a NULL id would fail the primary
+/// key. Taking the ids from the sequence keeps later INSERTs from colliding.
+pub fn reserve_ids(conn: &Connection, sequence: &str, count: usize) -> DatalintResult<Vec<i32>> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+
+    let query = format!(
+        "SELECT nextval('{}') AS id FROM range(?) ORDER BY id",
+        sequence
+    );
+    let mut stmt = conn.prepare(&query)?;
+
+    let results = stmt.query_map(params![count as i64], |row| row.get(0))?;
+
+    let mut vec = Vec::with_capacity(count);
+    for result in results {
+        vec.push(result?);
+    }
+    Ok(vec)
+}
+
+/// Give every row without an id one from the sequence, keeping ids that were
+/// assigned up front
+pub fn assign_ids<'a>(
+    conn: &Connection,
+    sequence: &str,
+    ids: impl IntoIterator<Item = &'a mut Option<i32>>,
+) -> DatalintResult<()> {
+    let missing: Vec<&mut Option<i32>> = ids.into_iter().filter(|id| id.is_none()).collect();
+    let reserved = reserve_ids(conn, sequence, missing.len())?;
+    for (slot, id) in missing.into_iter().zip(reserved) {
+        *slot = Some(id);
+    }
+    Ok(())
+}
+
+/// Comma-separated ids for an `IN (...)` list. Ids are integers, so they can
+/// be spliced into the statement instead of bound one parameter each.
+pub(crate) fn id_list(ids: &[i32]) -> String {
+    ids.iter()
+        .map(i32::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Ids reserved from a sequence in blocks, handed out one at a time.
+///
+/// Lets importers point child rows at a parent before either is written.
+/// Ids left over when the pool is dropped are skipped, like the ids of rolled
+/// back INSERTs.
+pub struct IdPool {
+    sequence: &'static str,
+    block: usize,
+    ids: std::vec::IntoIter<i32>,
+}
+
+impl IdPool {
+    pub fn new(sequence: &'static str, block: usize) -> Self {
+        Self {
+            sequence,
+            block: block.max(1),
+            ids: Vec::new().into_iter(),
+        }
+    }
+
+    /// Next unused id, reserving another block when the pool runs dry
+    pub fn next(&mut self, conn: &Connection) -> DatalintResult<i32> {
+        if let Some(id) = self.ids.next() {
+            return Ok(id);
+        }
+        self.ids = reserve_ids(conn, self.sequence, self.block)?.into_iter();
+        self.ids.next().ok_or_else(|| {
+            DatalintError::Core(format!("Sequence {} returned no ids", self.sequence))
+        })
+    }
+}
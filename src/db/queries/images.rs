@@ -1,4 +1,6 @@
 use crate::db::models::Image;
+use crate::db::queries::annotations::AnnotationQueries;
+use crate::db::queries::ids::{assign_ids, id_list};
 use crate::errors::DatalintResult;
 use duckdb::{params, Connection};
 use sha2::{Digest, Sha256};
@@ -9,22 +11,76 @@ pub struct ImageQueries;
 
 impl ImageQueries {
     const INSERT: &'static str = r#"
-        INSERT INTO images (name, filename, extension, relative_path, split, width, height, channels, file_size, file_hash, is_corrupted)
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        INSERT INTO images (name, filename, extension, relative_path, split, width, height, channels, file_size, file_mtime, file_hash, is_corrupted, ahash, dhash, phash, is_decoded)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         RETURNING id
     "#;
 
     const SELECT_BY_HASH: &'static str = r#"
-        SELECT id, name, filename, extension, relative_path, split, width, height, channels, file_size, file_hash, is_corrupted
+        SELECT id, name, filename, extension, relative_path, split, width, height, channels, file_size, file_mtime, file_hash, is_corrupted, ahash, dhash, phash, is_decoded
         FROM images WHERE file_hash = ?
     "#;
 
+    const SELECT_ALL: &'static str = r#"
+        SELECT id, name, filename, extension, relative_path, split, width, height, channels, file_size, file_mtime, file_hash, is_corrupted, ahash, dhash, phash, is_decoded
+        FROM images ORDER BY id
+    "#;
+
+    const SELECT_BY_SPLIT: &'static str = r#"
+        SELECT id, name, filename, extension, relative_path, split, width, height, channels, file_size, file_mtime, file_hash, is_corrupted, ahash, dhash, phash, is_decoded
+        FROM images WHERE split = ? ORDER BY id
+    "#;
+
+    const SELECT_DUPLICATES: &'static str = r#"
+        SELECT id, name, filename, extension, relative_path, split, width, height, channels, file_size, file_mtime, file_hash, is_corrupted, ahash, dhash, phash, is_decoded
+        FROM images
+        WHERE file_hash IN (SELECT file_hash FROM images GROUP BY file_hash HAVING COUNT(*) > 1)
+        ORDER BY file_hash, id
+    "#;
+
+    const UPDATE_FILE: &'static str = r#"
+        UPDATE images
+        SET extension = ?, width = ?, height = ?, channels = ?, file_size = ?, file_mtime = ?, file_hash = ?, is_corrupted = ?,
+            ahash = ?, dhash = ?, phash = ?, is_decoded = ?
+        WHERE id = ?
+    "#;
+
+    const UPDATE_SPLIT_BY_DIR: &'static str = r#"
+        UPDATE images SET split = ?
+        WHERE relative_path = ? OR starts_with(relative_path, ?)
+    "#;
+
+    const UPDATE_SPLIT_BY_FILE: &'static str = r#"
+        UPDATE images SET split = ?
+        WHERE relative_path = ? AND filename = ?
+    "#;
+
+    /// `{ids}` is replaced by the image ids. Errors logged for the label files
+    /// of these images go with the file records.
+    const DELETE_BY_IDS: &'static str = r#"
+        DELETE FROM findings WHERE image_id IN ({ids});
+        DELETE FROM scan_errors WHERE stage = 'annotation'
+            AND path IN (SELECT path FROM label_files WHERE image_id IN ({ids}));
+        DELETE FROM label_files WHERE image_id IN ({ids});
+        DELETE FROM images WHERE id IN ({ids});
+    "#;
+
+    const RESET_SPLITS: &'static str = r#"
+        UPDATE images SET split = 'unknown'
+    "#;
+
     const COUNT_BY_SPLIT: &'static str = r#"
         SELECT split, COUNT(*) as count
         FROM images
         GROUP BY split
     "#;
 
+    const COUNT_WITHOUT_PHASH: &'static str = r#"
+        SELECT COUNT(*) FILTER (WHERE phash IS NULL), COUNT(*)
+        FROM images
+        WHERE is_corrupted = 0
+    "#;
+
     /// Insert a new image
     pub fn insert(conn: &Connection, image: &Image) -> DatalintResult<i64> {
         conn.query_row(
@@ -39,14 +95,89 @@ impl ImageQueries {
                 image.height,
                 image.channels,
                 image.file_size,
+                image.file_mtime,
                 image.file_hash,
-                image.is_corrupted
+                image.is_corrupted,
+                image.ahash,
+                image.dhash,
+                image.phash,
+                image.is_decoded
             ],
             |row| row.get(0),
         )
         .map_err(Into::into)
     }
 
+    /// Append images through the bulk appender, giving rows without an id
+    /// one from the sequence
+    pub fn append(conn: &Connection, images: &mut [Image]) -> DatalintResult<()> {
+        assign_ids(
+            conn,
+            "images_id_seq",
+            images.iter_mut().map(|image| &mut image.id),
+        )?;
+        let mut appender = conn.appender("images")?;
+
+        // Table column order, including columns added by later migrations
+        for image in images.iter() {
+            appender.append_row(params![
+                image.id,
+                image.name,
+                image.filename,
+                image.extension,
+                image.relative_path,
+                image.split,
+                image.width,
+                image.height,
+                image.channels,
+                image.file_size,
+                image.file_hash,
+                image.is_corrupted as i32,
+                image.file_mtime,
+                image.ahash,
+                image.dhash,
+                image.phash,
+                image.is_decoded
+            ])?;
+        }
+
+        appender.flush().map_err(Into::into)
+    }
+
+    /// Refresh the file-derived columns of an existing image after its file changed
+    pub fn update_file(conn: &Connection, id: i32, image: &Image) -> DatalintResult<usize> {
+        conn.execute(
+            Self::UPDATE_FILE,
+            params![
+                image.extension,
+                image.width,
+                image.height,
+                image.channels,
+                image.file_size,
+                image.file_mtime,
+                image.file_hash,
+                image.is_corrupted,
+                image.ahash,
+                image.dhash,
+                image.phash,
+                image.is_decoded,
+                id
+            ],
+        )
+        .map_err(Into::into)
+    }
+
+    /// Delete images along with their annotations, findings and label file
+    /// records, one statement per table
+    pub fn delete_by_ids(conn: &Connection, ids: &[i32]) -> DatalintResult<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        AnnotationQueries::delete_by_images(conn, ids)?;
+        let sql = Self::DELETE_BY_IDS.replace("{ids}", &id_list(ids));
+        conn.execute_batch(&sql).map_err(Into::into)
+    }
+
     /// Compute SHA256 hash for a file
     pub fn compute_file_hash(path: &Path) -> DatalintResult<String> {
         let data = fs::read(path)?;
@@ -59,22 +190,7 @@ impl ImageQueries {
     pub fn find_by_hash(conn: &Connection, hash: &str) -> DatalintResult<Option<Image>> {
         let mut stmt = conn.prepare(Self::SELECT_BY_HASH)?;
 
-        let result = stmt.query_row(params![hash], |row| {
-            Ok(Image {
-                id: Some(row.get(0)?),
-                name: row.get(1)?,
-                filename: row.get(2)?,
-                extension: row.get(3)?,
-                relative_path: row.get(4)?,
-                split: row.get(5)?,
-                width: row.get(6)?,
-                height: row.get(7)?,
-                channels: row.get(8)?,
-                file_size: row.get(9)?,
-                file_hash: row.get(10)?,
-                is_corrupted: row.get::<_, i32>(11)? != 0, // Convert i32 to bool
-            })
-        });
+        let result = stmt.query_row(params![hash], Self::from_row);
 
         match result {
             Ok(image) => Ok(Some(image)),
@@ -83,6 +199,72 @@ impl ImageQueries {
         }
     }
 
+    /// Get all images
+    pub fn get_all(conn: &Connection) -> DatalintResult<Vec<Image>> {
+        let mut stmt = conn.prepare(Self::SELECT_ALL)?;
+
+        let results = stmt.query_map(params![], Self::from_row)?;
+
+        let mut vec = Vec::new();
+        for result in results {
+            vec.push(result?);
+        }
+        Ok(vec)
+    }
+
+    /// Get the images of one split
+    pub fn get_by_split(conn: &Connection, split: &str) -> DatalintResult<Vec<Image>> {
+        let mut stmt = conn.prepare(Self::SELECT_BY_SPLIT)?;
+
+        let results = stmt.query_map(params![split], Self::from_row)?;
+
+        let mut vec = Vec::new();
+        for result in results {
+            vec.push(result?);
+        }
+        Ok(vec)
+    }
+
+    /// Get images whose file hash is shared with another image, grouped by hash
+    pub fn get_duplicates(conn: &Connection) -> DatalintResult<Vec<Image>> {
+        let mut stmt = conn.prepare(Self::SELECT_DUPLICATES)?;
+
+        let results = stmt.query_map(params![], Self::from_row)?;
+
+        let mut vec = Vec::new();
+        for result in results {
+            vec.push(result?);
+        }
+        Ok(vec)
+    }
+
+    /// Assign a split to every image inside a directory (relative to the dataset root)
+    pub fn set_split_by_dir(conn: &Connection, dir: &str, split: &str) -> DatalintResult<usize> {
+        let prefix = format!("{}{}", dir, std::path::MAIN_SEPARATOR);
+        conn.execute(Self::UPDATE_SPLIT_BY_DIR, params![split, dir, prefix])
+            .map_err(Into::into)
+    }
+
+    /// Assign a split to a single image
+    pub fn set_split_by_file(
+        conn: &Connection,
+        relative_path: &str,
+        filename: &str,
+        split: &str,
+    ) -> DatalintResult<usize> {
+        conn.execute(
+            Self::UPDATE_SPLIT_BY_FILE,
+            params![split, relative_path, filename],
+        )
+        .map_err(Into::into)
+    }
+
+    /// Reset every image to the unknown split
+    pub fn reset_splits(conn: &Connection) -> DatalintResult<usize> {
+        conn.execute(Self::RESET_SPLITS, params![])
+            .map_err(Into::into)
+    }
+
     /// Count images by split
     pub fn count_by_split(conn: &Connection) -> DatalintResult<Vec<(String, i32)>> {
         let mut stmt = conn.prepare(Self::COUNT_BY_SPLIT)?;
@@ -97,4 +279,35 @@ impl ImageQueries {
         }
         Ok(vec)
     }
+
+    /// Readable images cached without a pHash, and all readable images
+    pub fn count_without_phash(conn: &Connection) -> DatalintResult<(i64, i64)> {
+        conn.query_row(Self::COUNT_WITHOUT_PHASH, params![], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .map_err(Into::into)
+    }
+
+    /// Map a row selected with the full image column list
+    fn from_row(row: &duckdb::Row<'_>) -> duckdb::Result<Image> {
+        Ok(Image {
+            id: Some(row.get(0)?),
+            name: row.get(1)?,
+            filename: row.get(2)?,
+            extension: row.get(3)?,
+            relative_path: row.get(4)?,
+            split: row.get(5)?,
+            width: row.get(6)?,
+            height: row.get(7)?,
+            channels: row.get(8)?,
+            file_size: row.get(9)?,
+            file_mtime: row.get(10)?,
+            file_hash: row.get(11)?,
+            is_corrupted: row.get::<_, i32>(12)? != 0, // Convert i32 to bool
+            ahash: row.get(13)?,
+            dhash: row.get(14)?,
+            phash: row.get(15)?,
+            is_decoded: row.get(16)?,
+        })
+    }
 }
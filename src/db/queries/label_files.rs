@@ -0,0 +1,93 @@
+use crate::db::models::LabelFile;
+use crate::db::queries::ids::assign_ids;
+use crate::errors::DatalintResult;
+use duckdb::{params, Connection};
+
+pub struct LabelFileQueries;
+
+impl LabelFileQueries {
+    const INSERT: &'static str = r#"
+        INSERT INTO label_files (path, image_id, annotation_count, file_size, file_mtime)
+        VALUES (?, ?, ?, ?, ?)
+        RETURNING id
+    "#;
+
+    const SELECT_ALL: &'static str = r#"
+        SELECT id, path, image_id, annotation_count, file_size, file_mtime
+        FROM label_files ORDER BY path
+    "#;
+
+    const DELETE_BY_PATH: &'static str = r#"
+        DELETE FROM label_files WHERE path = ?
+    "#;
+
+    /// Record an annotation file
+    pub fn insert(conn: &Connection, label_file: &LabelFile) -> DatalintResult<i64> {
+        conn.query_row(
+            Self::INSERT,
+            params![
+                label_file.path,
+                label_file.image_id,
+                label_file.annotation_count,
+                label_file.file_size,
+                label_file.file_mtime
+            ],
+            |row| row.get(0),
+        )
+        .map_err(Into::into)
+    }
+
+    /// Append annotation file records through the bulk appender
+    pub fn append(conn: &Connection, label_files: &mut [LabelFile]) -> DatalintResult<()> {
+        assign_ids(
+            conn,
+            "label_files_id_seq",
+            label_files.iter_mut().map(|file| &mut file.id),
+        )?;
+        let mut appender = conn.appender("label_files")?;
+
+        for label_file in label_files.iter() {
+            appender.append_row(params![
+                label_file.id,
+                label_file.path,
+                label_file.image_id,
+                label_file.annotation_count,
+                label_file.file_size,
+                label_file.file_mtime
+            ])?;
+        }
+
+        appender.flush().map_err(Into::into)
+    }
+
+    /// Forget the records of files about to be read again
+    pub fn delete_by_paths(conn: &Connection, paths: &[String]) -> DatalintResult<()> {
+        let mut stmt = conn.prepare(Self::DELETE_BY_PATH)?;
+        for path in paths {
+            stmt.execute(params![path])?;
+        }
+        Ok(())
+    }
+
+    /// Get all recorded annotation files, in path order
+    pub fn get_all(conn: &Connection) -> DatalintResult<Vec<LabelFile>> {
+        let mut stmt = conn.prepare(Self::SELECT_ALL)?;
+
+        let results = stmt.query_map(params![], |row| {
+            Ok(LabelFile {
+                id: Some(row.get(0)?),
+                path: row.get(1)?,
+                image_id: row.get(2)?,
+                annotation_count: row.get(3)?,
+                file_size: row.get(4)?,
+                file_mtime: row.get(5)?,
+            })
+        })?;
+
+        let mut vec = Vec::new();
+        for result in results {
+            vec.push(result?);
+        }
+        Ok(vec)
+    }
+}
@@ -1,4 +1,5 @@
 use crate::db::models::Label;
+use crate::db::queries::ids::assign_ids;
 use crate::errors::DatalintResult;
 use duckdb::{params, Connection};
 
@@ -6,27 +7,60 @@ pub struct LabelQueries;
 
 impl LabelQueries {
     const INSERT: &'static str = r#"
-        INSERT INTO labels (name, color)
-        VALUES (?, ?)
+        INSERT INTO labels (name, color, supercategory)
+        VALUES (?, ?, ?)
         RETURNING id
     "#;
 
     const SELECT_ALL: &'static str = r#"
-        SELECT id, name, color FROM labels ORDER BY id
+        SELECT id, name, color, supercategory FROM labels ORDER BY id
     "#;
 
     const SELECT_BY_NAME: &'static str = r#"
-        SELECT id, name, color FROM labels WHERE name = ?
+        SELECT id, name, color, supercategory FROM labels WHERE name = ?
+    "#;
+
+    const UPDATE_SUPERCATEGORY: &'static str = r#"
+        UPDATE labels SET supercategory = ? WHERE id = ?
+    "#;
+
+    const DELETE_UNUSED: &'static str = r#"
+        DELETE FROM labels
+        WHERE id NOT IN (SELECT label_id FROM bboxes)
+          AND id NOT IN (SELECT label_id FROM classifications)
     "#;
 
     /// Insert a new label
     pub fn insert(conn: &Connection, label: &Label) -> DatalintResult<i64> {
-        conn.query_row(Self::INSERT, params![label.name, label.color], |row| {
-            row.get(0)
-        })
+        conn.query_row(
+            Self::INSERT,
+            params![label.name, label.color, label.supercategory],
+            |row| row.get(0),
+        )
         .map_err(Into::into)
     }
 
+    /// Append labels through the bulk appender
+    pub fn append(conn: &Connection, labels: &mut [Label]) -> DatalintResult<()> {
+        assign_ids(
+            conn,
+            "labels_id_seq",
+            labels.iter_mut().map(|label| &mut label.id),
+        )?;
+        let mut appender = conn.appender("labels")?;
+
+        for label in labels.iter() {
+            appender.append_row(params![
+                label.id,
+                label.name,
+                label.color,
+                label.supercategory
+            ])?;
+        }
+
+        appender.flush().map_err(Into::into)
+    }
+
     /// Get all labels
     pub fn get_all(conn: &Connection) -> DatalintResult<Vec<Label>> {
         let mut stmt = conn.prepare(Self::SELECT_ALL)?;
@@ -36,6 +70,7 @@ impl LabelQueries {
                 id: Some(row.get(0)?),
                 name: row.get(1)?,
                 color: row.get(2)?,
+                supercategory: row.get(3)?,
             })
         })?;
 
@@ -55,6 +90,7 @@ impl LabelQueries {
                 id: Some(row.get(0)?),
                 name: row.get(1)?,
                 color: row.get(2)?,
+                supercategory: row.get(3)?,
             })
         });
 
@@ -65,6 +101,23 @@ impl LabelQueries {
         }
     }
 
+    /// Replace the supercategory of a label
+    pub fn set_supercategory(
+        conn: &Connection,
+        id: i32,
+        supercategory: Option<&str>,
+    ) -> DatalintResult<()> {
+        conn.execute(Self::UPDATE_SUPERCATEGORY, params![supercategory, id])?;
+        Ok(())
+    }
+
+    /// Delete labels no box or classification uses any more, for formats
+    /// whose labels only come from the annotations
+    pub fn delete_unused(conn: &Connection) -> DatalintResult<usize> {
+        conn.execute(Self::DELETE_UNUSED, params![])
+            .map_err(Into::into)
+    }
+
     /// Get or create label by name
     pub fn get_or_create(
         conn: &Connection,
@@ -78,6 +131,7 @@ impl LabelQueries {
                 id: None,
                 name: name.to_string(),
                 color,
+                supercategory: None,
             };
             Self::insert(conn, &label).map(|id| id as i32)
         }
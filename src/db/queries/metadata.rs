@@ -0,0 +1,56 @@
+use crate::errors::DatalintResult;
+use chrono::Utc;
+use duckdb::{params, Connection, OptionalExt};
+
+pub struct MetadataQueries;
+
+impl MetadataQueries {
+    const TOUCH: &'static str = r#"
+        UPDATE cache_metadata SET updated_at = ? WHERE id = 1
+    "#;
+
+    const UPDATE_KEYPOINTS: &'static str = r#"
+        UPDATE cache_metadata SET keypoint_names = ?, keypoint_skeleton = ? WHERE id = 1
+    "#;
+
+    const SELECT_ANNOTATION_SOURCES: &'static str = r#"
+        SELECT annotation_sources FROM cache_metadata WHERE id = 1
+    "#;
+
+    const UPDATE_ANNOTATION_SOURCES: &'static str = r#"
+        UPDATE cache_metadata SET annotation_sources = ? WHERE id = 1
+    "#;
+
+    /// Set the last update timestamp to now
+    pub fn touch(conn: &Connection) -> DatalintResult<()> {
+        conn.execute(Self::TOUCH, params![Utc::now().to_rfc3339()])?;
+        Ok(())
+    }
+
+    /// Store keypoint names and skeleton edges as JSON
+    pub fn set_keypoints(
+        conn: &Connection,
+        keypoint_names: Option<&str>,
+        keypoint_skeleton: Option<&str>,
+    ) -> DatalintResult<()> {
+        conn.execute(
+            Self::UPDATE_KEYPOINTS,
+            params![keypoint_names, keypoint_skeleton],
+        )?;
+        Ok(())
+    }
+
+    /// Fingerprint of the dataset-level annotation files at the last import
+    pub fn annotation_sources(conn: &Connection) -> DatalintResult<Option<String>> {
+        let sources = conn
+            .query_row(Self::SELECT_ANNOTATION_SOURCES, params![], |row| row.get(0))
+            .optional()?;
+        Ok(sources.flatten())
+    }
+
+    /// Record the fingerprint of the files an import read
+    pub fn set_annotation_sources(conn: &Connection, sources: &str) -> DatalintResult<()> {
+        conn.execute(Self::UPDATE_ANNOTATION_SOURCES, params![sources])?;
+        Ok(())
+    }
+}
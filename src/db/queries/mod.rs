@@ -1,7 +1,23 @@
+pub mod annotations;
 pub mod bboxes;
+pub mod classifications;
+pub mod exports;
+pub mod findings;
+pub mod ids;
 pub mod images;
+pub mod label_files;
 pub mod labels;
+pub mod metadata;
+pub mod scan_errors;
 
+pub use annotations::AnnotationQueries;
 pub use bboxes::BboxQueries;
+pub use classifications::ClassificationQueries;
+pub use exports::ExportQueries;
+pub use findings::FindingQueries;
+pub use ids::IdPool;
 pub use images::ImageQueries;
+pub use label_files::LabelFileQueries;
 pub use labels::LabelQueries;
+pub use metadata::MetadataQueries;
+pub use scan_errors::ScanErrorQueries;
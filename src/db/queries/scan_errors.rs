@@ -0,0 +1,133 @@
+use crate::db::models::ScanError;
+use crate::db::queries::ids::assign_ids;
+use crate::errors::DatalintResult;
+use duckdb::{params, Connection};
+
+pub struct ScanErrorQueries;
+
+impl ScanErrorQueries {
+    const INSERT: &'static str = r#"
+        INSERT INTO scan_errors (path, stage, kind, message, created_at)
+        VALUES (?, ?, ?, ?, ?)
+        RETURNING id
+    "#;
+
+    const DELETE_ALL: &'static str = r#"
+        DELETE FROM scan_errors
+    "#;
+
+    const DELETE_SCAN_STAGES: &'static str = r#"
+        DELETE FROM scan_errors WHERE stage <> 'annotation'
+    "#;
+
+    const DELETE_ANNOTATION_BY_PATH: &'static str = r#"
+        DELETE FROM scan_errors WHERE stage = 'annotation' AND path = ?
+    "#;
+
+    const SELECT_ALL: &'static str = r#"
+        SELECT id, path, stage, kind, message, created_at
+        FROM scan_errors ORDER BY id
+    "#;
+
+    const SELECT_BY_STAGE: &'static str = r#"
+        SELECT id, path, stage, kind, message, created_at
+        FROM scan_errors WHERE stage = ? ORDER BY id
+    "#;
+
+    /// Record a scan failure
+    pub fn insert(conn: &Connection, error: &ScanError) -> DatalintResult<i64> {
+        conn.query_row(
+            Self::INSERT,
+            params![
+                error.path,
+                error.stage,
+                error.kind,
+                error.message,
+                error.created_at
+            ],
+            |row| row.get(0),
+        )
+        .map_err(Into::into)
+    }
+
+    /// Append scan failures through the bulk appender
+    pub fn append(conn: &Connection, errors: &mut [ScanError]) -> DatalintResult<()> {
+        assign_ids(
+            conn,
+            "scan_errors_id_seq",
+            errors.iter_mut().map(|e| &mut e.id),
+        )?;
+        let mut appender = conn.appender("scan_errors")?;
+
+        for error in errors.iter() {
+            appender.append_row(params![
+                error.id,
+                error.path,
+                error.stage,
+                error.kind,
+                error.message,
+                error.created_at
+            ])?;
+        }
+
+        appender.flush().map_err(Into::into)
+    }
+
+    /// Forget the failures of the previous scan
+    pub fn delete_all(conn: &Connection) -> DatalintResult<usize> {
+        conn.execute(Self::DELETE_ALL, params![])
+            .map_err(Into::into)
+    }
+
+    /// Forget the walk, read and insert failures of the previous scan,
+    /// keeping the annotation errors
+    pub fn delete_scan_stages(conn: &Connection) -> DatalintResult<usize> {
+        conn.execute(Self::DELETE_SCAN_STAGES, params![])
+            .map_err(Into::into)
+    }
+
+    /// Forget the annotation errors of files about to be read again
+    pub fn delete_annotation_errors(conn: &Connection, paths: &[String]) -> DatalintResult<()> {
+        let mut stmt = conn.prepare(Self::DELETE_ANNOTATION_BY_PATH)?;
+        for path in paths {
+            stmt.execute(params![path])?;
+        }
+        Ok(())
+    }
+
+    /// Get all recorded failures
+    pub fn get_all(conn: &Connection) -> DatalintResult<Vec<ScanError>> {
+        Self::select(conn, Self::SELECT_ALL, params![])
+    }
+
+    /// Get the failures recorded at one stage
+    pub fn get_by_stage(conn: &Connection, stage: &str) -> DatalintResult<Vec<ScanError>> {
+        Self::select(conn, Self::SELECT_BY_STAGE, params![stage])
+    }
+
+    /// Run a select over the full scan error column list
+    fn select(
+        conn: &Connection,
+        sql: &str,
+        params: &[&dyn duckdb::ToSql],
+    ) -> DatalintResult<Vec<ScanError>> {
+        let mut stmt = conn.prepare(sql)?;
+
+        let results = stmt.query_map(params, |row| {
+            Ok(ScanError {
+                id: Some(row.get(0)?),
+                path: row.get(1)?,
+                stage: row.get(2)?,
+                kind: row.get(3)?,
+                message: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        })?;
+
+        let mut vec = Vec::new();
+        for result in results {
+            vec.push(result?);
+        }
+        Ok(vec)
+    }
+}
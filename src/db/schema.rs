@@ -1,17 +1,117 @@
-/// Database schema definitions for DuckDB
-pub const SCHEMA: &str = include_str!("../../migrations/001_initial.sql");
+/// A numbered schema change, applied once in version order
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+/// Database schema definitions for DuckDB, applied in order
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial",
+        sql: include_str!("../../migrations/001_initial.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "label_supercategory",
+        sql: include_str!("../../migrations/002_label_supercategory.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "masks",
+        sql: include_str!("../../migrations/003_masks.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "bbox_attributes",
+        sql: include_str!("../../migrations/004_bbox_attributes.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "image_mtime",
+        sql: include_str!("../../migrations/005_image_mtime.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "findings",
+        sql: include_str!("../../migrations/006_findings.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "bbox_degenerate",
+        sql: include_str!("../../migrations/007_bbox_degenerate.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "image_phash",
+        sql: include_str!("../../migrations/008_image_phash.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "label_files",
+        sql: include_str!("../../migrations/009_label_files.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "scan_errors",
+        sql: include_str!("../../migrations/010_scan_errors.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "image_decoded",
+        sql: include_str!("../../migrations/011_image_decoded.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "annotation_errors",
+        sql: include_str!("../../migrations/012_annotation_errors.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "incremental_refresh",
+        sql: include_str!("../../migrations/013_incremental_refresh.sql"),
+    },
+    Migration {
+        version: 14,
+        name: "image_path",
+        sql: include_str!("../../migrations/014_image_path.sql"),
+    },
+];
+
+/// Schema version written by this release
+pub const SCHEMA_VERSION: u32 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+/// Applied migrations, one row per version
+pub const CREATE_SCHEMA_VERSION: &str = r#"
+    CREATE TABLE IF NOT EXISTS schema_version (
+        version INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        applied_at TEXT NOT NULL
+    );
+"#;
 
 /// Drop all tables (useful for testing/resetting)
 pub const DROP_TABLES: &str = r#"
+    DROP TABLE IF EXISTS findings;
+    DROP TABLE IF EXISTS label_files;
+    DROP TABLE IF EXISTS scan_errors;
     DROP TABLE IF EXISTS classifications;
     DROP TABLE IF EXISTS keypoints;
+    DROP TABLE IF EXISTS masks;
     DROP TABLE IF EXISTS segmentations;
     DROP TABLE IF EXISTS bboxes;
     DROP TABLE IF EXISTS images;
     DROP TABLE IF EXISTS labels;
     DROP TABLE IF EXISTS cache_metadata;
+    DROP TABLE IF EXISTS schema_version;
+    DROP SEQUENCE IF EXISTS findings_id_seq;
+    DROP SEQUENCE IF EXISTS label_files_id_seq;
+    DROP SEQUENCE IF EXISTS scan_errors_id_seq;
     DROP SEQUENCE IF EXISTS classifications_id_seq;
     DROP SEQUENCE IF EXISTS keypoints_id_seq;
+    DROP SEQUENCE IF EXISTS masks_id_seq;
     DROP SEQUENCE IF EXISTS segmentations_id_seq;
     DROP SEQUENCE IF EXISTS bboxes_id_seq;
     DROP SEQUENCE IF EXISTS images_id_seq;
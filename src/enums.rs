@@ -1,4 +1,5 @@
 use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// Dataset task types for computer vision
@@ -23,7 +24,7 @@ pub enum DatasetTask {
 impl DatasetTask {
     #[new]
     fn new(value: &str) -> PyResult<Self> {
-        Self::from_str(value).map_err(|e| pyo3::exceptions::PyValueError::new_err(e))
+        Self::from_str(value).map_err(pyo3::exceptions::PyValueError::new_err)
     }
 
     /// Return the string value of the task
@@ -57,6 +58,7 @@ impl DatasetTask {
 
 impl DatasetTask {
     /// Parse a DatasetTask from a string
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(value: &str) -> Result<Self, String> {
         match value {
             "detect" => Ok(DatasetTask::ObjectDetection),
@@ -124,7 +126,7 @@ pub enum DatasetType {
 impl DatasetType {
     #[new]
     fn new(value: &str) -> PyResult<Self> {
-        Self::from_str(value).map_err(|e| pyo3::exceptions::PyValueError::new_err(e))
+        Self::from_str(value).map_err(pyo3::exceptions::PyValueError::new_err)
     }
 
     /// Return the string value of the type
@@ -166,6 +168,7 @@ impl DatasetType {
 
 impl DatasetType {
     /// Parse a DatasetType from a string
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(value: &str) -> Result<Self, String> {
         match value {
             "coco_classic" => Ok(DatasetType::CocoClassic),
@@ -211,3 +214,83 @@ impl fmt::Display for DatasetType {
         write!(f, "{}", self.as_str())
     }
 }
+
+/// Severity of a lint finding
+#[pyclass(eq, eq_int)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[pyo3(name = "INFO")]
+    Info,
+    #[pyo3(name = "WARNING")]
+    Warning,
+    #[pyo3(name = "ERROR")]
+    Error,
+}
+
+#[pymethods]
+impl Severity {
+    #[new]
+    fn new(value: &str) -> PyResult<Self> {
+        Self::from_str(value).map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// Return the string value of the severity
+    fn __str__(&self) -> &str {
+        self.as_str()
+    }
+
+    /// Return the string representation
+    fn __repr__(&self) -> String {
+        format!("Severity.{}", self.python_name())
+    }
+
+    /// Get the value as string
+    #[getter]
+    fn value(&self) -> &str {
+        self.as_str()
+    }
+
+    /// Get all severity values as a list
+    #[staticmethod]
+    fn as_list() -> Vec<&'static str> {
+        vec!["info", "warning", "error"]
+    }
+}
+
+impl Severity {
+    /// Parse a Severity from a string
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(value: &str) -> Result<Self, String> {
+        match value {
+            "info" => Ok(Severity::Info),
+            "warning" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            _ => Err(format!("Invalid Severity value: {}", value)),
+        }
+    }
+
+    /// Get string representation matching Python's value
+    pub fn as_str(&self) -> &str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+
+    /// Get Python enum member name
+    fn python_name(&self) -> &str {
+        match self {
+            Severity::Info => "INFO",
+            Severity::Warning => "WARNING",
+            Severity::Error => "ERROR",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
@@ -9,6 +9,9 @@ pub enum DatalintError {
     Database(String),
     Core(String),
     Generic(String),
+    /// Raised by Python code called back during an operation, including
+    /// `KeyboardInterrupt` from Ctrl-C
+    Python(PyErr),
 }
 
 impl fmt::Display for DatalintError {
@@ -18,6 +21,7 @@ impl fmt::Display for DatalintError {
             Self::Database(err) => write!(f, "Database error: {}", err),
             Self::Core(msg) => write!(f, "Core error: {}", msg),
             Self::Generic(msg) => write!(f, "{}", msg),
+            Self::Python(err) => write!(f, "{}", err),
         }
     }
 }
@@ -36,7 +40,10 @@ impl From<duckdb::Error> for DatalintError {
 
 impl From<DatalintError> for PyErr {
     fn from(err: DatalintError) -> PyErr {
-        PyRuntimeError::new_err(err.to_string())
+        match err {
+            DatalintError::Python(err) => err,
+            err => PyRuntimeError::new_err(err.to_string()),
+        }
     }
 }
 
@@ -0,0 +1,166 @@
+use super::{AnnotationWriter, ImportScope, ImportStats, PARSE_CHUNK};
+use crate::db::models::{Classification, Image};
+use crate::db::queries::{AnnotationQueries, ImageQueries, LabelQueries, ScanErrorQueries};
+use crate::errors::DatalintResult;
+use crate::progress::{ProgressSink, Stage};
+use duckdb::Connection;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Component, Path};
+
+/// Top-level directory names mapped to splits
+const SPLIT_DIRS: &[(&str, &str)] = &[
+    ("train", "train"),
+    ("val", "val"),
+    ("valid", "val"),
+    ("validation", "val"),
+    ("test", "test"),
+];
+
+/// Split declared by the first directory of a relative path, if any
+pub fn split_for_dir(relative_path: &Path) -> Option<&'static str> {
+    let first = match relative_path.components().next()? {
+        Component::Normal(first) => first.to_str()?.to_lowercase(),
+        _ => return None,
+    };
+    SPLIT_DIRS
+        .iter()
+        .find(|(dir, _)| *dir == first)
+        .map(|(_, split)| *split)
+}
+
+/// Whether the top-level directories are split directories: only when every
+/// one of them is named after a split, so that a class called `test` next to
+/// other classes stays a class
+pub fn has_split_dirs<'a>(relative_paths: impl IntoIterator<Item = &'a str>) -> bool {
+    let mut any = false;
+    for relative_path in relative_paths {
+        let relative_path = Path::new(relative_path);
+        if relative_path.components().next().is_none() {
+            continue;
+        }
+        if split_for_dir(relative_path).is_none() {
+            return false;
+        }
+        any = true;
+    }
+    any
+}
+
+/// Class directory of an image: its parent directory, unless the image sits
+/// directly in the root or, with `split_dirs`, in a split directory
+pub fn class_for_dir(relative_path: &Path, split_dirs: bool) -> Option<String> {
+    match relative_path.components().count() {
+        0 => None,
+        1 if split_dirs && split_for_dir(relative_path).is_some() => None,
+        _ => relative_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string()),
+    }
+}
+
+/// Import a folder-per-class dataset (`split/class_name/image.jpg` or
+/// `class_name/image.jpg`): the directory holding each image is its label.
+/// Top-level directories are splits only when all of them are split names.
+/// Labels and splits are derived from every image, classifications are
+/// written for the images in `scope`.
+pub fn import_dataset(
+    conn: &Connection,
+    scope: ImportScope,
+    progress: &dyn ProgressSink,
+) -> DatalintResult<ImportStats> {
+    let images = ImageQueries::get_all(conn)?;
+    let has_splits = has_split_dirs(images.iter().map(|image| image.relative_path.as_str()));
+    let classes: Vec<Option<String>> = images
+        .iter()
+        .map(|image| class_for_dir(Path::new(&image.relative_path), has_splits))
+        .collect();
+
+    let mut stats = ImportStats::default();
+    let mut writer = AnnotationWriter::new();
+
+    // Labels are created in sorted class order, like Ultralytics class indices
+    let class_names: BTreeSet<&String> = classes.iter().flatten().collect();
+    let mut label_ids: HashMap<&str, i32> = HashMap::new();
+    for name in class_names {
+        label_ids.insert(name, LabelQueries::get_or_create(conn, name, None)?);
+    }
+
+    // The top-level layout decides the splits, replacing the path-based guess
+    // from the scanner that would take a `test` class for a split
+    ImageQueries::reset_splits(conn)?;
+    if has_splits {
+        let split_dirs: BTreeMap<&str, &str> = images
+            .iter()
+            .filter_map(|image| {
+                let relative_path = Path::new(&image.relative_path);
+                let split = split_for_dir(relative_path)?;
+                match relative_path.components().next()? {
+                    Component::Normal(dir) => Some((dir.to_str()?, split)),
+                    _ => None,
+                }
+            })
+            .collect();
+        for (dir, split) in split_dirs {
+            ImageQueries::set_split_by_dir(conn, dir, split)?;
+        }
+    }
+
+    // Images right inside a split-named directory change class with the
+    // layout, so they are written again whatever the scope
+    let targets: Vec<(&Image, &Option<String>)> = images
+        .iter()
+        .zip(&classes)
+        .filter(|(image, _)| {
+            let relative_path = Path::new(&image.relative_path);
+            scope.contains(image.id)
+                || (relative_path.components().count() == 1
+                    && split_for_dir(relative_path).is_some())
+        })
+        .collect();
+    if let ImportScope::Changed { .. } = scope {
+        let image_ids: Vec<i32> = targets.iter().filter_map(|(image, _)| image.id).collect();
+        let sources: Vec<String> = targets.iter().map(|(image, _)| source(image)).collect();
+        AnnotationQueries::delete_by_images(conn, &image_ids)?;
+        ScanErrorQueries::delete_annotation_errors(conn, &sources)?;
+    }
+
+    for (i, (image, class_name)) in targets.iter().enumerate() {
+        if i % PARSE_CHUNK == 0 {
+            progress.update(Stage::Annotations, i, Some(targets.len()))?;
+        }
+        let (Some(image_id), Some(class_name)) = (image.id, class_name) else {
+            stats.push_error(
+                &source(image),
+                "no_class",
+                "image is not inside a class directory",
+            );
+            continue;
+        };
+
+        let classification = Classification {
+            id: None,
+            image_id,
+            label_id: label_ids[class_name.as_str()],
+            confidence: None,
+        };
+        writer.push_classification(conn, classification)?;
+        stats.annotations += 1;
+    }
+
+    writer.finish(conn)?;
+    if let ImportScope::Changed { .. } = scope {
+        // Classes whose last image went away
+        LabelQueries::delete_unused(conn)?;
+    }
+    progress.update(Stage::Annotations, targets.len(), Some(targets.len()))?;
+
+    Ok(stats)
+}
+
+/// Path of an image relative to the dataset root, as its errors are logged
+fn source(image: &Image) -> String {
+    Path::new(&image.relative_path)
+        .join(&image.filename)
+        .display()
+        .to_string()
+}
@@ -0,0 +1,514 @@
+use super::{AnnotationWriter, ImageIndex, ImportScope, ImportStats, PARSE_CHUNK};
+use crate::db::models::{Bbox, Image, Keypoint, Label, Mask, Point, Segmentation};
+use crate::db::queries::{
+    AnnotationQueries, ImageQueries, LabelQueries, MetadataQueries, ScanErrorQueries,
+};
+use crate::errors::{DatalintError, DatalintResult};
+use crate::progress::{ProgressSink, Stage};
+use crate::rle::Rle;
+use duckdb::Connection;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Roboflow-style COCO export file name, one per split directory
+const ROBOFLOW_ANNOTATION_FILE: &str = "_annotations.coco.json";
+
+/// Top-level COCO annotation file
+#[derive(Debug, Clone, Deserialize)]
+pub struct CocoFile {
+    #[serde(default)]
+    pub images: Vec<CocoImage>,
+    #[serde(default)]
+    pub annotations: Vec<CocoAnnotation>,
+    #[serde(default)]
+    pub categories: Vec<CocoCategory>,
+}
+
+impl CocoFile {
+    /// Load and parse a COCO annotation file
+    pub fn load(path: &Path) -> DatalintResult<Self> {
+        let data = fs::read(path)?;
+        serde_json::from_slice(&data)
+            .map_err(|e| DatalintError::Core(format!("Invalid {}: {}", path.display(), e)))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CocoImage {
+    pub id: i64,
+    pub file_name: String,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CocoCategory {
+    pub id: i64,
+    pub name: String,
+    pub supercategory: Option<String>,
+    #[serde(default)]
+    pub keypoints: Vec<String>,
+    #[serde(default)]
+    pub skeleton: Vec<[usize; 2]>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CocoAnnotation {
+    #[serde(default)]
+    pub id: i64,
+    pub image_id: i64,
+    pub category_id: i64,
+    pub bbox: Option<[f64; 4]>,
+    pub segmentation: Option<CocoSegmentation>,
+    pub keypoints: Option<Vec<f64>>,
+    #[serde(default)]
+    pub iscrowd: u8,
+}
+
+/// Polygon list or run-length encoded mask
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum CocoSegmentation {
+    Polygons(Vec<Vec<f64>>),
+    Rle(CocoRle),
+}
+
+/// COCO RLE object, `size` is [height, width]
+#[derive(Debug, Clone, Deserialize)]
+pub struct CocoRle {
+    pub counts: CocoRleCounts,
+    pub size: [u32; 2],
+}
+
+/// Compressed string counts (crowd annotations in instances files) or raw run lengths
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum CocoRleCounts {
+    Compressed(String),
+    Uncompressed(Vec<u64>),
+}
+
+impl CocoRle {
+    /// Decode into an RLE mask
+    pub fn to_rle(&self) -> DatalintResult<Rle> {
+        let [height, width] = self.size;
+        match &self.counts {
+            CocoRleCounts::Compressed(counts) => Rle::from_compressed(counts, height, width),
+            CocoRleCounts::Uncompressed(counts) => {
+                let rle = Rle {
+                    height,
+                    width,
+                    counts: counts.clone(),
+                };
+                rle.validate()?;
+                Ok(rle)
+            }
+        }
+    }
+}
+
+impl CocoAnnotation {
+    /// Pixel corners (x1, y1, x2, y2) from `bbox`, or the segmentation extent when missing
+    pub fn corners(&self) -> Option<(f64, f64, f64, f64)> {
+        if let Some([x, y, w, h]) = self.bbox {
+            return Some((x, y, x + w, y + h));
+        }
+
+        let polygons = match &self.segmentation {
+            Some(CocoSegmentation::Polygons(polygons)) => polygons,
+            Some(CocoSegmentation::Rle(rle)) => {
+                let (x, y, w, h) = rle.to_rle().ok()?.bbox()?;
+                return Some((x, y, x + w, y + h));
+            }
+            None => return None,
+        };
+        let mut points = polygons
+            .iter()
+            .flat_map(|polygon| polygon.chunks_exact(2).map(|xy| (xy[0], xy[1])));
+        let (x, y) = points.next()?;
+        Some(points.fold((x, y, x, y), |(x1, y1, x2, y2), (x, y)| {
+            (x1.min(x), y1.min(y), x2.max(x), y2.max(y))
+        }))
+    }
+}
+
+/// Find COCO annotation files: `annotations/*.json` and Roboflow `_annotations.coco.json`
+pub fn find_annotation_files(dataset_root: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = WalkDir::new(dataset_root)
+        .max_depth(3)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|path| {
+            let is_json = path
+                .extension()
+                .map(|ext| ext.eq_ignore_ascii_case("json"))
+                .unwrap_or(false);
+            let in_annotations_dir = path
+                .parent()
+                .and_then(|p| p.file_name())
+                .map(|name| name.eq_ignore_ascii_case("annotations"))
+                .unwrap_or(false);
+            let is_roboflow = path
+                .file_name()
+                .map(|name| name == ROBOFLOW_ANNOTATION_FILE)
+                .unwrap_or(false);
+            is_json && (in_annotations_dir || is_roboflow)
+        })
+        .collect();
+
+    files.sort();
+    files
+}
+
+/// Infer the split from the annotation file name (instances_val2017.json)
+/// or its directory (valid/_annotations.coco.json)
+fn split_for_file(path: &Path, dataset_root: &Path) -> Option<&'static str> {
+    let guess = |text: &str| {
+        let text = text.to_lowercase();
+        let matches: Vec<&'static str> = [("train", "train"), ("val", "val"), ("test", "test")]
+            .into_iter()
+            .filter(|(pattern, _)| text.contains(pattern))
+            .map(|(_, split)| split)
+            .collect();
+        match matches.as_slice() {
+            [split] => Some(*split),
+            _ => None,
+        }
+    };
+
+    let stem = path.file_stem()?.to_string_lossy();
+    guess(&stem).or_else(|| {
+        let parent = path.parent()?.strip_prefix(dataset_root).ok()?;
+        guess(&parent.to_string_lossy())
+    })
+}
+
+/// Import every COCO annotation file found in the dataset.
+///
+/// The files describe every image at once, so a refresh reads them all again
+/// (unless no image was added, changed or removed), writes the annotations of
+/// the images in `scope` and logs the problems of the whole files.
+pub fn import_dataset(
+    conn: &Connection,
+    dataset_root: &Path,
+    scope: ImportScope,
+    progress: &dyn ProgressSink,
+) -> DatalintResult<ImportStats> {
+    let mut stats = ImportStats::default();
+    let files = find_annotation_files(dataset_root);
+
+    if let ImportScope::Changed {
+        images,
+        images_removed,
+    } = scope
+    {
+        if images.is_empty() && !images_removed {
+            return Ok(stats);
+        }
+        let image_ids: Vec<i32> = images.iter().copied().collect();
+        AnnotationQueries::delete_by_images(conn, &image_ids)?;
+        let sources: Vec<String> = files
+            .iter()
+            .map(|path| source_for(path, dataset_root))
+            .collect();
+        ScanErrorQueries::delete_annotation_errors(conn, &sources)?;
+    }
+
+    let images = ImageQueries::get_all(conn)?;
+    let index = ImageIndex::new(&images);
+    for path in files {
+        import_file(
+            conn,
+            dataset_root,
+            &path,
+            &index,
+            scope,
+            &mut stats,
+            progress,
+        )?;
+    }
+
+    Ok(stats)
+}
+
+/// Path of an annotation file relative to the dataset root
+fn source_for(path: &Path, dataset_root: &Path) -> String {
+    path.strip_prefix(dataset_root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+/// Import a single COCO annotation file, resolving its images through `index`
+fn import_file(
+    conn: &Connection,
+    dataset_root: &Path,
+    path: &Path,
+    index: &ImageIndex,
+    scope: ImportScope,
+    stats: &mut ImportStats,
+    progress: &dyn ProgressSink,
+) -> DatalintResult<()> {
+    let source = source_for(path, dataset_root);
+
+    let coco = match CocoFile::load(path) {
+        Ok(coco) => coco,
+        Err(e) => {
+            stats.push_error(&source, "unreadable", e);
+            return Ok(());
+        }
+    };
+    stats.label_files += 1;
+
+    store_keypoint_metadata(conn, &coco.categories)?;
+
+    // Images live next to Roboflow exports, or beside the `annotations` directory
+    let mut image_root = path
+        .parent()
+        .and_then(|p| p.strip_prefix(dataset_root).ok())
+        .unwrap_or_else(|| Path::new(""));
+    if image_root
+        .file_name()
+        .map(|name| name.eq_ignore_ascii_case("annotations"))
+        .unwrap_or(false)
+    {
+        image_root = image_root.parent().unwrap_or_else(|| Path::new(""));
+    }
+    let split = split_for_file(path, dataset_root);
+
+    // Map COCO category ids to label ids
+    let mut label_ids: HashMap<i64, i32> = HashMap::new();
+    for category in &coco.categories {
+        let label_id = match LabelQueries::find_by_name(conn, &category.name)? {
+            Some(label) => {
+                let id = label.id.unwrap();
+                if label.supercategory != category.supercategory {
+                    LabelQueries::set_supercategory(conn, id, category.supercategory.as_deref())?;
+                }
+                id
+            }
+            None => {
+                let label = Label {
+                    id: None,
+                    name: category.name.clone(),
+                    color: None,
+                    supercategory: category.supercategory.clone(),
+                };
+                LabelQueries::insert(conn, &label)? as i32
+            }
+        };
+        label_ids.insert(category.id, label_id);
+    }
+
+    // Map COCO image ids to scanned images
+    let mut image_ids: HashMap<i64, &Image> = HashMap::new();
+    let mut declared: HashSet<i64> = HashSet::new();
+    for coco_image in &coco.images {
+        declared.insert(coco_image.id);
+        match index.resolve(&coco_image.file_name, image_root) {
+            Some(image) => {
+                if let Some(split) = split {
+                    ImageQueries::set_split_by_file(
+                        conn,
+                        &image.relative_path,
+                        &image.filename,
+                        split,
+                    )?;
+                }
+                image_ids.insert(coco_image.id, image);
+            }
+            None => stats.push_error(
+                &source,
+                "missing_image",
+                format!("image '{}' not found in dataset", coco_image.file_name),
+            ),
+        }
+    }
+
+    // Annotations of images the file does not declare, reported once per
+    // image id. Declared images that were not found are reported above.
+    let mut missing: BTreeMap<i64, usize> = BTreeMap::new();
+    let mut writer = AnnotationWriter::new();
+
+    // Progress counts the annotations of this file
+    let total = coco.annotations.len();
+    for (i, annotation) in coco.annotations.iter().enumerate() {
+        if i % PARSE_CHUNK == 0 {
+            progress.update(Stage::Annotations, i, Some(total))?;
+        }
+        let Some(image) = image_ids.get(&annotation.image_id) else {
+            if !declared.contains(&annotation.image_id) {
+                *missing.entry(annotation.image_id).or_default() += 1;
+            }
+            continue;
+        };
+        let Some(&label_id) = label_ids.get(&annotation.category_id) else {
+            stats.push_error(
+                &source,
+                "unknown_category",
+                format!(
+                    "annotation {} has unknown category {}",
+                    annotation.id, annotation.category_id
+                ),
+            );
+            continue;
+        };
+        let Some((x1, y1, x2, y2)) = annotation.corners() else {
+            stats.push_error(
+                &source,
+                "invalid",
+                format!("annotation {} has no box", annotation.id),
+            );
+            continue;
+        };
+        // Images outside the scope keep the annotations already cached, their
+        // annotations are only checked for errors
+        let in_scope = scope.contains(image.id);
+        let bbox_id = if in_scope {
+            let bbox = Bbox::new(image.id.unwrap(), label_id, x1, y1, x2, y2);
+            stats.annotations += 1;
+            writer.push_bbox(conn, bbox)?
+        } else {
+            0
+        };
+
+        match &annotation.segmentation {
+            Some(CocoSegmentation::Polygons(polygons)) => {
+                let mut outlines: Vec<Vec<(f64, f64)>> = Vec::new();
+                for polygon in polygons {
+                    if polygon.len() < 6 || polygon.len() % 2 != 0 {
+                        stats.push_error(
+                            &source,
+                            "invalid",
+                            format!(
+                                "annotation {}: polygon of {} values is not a list of \
+                                 at least 3 x, y pairs",
+                                annotation.id,
+                                polygon.len()
+                            ),
+                        );
+                        continue;
+                    }
+                    outlines.push(polygon.chunks_exact(2).map(|xy| (xy[0], xy[1])).collect());
+                }
+                if in_scope {
+                    // Outlines are rasterized when needed, only crowd regions
+                    // keep a mask since it carries their flag
+                    if annotation.iscrowd != 0 {
+                        if let (Some(width), Some(height)) = (image.width, image.height) {
+                            let rle = Rle::from_polygons(&outlines, height as u32, width as u32);
+                            writer.push_mask(Mask::from_rle(bbox_id, &rle, true));
+                        }
+                    }
+                    for vertices in outlines {
+                        let segmentation = Segmentation {
+                            id: None,
+                            bbox_id,
+                            vertex_count: vertices.len() as i32,
+                            vertices,
+                        };
+                        writer.push_segmentation(segmentation);
+                    }
+                }
+            }
+            Some(CocoSegmentation::Rle(coco_rle)) => match coco_rle.to_rle() {
+                Ok(_) if !in_scope => {}
+                Ok(rle) => {
+                    // Crowd regions have no instance outline worth tracing
+                    if annotation.iscrowd == 0 {
+                        for vertices in rle.to_polygons() {
+                            let segmentation = Segmentation {
+                                id: None,
+                                bbox_id,
+                                vertex_count: vertices.len() as i32,
+                                vertices,
+                            };
+                            writer.push_segmentation(segmentation);
+                        }
+                    }
+                    let mask = Mask::from_rle(bbox_id, &rle, annotation.iscrowd != 0);
+                    writer.push_mask(mask);
+                }
+                Err(e) => stats.push_error(
+                    &source,
+                    "invalid",
+                    format!("annotation {}: {}", annotation.id, e),
+                ),
+            },
+            None => {}
+        }
+
+        if let Some(values) = annotation.keypoints.as_ref().filter(|v| !v.is_empty()) {
+            if values.len() % 3 != 0 {
+                stats.push_error(
+                    &source,
+                    "invalid",
+                    format!(
+                        "annotation {}: keypoints length {} is not a multiple of 3",
+                        annotation.id,
+                        values.len()
+                    ),
+                );
+                continue;
+            }
+            if !in_scope {
+                continue;
+            }
+            let points: Vec<Point> = values
+                .chunks_exact(3)
+                .map(|p| Point {
+                    x: p[0],
+                    y: p[1],
+                    visibility: Some(p[2]),
+                })
+                .collect();
+            let keypoint = Keypoint {
+                id: None,
+                bbox_id,
+                point_count: points.len() as i32,
+                points,
+                has_visibility: true,
+            };
+            writer.push_keypoint(keypoint);
+        }
+    }
+
+    writer.finish(conn)?;
+    progress.update(Stage::Annotations, total, Some(total))?;
+
+    for (image_id, count) in missing {
+        stats.push_error(
+            &source,
+            "missing_image",
+            format!("{} annotations reference missing image {}", count, image_id),
+        );
+    }
+
+    Ok(())
+}
+
+/// Store keypoint names and skeleton from the first category declaring them.
+/// COCO skeleton edges are 1-based and stored 0-based like data.yaml.
+fn store_keypoint_metadata(conn: &Connection, categories: &[CocoCategory]) -> DatalintResult<()> {
+    let Some(category) = categories.iter().find(|c| !c.keypoints.is_empty()) else {
+        return Ok(());
+    };
+
+    let skeleton: Vec<[usize; 2]> = category
+        .skeleton
+        .iter()
+        .map(|[a, b]| [a.saturating_sub(1), b.saturating_sub(1)])
+        .collect();
+
+    let names = serde_json::to_string(&category.keypoints)
+        .map_err(|e| DatalintError::Generic(e.to_string()))?;
+    let skeleton =
+        serde_json::to_string(&skeleton).map_err(|e| DatalintError::Generic(e.to_string()))?;
+
+    MetadataQueries::set_keypoints(conn, Some(&names), Some(&skeleton))
+}
@@ -0,0 +1,190 @@
+use crate::errors::{DatalintError, DatalintResult};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Config file names checked before falling back to any yaml file at the root
+const DATA_YAML_NAMES: &[&str] = &["data.yaml", "data.yml", "dataset.yaml", "dataset.yml"];
+
+/// Split entry: a directory, an image list file, or several of them
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum SplitPaths {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl SplitPaths {
+    /// Iterate over the declared paths
+    pub fn paths(&self) -> Vec<&str> {
+        match self {
+            Self::Single(path) => vec![path.as_str()],
+            Self::Multiple(paths) => paths.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+/// Class names: either a list or an `{id: name}` mapping
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ClassNames {
+    List(Vec<String>),
+    Map(BTreeMap<usize, String>),
+}
+
+/// Keypoint names: shared by all classes or declared per class
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum KeypointNames {
+    List(Vec<String>),
+    PerClass(BTreeMap<usize, Vec<String>>),
+}
+
+/// Ultralytics dataset configuration (data.yaml)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DataYaml {
+    pub path: Option<String>,
+    pub train: Option<SplitPaths>,
+    pub val: Option<SplitPaths>,
+    pub test: Option<SplitPaths>,
+    pub nc: Option<usize>,
+    pub names: Option<ClassNames>,
+    pub kpt_shape: Option<Vec<usize>>,
+    pub flip_idx: Option<Vec<usize>>,
+    pub kpt_names: Option<KeypointNames>,
+    pub skeleton: Option<Vec<[usize; 2]>>,
+}
+
+impl DataYaml {
+    /// Locate the dataset config at the root of a dataset
+    pub fn find(dataset_root: &Path) -> Option<PathBuf> {
+        for name in DATA_YAML_NAMES {
+            let path = dataset_root.join(name);
+            if path.is_file() {
+                return Some(path);
+            }
+        }
+
+        // Fall back to a single yaml file at the root (e.g. coco8.yaml), as long
+        // as it reads as a dataset config rather than some unrelated settings file
+        let mut candidates: Vec<PathBuf> = fs::read_dir(dataset_root)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .extension()
+                        .and_then(|ext| ext.to_str())
+                        .map(|ext| {
+                            ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml")
+                        })
+                        .unwrap_or(false)
+            })
+            .collect();
+
+        match candidates.len() {
+            1 => candidates
+                .pop()
+                .filter(|path| Self::load(path).is_ok_and(|config| config.is_dataset_config())),
+            _ => None,
+        }
+    }
+
+    /// Whether the file declares classes or split paths. Every field is
+    /// optional, so any yaml mapping would otherwise parse as a config.
+    pub fn is_dataset_config(&self) -> bool {
+        self.names.is_some() || self.train.is_some() || self.val.is_some()
+    }
+
+    /// Load and parse a data.yaml file
+    pub fn load(path: &Path) -> DatalintResult<Self> {
+        let content = fs::read_to_string(path)?;
+        serde_yaml::from_str(&content)
+            .map_err(|e| DatalintError::Core(format!("Invalid {}: {}", path.display(), e)))
+    }
+
+    /// Class names ordered by class id
+    pub fn class_names(&self) -> Vec<(usize, String)> {
+        match &self.names {
+            Some(ClassNames::List(names)) => names.iter().cloned().enumerate().collect(),
+            Some(ClassNames::Map(names)) => names.iter().map(|(id, n)| (*id, n.clone())).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Declared split entries as (split, paths)
+    pub fn splits(&self) -> Vec<(&'static str, Vec<&str>)> {
+        [
+            ("train", &self.train),
+            ("val", &self.val),
+            ("test", &self.test),
+        ]
+        .into_iter()
+        .filter_map(|(split, paths)| paths.as_ref().map(|p| (split, p.paths())))
+        .collect()
+    }
+
+    /// Image list files among the declared splits, relative to the dataset root
+    pub fn split_lists(&self, dataset_root: &Path) -> Vec<PathBuf> {
+        self.splits()
+            .into_iter()
+            .flat_map(|(_, entries)| entries)
+            .filter_map(|entry| self.relative_to_root(entry, dataset_root))
+            .filter(|path| is_list_file(path))
+            .collect()
+    }
+
+    /// Number of keypoints and values per keypoint (2 or 3) from `kpt_shape`
+    pub fn keypoint_shape(&self) -> Option<(usize, usize)> {
+        match self.kpt_shape.as_deref() {
+            Some([count, dims]) => Some((*count, *dims)),
+            _ => None,
+        }
+    }
+
+    /// Keypoint names, falling back to their index when only `kpt_shape` is given
+    pub fn keypoint_names(&self) -> Option<Vec<String>> {
+        match &self.kpt_names {
+            Some(KeypointNames::List(names)) => Some(names.clone()),
+            Some(KeypointNames::PerClass(names)) => names.values().next().cloned(),
+            None => self
+                .keypoint_shape()
+                .map(|(count, _)| (0..count).map(|i| i.to_string()).collect()),
+        }
+    }
+
+    /// Resolve a declared split path to a path relative to the dataset root.
+    ///
+    /// Paths are usually relative to the yaml file, but exports often use
+    /// `../train/images` style entries, so `.` and `..` components are dropped.
+    /// Returns None for absolute paths outside the dataset.
+    pub fn relative_to_root(&self, entry: &str, dataset_root: &Path) -> Option<PathBuf> {
+        let mut path = PathBuf::from(entry);
+        if path.is_relative() {
+            if let Some(base) = self.path.as_deref().map(Path::new) {
+                if base.is_absolute() {
+                    path = base.join(path);
+                }
+            }
+        }
+
+        if path.is_absolute() {
+            return path.strip_prefix(dataset_root).ok().map(Path::to_path_buf);
+        }
+
+        Some(
+            path.components()
+                .filter(|c| matches!(c, Component::Normal(_)))
+                .collect(),
+        )
+    }
+}
+
+/// Whether a split entry names an image list file rather than a directory
+pub fn is_list_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.eq_ignore_ascii_case("txt"))
+        .unwrap_or(false)
+}
@@ -0,0 +1,365 @@
+//! Dataset format detection for folders of unknown layout
+use super::cls::split_for_dir;
+use super::coco::find_annotation_files;
+use super::data_yaml::DataYaml;
+use super::voc::find_annotation_dirs;
+use crate::enums::DatasetType;
+use crate::scanner::is_image_file;
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Directory depth walked when sampling the dataset tree
+const SAMPLE_DEPTH: usize = 4;
+
+/// Files examined before the sample is considered representative
+const MAX_SAMPLED_FILES: usize = 20_000;
+
+/// Label files whose first row is checked against the YOLO layout
+const MAX_INSPECTED_LABELS: usize = 20;
+
+/// Bytes of an annotation json read when looking for COCO keys
+const JSON_PEEK_BYTES: u64 = 1 << 20;
+
+/// Lowest confidence at which a guess replaces `DatasetType::Unknown`
+pub const MIN_CONFIDENCE: f64 = 0.5;
+
+/// Directory names that hold images but never name a class
+const NON_CLASS_DIRS: &[&str] = &["images", "jpegimages", "labels", "annotations"];
+
+/// A candidate format with its confidence and the evidence behind it
+#[pyclass(frozen, get_all)]
+#[derive(Debug, Clone)]
+pub struct DetectedFormat {
+    pub dataset_type: DatasetType,
+    pub confidence: f64,
+    pub evidence: Vec<String>,
+}
+
+#[pymethods]
+impl DetectedFormat {
+    fn __repr__(&self) -> String {
+        format!(
+            "DetectedFormat(dataset_type={}, confidence={:.2})",
+            self.dataset_type.as_str(),
+            self.confidence
+        )
+    }
+}
+
+/// Scores one format from the signals found in the tree
+#[derive(Default)]
+struct Score {
+    confidence: f64,
+    evidence: Vec<String>,
+}
+
+impl Score {
+    fn add(&mut self, weight: f64, evidence: String) {
+        self.confidence += weight;
+        self.evidence.push(evidence);
+    }
+
+    fn into_format(self, dataset_type: DatasetType) -> Option<DetectedFormat> {
+        (self.confidence > 0.0).then(|| DetectedFormat {
+            dataset_type,
+            confidence: self.confidence.clamp(0.0, 1.0),
+            evidence: self.evidence,
+        })
+    }
+}
+
+/// Files found in a bounded walk of the dataset tree
+#[derive(Default)]
+struct TreeSample {
+    /// Image count per directory, relative to the root
+    image_dirs: HashMap<PathBuf, usize>,
+    images: usize,
+    label_files: Vec<PathBuf>,
+}
+
+impl TreeSample {
+    fn collect(dataset_root: &Path) -> Self {
+        let mut sample = Self::default();
+
+        let files = WalkDir::new(dataset_root)
+            .max_depth(SAMPLE_DEPTH)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .take(MAX_SAMPLED_FILES);
+
+        for entry in files {
+            let path = entry.path();
+            let relative = path.strip_prefix(dataset_root).unwrap_or(path);
+
+            if is_image_file(path) {
+                let dir = relative.parent().unwrap_or(Path::new("")).to_path_buf();
+                *sample.image_dirs.entry(dir).or_default() += 1;
+                sample.images += 1;
+            } else if is_label_file(relative) {
+                sample.label_files.push(path.to_path_buf());
+            }
+        }
+
+        sample.label_files.sort();
+        sample
+    }
+}
+
+/// `.txt` files below a `labels` directory
+fn is_label_file(relative_path: &Path) -> bool {
+    let is_txt = relative_path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("txt"))
+        .unwrap_or(false);
+    is_txt
+        && relative_path
+            .components()
+            .any(|c| matches!(c, Component::Normal(name) if name == "labels"))
+}
+
+/// Whether the first row of a label file reads as `class x y ...` with
+/// normalized coordinates
+fn looks_like_yolo_rows(path: &Path) -> bool {
+    let Ok(content) = fs::read_to_string(path) else {
+        return false;
+    };
+    let Some(line) = content.lines().map(str::trim).find(|l| !l.is_empty()) else {
+        return false;
+    };
+
+    let mut tokens = line.split_whitespace();
+    let class_ok = tokens.next().map(|t| t.parse::<usize>().is_ok()) == Some(true);
+    let values: Option<Vec<f64>> = tokens.map(|t| t.parse::<f64>().ok()).collect();
+
+    match values {
+        Some(values) if class_ok && values.len() >= 4 => {
+            values[..4].iter().all(|v| (0.0..=1.0).contains(v))
+        }
+        _ => false,
+    }
+}
+
+/// Whether the start of a json file carries the COCO top-level keys
+fn looks_like_coco_json(path: &Path) -> bool {
+    let mut head = String::new();
+    let read = File::open(path)
+        .and_then(|file| file.take(JSON_PEEK_BYTES).read_to_string(&mut head))
+        .is_ok();
+    read && head.contains("\"images\"") && head.contains("\"annotations\"")
+}
+
+fn detect_yolo(dataset_root: &Path, sample: &TreeSample) -> Option<DetectedFormat> {
+    let mut score = Score::default();
+
+    if let Some(path) = DataYaml::find(dataset_root) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        match DataYaml::load(&path) {
+            Ok(config) if config.is_dataset_config() => {
+                score.add(0.4, format!("found dataset config '{}'", name))
+            }
+            // A data.yaml without names or splits says nothing about the layout
+            Ok(_) => {}
+            Err(_) => score.add(0.1, format!("found unreadable yaml '{}'", name)),
+        }
+    }
+
+    if !sample.label_files.is_empty() {
+        score.add(
+            0.3,
+            format!(
+                "found {} .txt files under labels/",
+                sample.label_files.len()
+            ),
+        );
+
+        let inspected = sample.label_files.len().min(MAX_INSPECTED_LABELS);
+        let matching = sample.label_files[..inspected]
+            .iter()
+            .filter(|path| looks_like_yolo_rows(path))
+            .count();
+        if matching > 0 {
+            score.add(
+                0.3 * matching as f64 / inspected as f64,
+                format!(
+                    "{}/{} inspected label files hold normalized 'class x y ...' rows",
+                    matching, inspected
+                ),
+            );
+        }
+    }
+
+    score.into_format(DatasetType::Yolo)
+}
+
+fn detect_coco(dataset_root: &Path) -> Vec<DetectedFormat> {
+    // Roboflow exports ship one `_annotations.coco.json` per split folder,
+    // the original layout keeps `annotations/*.json` beside the image folders
+    let (roboflow, classic): (Vec<PathBuf>, Vec<PathBuf>) = find_annotation_files(dataset_root)
+        .into_iter()
+        .partition(|path| {
+            path.parent()
+                .and_then(|p| p.file_name())
+                .map(|name| !name.eq_ignore_ascii_case("annotations"))
+                .unwrap_or(true)
+        });
+
+    [
+        (DatasetType::Coco, roboflow),
+        (DatasetType::CocoClassic, classic),
+    ]
+    .into_iter()
+    .filter_map(|(dataset_type, files)| {
+        let mut score = Score::default();
+        if files.is_empty() {
+            return None;
+        }
+        let shown: Vec<String> = files
+            .iter()
+            .take(3)
+            .map(|p| {
+                p.strip_prefix(dataset_root)
+                    .unwrap_or(p)
+                    .display()
+                    .to_string()
+            })
+            .collect();
+        score.add(
+            0.5,
+            format!(
+                "found {} annotation json files ({})",
+                files.len(),
+                shown.join(", ")
+            ),
+        );
+
+        let valid = files.iter().filter(|p| looks_like_coco_json(p)).count();
+        if valid > 0 {
+            score.add(
+                0.45,
+                format!("{} json files declare 'images' and 'annotations'", valid),
+            );
+        }
+        score.into_format(dataset_type)
+    })
+    .collect()
+}
+
+fn detect_voc(dataset_root: &Path) -> Option<DetectedFormat> {
+    let mut score = Score::default();
+
+    let dirs = find_annotation_dirs(dataset_root);
+    let xml_count: usize = dirs
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(|entry| entry.ok()))
+        .filter(|entry| {
+            entry
+                .path()
+                .extension()
+                .map(|ext| ext.eq_ignore_ascii_case("xml"))
+                .unwrap_or(false)
+        })
+        .count();
+
+    if xml_count == 0 {
+        return None;
+    }
+    score.add(
+        0.6,
+        format!("found {} xml files in Annotations/", xml_count),
+    );
+
+    let bases: Vec<&Path> = dirs.iter().filter_map(|dir| dir.parent()).collect();
+    if bases.iter().any(|base| base.join("JPEGImages").is_dir()) {
+        score.add(0.25, "found JPEGImages/ beside Annotations/".to_string());
+    }
+    if bases
+        .iter()
+        .any(|base| base.join("ImageSets").join("Main").is_dir())
+    {
+        score.add(0.15, "found ImageSets/Main split lists".to_string());
+    }
+
+    score.into_format(DatasetType::Voc)
+}
+
+fn detect_cls(sample: &TreeSample, annotated: bool) -> Option<DetectedFormat> {
+    let mut score = Score::default();
+
+    // A class directory is the last component of a directory holding images,
+    // skipping split folders and the generic image folders of other formats
+    let mut classes: HashMap<String, usize> = HashMap::new();
+    let mut in_split_dirs = 0;
+    for (dir, count) in &sample.image_dirs {
+        let Some(name) = dir.file_name().map(|n| n.to_string_lossy().to_string()) else {
+            continue;
+        };
+        if dir.components().count() == 1 && split_for_dir(dir).is_some() {
+            continue;
+        }
+        if dir.components().any(|c| {
+            NON_CLASS_DIRS.contains(&c.as_os_str().to_string_lossy().to_lowercase().as_str())
+        }) {
+            continue;
+        }
+        if split_for_dir(dir).is_some() {
+            in_split_dirs += count;
+        }
+        *classes.entry(name).or_default() += count;
+    }
+
+    let classified: usize = classes.values().sum();
+    if classes.len() < 2 || sample.images == 0 {
+        return None;
+    }
+
+    let coverage = classified as f64 / sample.images as f64;
+    score.add(
+        0.6 * coverage,
+        format!(
+            "{} of {} images sit in {} class folders",
+            classified,
+            sample.images,
+            classes.len()
+        ),
+    );
+    if in_split_dirs > 0 {
+        score.add(
+            0.2,
+            format!("{} images in split/class folders", in_split_dirs),
+        );
+    }
+    if !annotated {
+        score.add(0.1, "no annotation files found".to_string());
+    }
+
+    score.into_format(DatasetType::Cls)
+}
+
+/// Inspect a dataset folder and rank the formats it may be in, most likely
+/// first. An empty result means no format left any trace.
+pub fn detect_dataset_format(dataset_root: &Path) -> Vec<DetectedFormat> {
+    let sample = TreeSample::collect(dataset_root);
+
+    let mut formats: Vec<DetectedFormat> = detect_coco(dataset_root);
+    formats.extend(detect_yolo(dataset_root, &sample));
+    formats.extend(detect_voc(dataset_root));
+    let annotated = !formats.is_empty();
+    formats.extend(detect_cls(&sample, annotated));
+
+    formats.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    formats
+}
+
+/// The most likely format, if any guess reaches `MIN_CONFIDENCE`
+pub fn best_guess(dataset_root: &Path) -> Option<DetectedFormat> {
+    detect_dataset_format(dataset_root)
+        .into_iter()
+        .next()
+        .filter(|format| format.confidence >= MIN_CONFIDENCE)
+}
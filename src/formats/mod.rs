@@ -0,0 +1,271 @@
+//! Annotation readers for the supported dataset formats
+pub mod cls;
+pub mod coco;
+pub mod data_yaml;
+pub mod detect;
+pub mod voc;
+pub mod yolo;
+
+use crate::db::models::{
+    Bbox, Classification, Image, Keypoint, LabelFile, Mask, ScanError, Segmentation,
+};
+use crate::db::queries::{
+    BboxQueries, ClassificationQueries, IdPool, LabelFileQueries, ScanErrorQueries,
+};
+use crate::errors::DatalintResult;
+use crate::scanner::file_mtime;
+use chrono::Utc;
+use duckdb::Connection;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// Summary of an annotation import
+#[derive(Debug, Clone, Default)]
+pub struct ImportStats {
+    pub label_files: usize,
+    pub annotations: usize,
+    /// Logged with the scan errors at the `annotation` stage
+    pub errors: Vec<ScanError>,
+}
+
+impl ImportStats {
+    /// Record a non-fatal error for a given source file. `kind` is one of
+    /// `unreadable` (the whole file), `invalid` (a row or object),
+    /// `missing_image`, `unknown_category`, `no_dimensions`, `size_mismatch`
+    /// or `no_class`.
+    pub fn push_error(&mut self, source: &str, kind: &str, message: impl std::fmt::Display) {
+        self.errors.push(ScanError {
+            id: None,
+            path: source.to_string(),
+            stage: "annotation".to_string(),
+            kind: kind.to_string(),
+            message: message.to_string(),
+            created_at: Utc::now().to_rfc3339(),
+        });
+    }
+}
+
+/// Images an import writes annotations for
+#[derive(Debug, Clone, Copy)]
+pub enum ImportScope<'a> {
+    /// Every image, on top of a cache without annotations
+    All,
+    /// Refresh after the images with these ids were added or changed, and
+    /// possibly others removed. Importers also re-read the label files that
+    /// changed since they were recorded, and replace the rows of what they read.
+    Changed {
+        images: &'a HashSet<i32>,
+        images_removed: bool,
+    },
+}
+
+impl ImportScope<'_> {
+    /// Whether annotations of an added or changed image are (re)imported
+    pub fn contains(&self, image_id: Option<i32>) -> bool {
+        match self {
+            ImportScope::All => true,
+            ImportScope::Changed { images, .. } => image_id.is_some_and(|id| images.contains(&id)),
+        }
+    }
+}
+
+/// Record for a label file, stamped with its current size and modification time
+pub(crate) fn label_file(
+    path: &Path,
+    source: String,
+    image_id: Option<i32>,
+    annotation_count: usize,
+) -> LabelFile {
+    let metadata = fs::metadata(path).ok();
+    LabelFile {
+        id: None,
+        path: source,
+        image_id,
+        annotation_count: annotation_count as i32,
+        file_size: metadata.as_ref().map(|meta| meta.len() as i64),
+        file_mtime: metadata.as_ref().and_then(file_mtime),
+    }
+}
+
+/// Whether a label file was created, removed or modified since it was
+/// recorded. Files never recorded (unreadable ones) count as modified while
+/// they exist.
+pub(crate) fn is_modified(record: Option<&LabelFile>, path: &Path) -> bool {
+    let metadata = fs::metadata(path).ok().filter(|meta| meta.is_file());
+    match (record, metadata) {
+        (None, metadata) => metadata.is_some(),
+        (Some(_), None) => true,
+        (Some(record), Some(meta)) => {
+            record.file_size != Some(meta.len() as i64)
+                || record.file_mtime.is_none()
+                || record.file_mtime != file_mtime(&meta)
+        }
+    }
+}
+
+/// Drop the records and annotation errors of files about to be read again
+pub(crate) fn forget_sources(conn: &Connection, sources: &[String]) -> DatalintResult<()> {
+    LabelFileQueries::delete_by_paths(conn, sources)?;
+    ScanErrorQueries::delete_annotation_errors(conn, sources)
+}
+
+/// Index of scanned images by filename and basename, used to resolve the
+/// image references found in annotation files
+pub(crate) struct ImageIndex<'a> {
+    by_filename: HashMap<&'a str, Vec<&'a Image>>,
+    by_name: HashMap<&'a str, Vec<&'a Image>>,
+}
+
+impl<'a> ImageIndex<'a> {
+    pub(crate) fn new(images: &'a [Image]) -> Self {
+        let mut by_filename: HashMap<&str, Vec<&Image>> = HashMap::new();
+        let mut by_name: HashMap<&str, Vec<&Image>> = HashMap::new();
+        for image in images {
+            by_filename.entry(&image.filename).or_default().push(image);
+            by_name.entry(&image.name).or_default().push(image);
+        }
+        Self {
+            by_filename,
+            by_name,
+        }
+    }
+
+    /// Resolve a file name (optionally with directories), preferring images under
+    /// `preferred_root` when the same filename exists in several directories
+    pub(crate) fn resolve(&self, file_name: &str, preferred_root: &Path) -> Option<&'a Image> {
+        let path = Path::new(file_name);
+        let filename = path.file_name()?.to_str()?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+        let matching: Vec<&Image> = self
+            .by_filename
+            .get(filename)?
+            .iter()
+            .copied()
+            .filter(|image| Path::new(&image.relative_path).ends_with(dir))
+            .collect();
+
+        Self::prefer(&matching, preferred_root)
+    }
+
+    /// Resolve an image by basename (without extension)
+    pub(crate) fn resolve_name(&self, name: &str, preferred_root: &Path) -> Option<&'a Image> {
+        Self::prefer(self.by_name.get(name)?, preferred_root)
+    }
+
+    fn prefer(candidates: &[&'a Image], preferred_root: &Path) -> Option<&'a Image> {
+        match candidates {
+            [] => None,
+            [image] => Some(image),
+            _ => candidates
+                .iter()
+                .copied()
+                .find(|image| Path::new(&image.relative_path).starts_with(preferred_root))
+                .or_else(|| candidates.first().copied()),
+        }
+    }
+}
+
+/// Annotation files, or annotations of a COCO file, imported between two
+/// progress updates
+pub(crate) const PARSE_CHUNK: usize = 1024;
+
+/// Rows buffered per table before the importers flush them through the appender
+const WRITE_BATCH: usize = 50_000;
+
+/// Buffers the rows parsed by an importer and writes them through the
+/// appender in batches, so large annotation files are bounded by parsing
+/// rather than by per-row INSERTs.
+///
+/// Box ids come from the sequence as boxes are pushed, so segmentations,
+/// masks and keypoints can point at their box before it is written. Rows are
+/// only visible once flushed; call [`AnnotationWriter::finish`] before
+/// committing.
+pub(crate) struct AnnotationWriter {
+    bbox_ids: IdPool,
+    bboxes: Vec<Bbox>,
+    segmentations: Vec<Segmentation>,
+    masks: Vec<Mask>,
+    keypoints: Vec<Keypoint>,
+    classifications: Vec<Classification>,
+    label_files: Vec<LabelFile>,
+}
+
+impl AnnotationWriter {
+    pub(crate) fn new() -> Self {
+        Self {
+            bbox_ids: IdPool::new("bboxes_id_seq", 4096),
+            bboxes: Vec::new(),
+            segmentations: Vec::new(),
+            masks: Vec::new(),
+            keypoints: Vec::new(),
+            classifications: Vec::new(),
+            label_files: Vec::new(),
+        }
+    }
+
+    /// Queue a box and return the id it will be written with
+    pub(crate) fn push_bbox(&mut self, conn: &Connection, mut bbox: Bbox) -> DatalintResult<i32> {
+        let id = self.bbox_ids.next(conn)?;
+        bbox.id = Some(id);
+        self.bboxes.push(bbox);
+        self.flush_if_full(conn)?;
+        Ok(id)
+    }
+
+    pub(crate) fn push_segmentation(&mut self, segmentation: Segmentation) {
+        self.segmentations.push(segmentation);
+    }
+
+    pub(crate) fn push_mask(&mut self, mask: Mask) {
+        self.masks.push(mask);
+    }
+
+    pub(crate) fn push_keypoint(&mut self, keypoint: Keypoint) {
+        self.keypoints.push(keypoint);
+    }
+
+    pub(crate) fn push_classification(
+        &mut self,
+        conn: &Connection,
+        classification: Classification,
+    ) -> DatalintResult<()> {
+        self.classifications.push(classification);
+        self.flush_if_full(conn)
+    }
+
+    pub(crate) fn push_label_file(&mut self, label_file: LabelFile) {
+        self.label_files.push(label_file);
+    }
+
+    fn flush_if_full(&mut self, conn: &Connection) -> DatalintResult<()> {
+        if self.bboxes.len() >= WRITE_BATCH || self.classifications.len() >= WRITE_BATCH {
+            self.flush(conn)?;
+        }
+        Ok(())
+    }
+
+    /// Write everything buffered, boxes before the rows referencing them
+    fn flush(&mut self, conn: &Connection) -> DatalintResult<()> {
+        BboxQueries::append(conn, &mut self.bboxes)?;
+        BboxQueries::append_segmentations(conn, &mut self.segmentations)?;
+        BboxQueries::append_masks(conn, &mut self.masks)?;
+        BboxQueries::append_keypoints(conn, &mut self.keypoints)?;
+        ClassificationQueries::append(conn, &mut self.classifications)?;
+        LabelFileQueries::append(conn, &mut self.label_files)?;
+
+        self.bboxes.clear();
+        self.segmentations.clear();
+        self.masks.clear();
+        self.keypoints.clear();
+        self.classifications.clear();
+        self.label_files.clear();
+        Ok(())
+    }
+
+    /// Write the remaining rows
+    pub(crate) fn finish(mut self, conn: &Connection) -> DatalintResult<()> {
+        self.flush(conn)
+    }
+}
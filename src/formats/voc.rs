@@ -0,0 +1,481 @@
+use super::{
+    forget_sources, is_modified, label_file, AnnotationWriter, ImageIndex, ImportScope,
+    ImportStats, PARSE_CHUNK,
+};
+use crate::db::models::{Bbox, LabelFile};
+use crate::db::queries::{
+    AnnotationQueries, ImageQueries, LabelFileQueries, LabelQueries, ScanErrorQueries,
+};
+use crate::errors::{DatalintError, DatalintResult};
+use crate::progress::{ProgressSink, Stage};
+use duckdb::Connection;
+use rayon::prelude::*;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Split list files in `ImageSets/Main` (`trainval.txt` is their union and ignored)
+const SPLITS: &[&str] = &["train", "val", "test"];
+
+/// A parsed `Annotations/*.xml` file
+#[derive(Debug, Clone)]
+pub struct VocAnnotation {
+    pub filename: Option<String>,
+    pub width: Option<f64>,
+    pub height: Option<f64>,
+    pub objects: Vec<VocObject>,
+}
+
+/// A single `<object>` entry
+#[derive(Debug, Clone)]
+pub struct VocObject {
+    pub name: String,
+    pub pose: Option<String>,
+    pub truncated: Option<bool>,
+    pub occluded: Option<bool>,
+    pub difficult: Option<bool>,
+    pub xmin: f64,
+    pub ymin: f64,
+    pub xmax: f64,
+    pub ymax: f64,
+}
+
+impl VocObject {
+    /// VOC flags kept as per-annotation attributes
+    pub fn attributes(&self) -> Option<serde_json::Value> {
+        let mut attributes = serde_json::Map::new();
+        if let Some(pose) = &self.pose {
+            attributes.insert("pose".to_string(), json!(pose));
+        }
+        for (key, flag) in [
+            ("truncated", self.truncated),
+            ("occluded", self.occluded),
+            ("difficult", self.difficult),
+        ] {
+            if let Some(flag) = flag {
+                attributes.insert(key.to_string(), json!(flag));
+            }
+        }
+
+        (!attributes.is_empty()).then_some(serde_json::Value::Object(attributes))
+    }
+}
+
+/// Text of a direct child element
+fn child_text<'a>(node: roxmltree::Node<'a, 'a>, tag: &str) -> Option<&'a str> {
+    node.children()
+        .find(|c| c.has_tag_name(tag))
+        .and_then(|c| c.text())
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+}
+
+/// Parse a `0`/`1` flag
+fn child_flag(node: roxmltree::Node<'_, '_>, tag: &str) -> Option<bool> {
+    child_text(node, tag).map(|t| t != "0")
+}
+
+/// Parse a numeric child element
+fn child_number(node: roxmltree::Node<'_, '_>, tag: &str) -> Result<f64, String> {
+    let text = child_text(node, tag).ok_or_else(|| format!("missing <{}>", tag))?;
+    text.parse::<f64>()
+        .map_err(|_| format!("invalid <{}> value '{}'", tag, text))
+}
+
+/// Parse a VOC annotation document, returning it with per-object errors
+pub fn parse_annotation(content: &str) -> DatalintResult<(VocAnnotation, Vec<String>)> {
+    let doc = roxmltree::Document::parse(content)
+        .map_err(|e| DatalintError::Core(format!("Invalid XML: {}", e)))?;
+    let root = doc.root_element();
+
+    let size = root.children().find(|c| c.has_tag_name("size"));
+    let mut annotation = VocAnnotation {
+        filename: child_text(root, "filename").map(str::to_string),
+        width: size.and_then(|s| child_number(s, "width").ok()),
+        height: size.and_then(|s| child_number(s, "height").ok()),
+        objects: Vec::new(),
+    };
+    let mut errors = Vec::new();
+
+    for (i, object) in root
+        .children()
+        .filter(|c| c.has_tag_name("object"))
+        .enumerate()
+    {
+        let parsed = (|| {
+            let name = child_text(object, "name").ok_or("missing <name>")?;
+            let bndbox = object
+                .children()
+                .find(|c| c.has_tag_name("bndbox"))
+                .ok_or("missing <bndbox>")?;
+            Ok::<_, String>(VocObject {
+                name: name.to_string(),
+                pose: child_text(object, "pose").map(str::to_string),
+                truncated: child_flag(object, "truncated"),
+                occluded: child_flag(object, "occluded"),
+                difficult: child_flag(object, "difficult"),
+                xmin: child_number(bndbox, "xmin")?,
+                ymin: child_number(bndbox, "ymin")?,
+                xmax: child_number(bndbox, "xmax")?,
+                ymax: child_number(bndbox, "ymax")?,
+            })
+        })();
+
+        match parsed {
+            Ok(object) => annotation.objects.push(object),
+            Err(e) => errors.push(format!("object {}: {}", i + 1, e)),
+        }
+    }
+
+    Ok((annotation, errors))
+}
+
+/// Find every `Annotations` directory (VOCdevkit may hold several years)
+pub fn find_annotation_dirs(dataset_root: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = WalkDir::new(dataset_root)
+        .max_depth(3)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_dir() && entry.file_name() == "Annotations")
+        .map(|entry| entry.into_path())
+        .collect();
+
+    dirs.sort();
+    dirs
+}
+
+/// `ImageSets/Main/{split}.txt` lists found next to an `Annotations` directory
+fn image_set_lists(base_dir: &Path) -> Vec<(&'static str, PathBuf)> {
+    let sets_dir = base_dir.join("ImageSets").join("Main");
+    SPLITS
+        .iter()
+        .map(|split| (*split, sets_dir.join(format!("{}.txt", split))))
+        .filter(|(_, path)| path.is_file())
+        .collect()
+}
+
+/// Split list files of every annotation directory found in the dataset
+pub fn find_split_lists(dataset_root: &Path) -> Vec<PathBuf> {
+    find_annotation_dirs(dataset_root)
+        .iter()
+        .flat_map(|dir| image_set_lists(dir.parent().unwrap_or(dataset_root)))
+        .map(|(_, path)| path)
+        .collect()
+}
+
+/// An xml file of an `Annotations` directory
+struct XmlFile {
+    path: PathBuf,
+    /// Relative to the dataset root
+    source: String,
+    /// Directory holding the images it refers to, relative to the dataset root
+    image_root: PathBuf,
+}
+
+/// Parsed xml file with the image it resolved to
+type ParsedXml = (DatalintResult<(VocAnnotation, Vec<String>)>, Option<i32>);
+
+/// Import every VOC annotation directory found in the dataset.
+///
+/// A refresh reads the xml files that are new or changed, those recorded for
+/// an image in `scope` and those that matched no image. Images gaining or
+/// losing objects that way take their other xml files along, so every
+/// annotation of an image comes from a single import.
+pub fn import_dataset(
+    conn: &Connection,
+    dataset_root: &Path,
+    scope: ImportScope,
+    progress: &dyn ProgressSink,
+) -> DatalintResult<ImportStats> {
+    let dirs = find_annotation_dirs(dataset_root);
+    let mut xml_files = Vec::new();
+    for dir in &dirs {
+        let base_dir = dir.parent().unwrap_or(dataset_root);
+        let image_root = base_dir.strip_prefix(dataset_root).unwrap_or(Path::new(""));
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .map(|ext| ext.eq_ignore_ascii_case("xml"))
+                    .unwrap_or(false)
+            })
+            .collect();
+        paths.sort();
+        xml_files.extend(paths.into_iter().map(|path| {
+            XmlFile {
+                source: path
+                    .strip_prefix(dataset_root)
+                    .unwrap_or(&path)
+                    .display()
+                    .to_string(),
+                path,
+                image_root: image_root.to_path_buf(),
+            }
+        }));
+    }
+
+    let images = ImageQueries::get_all(conn)?;
+    let index = ImageIndex::new(&images);
+    let sizes: HashMap<i32, (Option<i32>, Option<i32>)> = images
+        .iter()
+        .filter_map(|image| Some((image.id?, (image.width, image.height))))
+        .collect();
+    let records: HashMap<String, LabelFile> = match scope {
+        ImportScope::All => HashMap::new(),
+        ImportScope::Changed { .. } => LabelFileQueries::get_all(conn)?
+            .into_iter()
+            .map(|record| (record.path.clone(), record))
+            .collect(),
+    };
+    let recorded_image = |xml: &XmlFile| records.get(&xml.source).and_then(|r| r.image_id);
+
+    let mut selected: Vec<bool> = xml_files
+        .iter()
+        .map(|xml| match (scope, records.get(&xml.source)) {
+            (ImportScope::All, _) | (_, None) => true,
+            (_, Some(record)) => {
+                record.image_id.is_none()
+                    || scope.contains(record.image_id)
+                    || is_modified(Some(record), &xml.path)
+            }
+        })
+        .collect();
+    // Records of xml files that were deleted
+    let on_disk: HashSet<&str> = xml_files.iter().map(|xml| xml.source.as_str()).collect();
+    let deleted: Vec<&LabelFile> = records
+        .values()
+        .filter(|record| !on_disk.contains(record.path.as_str()))
+        .collect();
+
+    // Images whose objects are replaced
+    let mut image_ids: HashSet<i32> = xml_files
+        .iter()
+        .zip(&selected)
+        .filter(|(_, &selected)| selected)
+        .filter_map(|(xml, _)| recorded_image(xml))
+        .chain(deleted.iter().filter_map(|record| record.image_id))
+        .collect();
+    if let ImportScope::Changed { images, .. } = scope {
+        image_ids.extend(images);
+    }
+
+    // Parse in parallel, a chunk of files at a time so progress is reported
+    let mut parsed: Vec<Option<ParsedXml>> = xml_files.iter().map(|_| None).collect();
+    let mut done = 0;
+    loop {
+        let pending: Vec<usize> = (0..xml_files.len())
+            .filter(|&i| selected[i] && parsed[i].is_none())
+            .collect();
+        if pending.is_empty() {
+            break;
+        }
+        let total = selected.iter().filter(|&&selected| selected).count();
+
+        for chunk in pending.chunks(PARSE_CHUNK) {
+            let results: Vec<_> = chunk
+                .par_iter()
+                .map(|&i| {
+                    let result = fs::read_to_string(&xml_files[i].path)
+                        .map_err(DatalintError::from)
+                        .and_then(|content| parse_annotation(&content));
+                    (i, result)
+                })
+                .collect();
+
+            for (i, result) in results {
+                let xml = &xml_files[i];
+                // Fall back to the xml basename when <filename> is missing
+                let image =
+                    result
+                        .as_ref()
+                        .ok()
+                        .and_then(|(annotation, _)| match &annotation.filename {
+                            Some(filename) => index.resolve(filename, &xml.image_root),
+                            None => xml
+                                .path
+                                .file_stem()
+                                .and_then(|stem| stem.to_str())
+                                .and_then(|stem| index.resolve_name(stem, &xml.image_root)),
+                        });
+                let image_id = image.and_then(|image| image.id);
+                image_ids.extend(image_id);
+                parsed[i] = Some((result, image_id));
+            }
+
+            done += chunk.len();
+            progress.update(Stage::Annotations, done, Some(total))?;
+        }
+
+        for (i, xml) in xml_files.iter().enumerate() {
+            if recorded_image(xml).is_some_and(|id| image_ids.contains(&id)) {
+                selected[i] = true;
+            }
+        }
+    }
+
+    if let ImportScope::Changed { .. } = scope {
+        let image_ids: Vec<i32> = image_ids.into_iter().collect();
+        AnnotationQueries::delete_by_images(conn, &image_ids)?;
+        let mut sources: Vec<String> = xml_files
+            .iter()
+            .zip(&selected)
+            .filter(|(_, &selected)| selected)
+            .map(|(xml, _)| xml.source.clone())
+            .collect();
+        sources.extend(deleted.iter().map(|record| record.path.clone()));
+        forget_sources(conn, &sources)?;
+    }
+
+    let mut stats = ImportStats::default();
+    let mut label_ids: HashMap<String, i32> = HashMap::new();
+    let mut writer = AnnotationWriter::new();
+
+    for (xml, parsed) in xml_files.iter().zip(parsed) {
+        let Some((result, image_id)) = parsed else {
+            continue;
+        };
+        let source = &xml.source;
+        let (annotation, object_errors) = match result {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                stats.push_error(source, "unreadable", e);
+                continue;
+            }
+        };
+        stats.label_files += 1;
+
+        writer.push_label_file(label_file(
+            &xml.path,
+            source.clone(),
+            image_id,
+            annotation.objects.len() + object_errors.len(),
+        ));
+        for err in object_errors {
+            stats.push_error(source, "invalid", err);
+        }
+
+        let Some(image_id) = image_id else {
+            stats.push_error(
+                source,
+                "missing_image",
+                format!(
+                    "image '{}' not found in dataset",
+                    annotation.filename.as_deref().unwrap_or("?")
+                ),
+            );
+            continue;
+        };
+        // Tools writing 0 for an unknown size are left alone
+        if let (Some(width), Some(height), Some(&(Some(image_width), Some(image_height)))) =
+            (annotation.width, annotation.height, sizes.get(&image_id))
+        {
+            if width > 0.0
+                && height > 0.0
+                && (width != image_width as f64 || height != image_height as f64)
+            {
+                stats.push_error(
+                    source,
+                    "size_mismatch",
+                    format!(
+                        "<size> {}x{} does not match the image, {}x{}",
+                        width, height, image_width, image_height
+                    ),
+                );
+            }
+        }
+
+        for object in annotation.objects {
+            let label_id = match label_ids.get(&object.name) {
+                Some(&id) => id,
+                None => {
+                    let id = LabelQueries::get_or_create(conn, &object.name, None)?;
+                    label_ids.insert(object.name.clone(), id);
+                    id
+                }
+            };
+
+            let mut bbox = Bbox::new(
+                image_id,
+                label_id,
+                object.xmin,
+                object.ymin,
+                object.xmax,
+                object.ymax,
+            );
+            bbox.attributes = object.attributes();
+            writer.push_bbox(conn, bbox)?;
+            stats.annotations += 1;
+        }
+    }
+
+    writer.finish(conn)?;
+    if let ImportScope::Changed { .. } = scope {
+        // Classes whose last object went away
+        LabelQueries::delete_unused(conn)?;
+    }
+
+    for dir in &dirs {
+        apply_image_sets(conn, dataset_root, dir, &index, &mut stats)?;
+    }
+
+    Ok(stats)
+}
+
+/// Assign splits from the `ImageSets/Main` lists next to an `Annotations`
+/// directory, replacing the path-based guess for its images
+fn apply_image_sets(
+    conn: &Connection,
+    dataset_root: &Path,
+    annotations_dir: &Path,
+    index: &ImageIndex,
+    stats: &mut ImportStats,
+) -> DatalintResult<()> {
+    let base_dir = annotations_dir.parent().unwrap_or(dataset_root);
+    let image_root = base_dir.strip_prefix(dataset_root).unwrap_or(Path::new(""));
+
+    let split_lists = image_set_lists(base_dir);
+    let sources: Vec<String> = split_lists
+        .iter()
+        .map(|(_, list)| {
+            list.strip_prefix(dataset_root)
+                .unwrap_or(list)
+                .display()
+                .to_string()
+        })
+        .collect();
+    // The lists are read on every import
+    ScanErrorQueries::delete_annotation_errors(conn, &sources)?;
+
+    if !split_lists.is_empty() {
+        if image_root.as_os_str().is_empty() {
+            ImageQueries::reset_splits(conn)?;
+        } else {
+            ImageQueries::set_split_by_dir(conn, &image_root.to_string_lossy(), "unknown")?;
+        }
+    }
+
+    for ((split, list), source) in split_lists.into_iter().zip(&sources) {
+        let content = match fs::read_to_string(&list) {
+            Ok(content) => content,
+            Err(e) => {
+                stats.push_error(source, "unreadable", e);
+                continue;
+            }
+        };
+        for name in content.lines().filter_map(|l| l.split_whitespace().next()) {
+            if let Some(image) = index.resolve_name(name, image_root) {
+                ImageQueries::set_split_by_file(
+                    conn,
+                    &image.relative_path,
+                    &image.filename,
+                    split,
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,652 @@
+use super::data_yaml::{is_list_file, DataYaml};
+use super::{
+    forget_sources, is_modified, label_file, AnnotationWriter, ImportScope, ImportStats,
+    PARSE_CHUNK,
+};
+use crate::db::models::{Bbox, Image, Keypoint, LabelFile, Point, Segmentation};
+use crate::db::queries::{
+    AnnotationQueries, ImageQueries, LabelFileQueries, LabelQueries, MetadataQueries,
+};
+use crate::enums::DatasetTask;
+use crate::errors::{DatalintError, DatalintResult};
+use crate::progress::{ProgressSink, Stage};
+use duckdb::Connection;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Pixel-space annotation built from a label row
+#[derive(Debug, Clone)]
+pub struct PixelAnnotation {
+    pub bbox: Bbox,
+    pub polygon: Option<Vec<(f64, f64)>>,
+    pub keypoints: Option<Keypoint>,
+}
+
+/// A row of a YOLO label file, with one implementation per task
+pub trait YoloRow: Send {
+    /// Class index of the row
+    fn class_id(&self) -> usize;
+
+    /// Convert to pixel coordinates for an image of the given size.
+    /// Image and label ids of the returned bbox are filled in on insert.
+    fn to_pixels(&self, width: f64, height: f64) -> PixelAnnotation;
+}
+
+/// A detection row `class cx cy w h` (normalized coordinates)
+#[derive(Debug, Clone, PartialEq)]
+pub struct YoloBox {
+    pub class_id: usize,
+    pub cx: f64,
+    pub cy: f64,
+    pub w: f64,
+    pub h: f64,
+}
+
+impl YoloBox {
+    /// Convert to pixel corners (x1, y1, x2, y2) for an image of the given size
+    pub fn to_corners(&self, width: f64, height: f64) -> (f64, f64, f64, f64) {
+        let half_w = self.w * width / 2.0;
+        let half_h = self.h * height / 2.0;
+        let cx = self.cx * width;
+        let cy = self.cy * height;
+        (cx - half_w, cy - half_h, cx + half_w, cy + half_h)
+    }
+}
+
+impl YoloRow for YoloBox {
+    fn class_id(&self) -> usize {
+        self.class_id
+    }
+
+    fn to_pixels(&self, width: f64, height: f64) -> PixelAnnotation {
+        let (x1, y1, x2, y2) = self.to_corners(width, height);
+        PixelAnnotation {
+            bbox: Bbox::new(0, 0, x1, y1, x2, y2),
+            polygon: None,
+            keypoints: None,
+        }
+    }
+}
+
+/// A segmentation row `class x1 y1 x2 y2 ...` (normalized polygon)
+#[derive(Debug, Clone, PartialEq)]
+pub struct YoloPolygon {
+    pub class_id: usize,
+    pub points: Vec<(f64, f64)>,
+}
+
+impl YoloRow for YoloPolygon {
+    fn class_id(&self) -> usize {
+        self.class_id
+    }
+
+    /// The bbox is the enclosing box of the polygon
+    fn to_pixels(&self, width: f64, height: f64) -> PixelAnnotation {
+        let points: Vec<(f64, f64)> = self
+            .points
+            .iter()
+            .map(|(x, y)| (x * width, y * height))
+            .collect();
+        let (x1, y1, x2, y2) = points.iter().fold(
+            (
+                f64::INFINITY,
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+                f64::NEG_INFINITY,
+            ),
+            |(x1, y1, x2, y2), &(x, y)| (x1.min(x), y1.min(y), x2.max(x), y2.max(y)),
+        );
+        PixelAnnotation {
+            bbox: Bbox::new(0, 0, x1, y1, x2, y2),
+            polygon: Some(points),
+            keypoints: None,
+        }
+    }
+}
+
+/// An oriented box row `class x1 y1 x2 y2 x3 y3 x4 y4` (normalized corners)
+#[derive(Debug, Clone, PartialEq)]
+pub struct YoloObb {
+    pub class_id: usize,
+    pub corners: [(f64, f64); 4],
+}
+
+impl YoloRow for YoloObb {
+    fn class_id(&self) -> usize {
+        self.class_id
+    }
+
+    fn to_pixels(&self, width: f64, height: f64) -> PixelAnnotation {
+        let corners = self.corners.map(|(x, y)| (x * width, y * height));
+        PixelAnnotation {
+            bbox: Bbox::from_obb_corners(0, 0, &corners),
+            polygon: None,
+            keypoints: None,
+        }
+    }
+}
+
+/// Resolve the label file for an image following the Ultralytics convention:
+/// the last `images` directory is swapped for `labels` and the extension for `.txt`.
+/// Images outside an `images` directory look for the label file next to them.
+pub fn label_path_for(dataset_root: &Path, image: &Image) -> PathBuf {
+    let image_dir = Path::new(&image.relative_path);
+    let components: Vec<_> = image_dir.components().collect();
+
+    let label_dir = match components.iter().rposition(|c| c.as_os_str() == "images") {
+        Some(pos) => components
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                if i == pos {
+                    Path::new("labels")
+                } else {
+                    Path::new(c.as_os_str())
+                }
+            })
+            .collect::<PathBuf>(),
+        None => image_dir.to_path_buf(),
+    };
+
+    dataset_root
+        .join(label_dir)
+        .join(format!("{}.txt", image.name))
+}
+
+/// Label files below a `labels` directory that no image resolved to
+fn find_orphan_label_files(dataset_root: &Path, matched: &HashSet<PathBuf>) -> Vec<PathBuf> {
+    WalkDir::new(dataset_root)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|path| {
+            path.extension()
+                .map(|ext| ext.eq_ignore_ascii_case("txt"))
+                .unwrap_or(false)
+        })
+        .filter(|path| {
+            path.strip_prefix(dataset_root)
+                .ok()
+                .and_then(Path::parent)
+                .is_some_and(|dir| dir.components().any(|c| c.as_os_str() == "labels"))
+        })
+        .filter(|path| !matched.contains(path))
+        .collect()
+}
+
+/// A pose row `class cx cy w h px py [v] ...` (normalized coordinates)
+#[derive(Debug, Clone, PartialEq)]
+pub struct YoloPose {
+    pub bbox: YoloBox,
+    pub points: Vec<(f64, f64, Option<f64>)>,
+}
+
+impl YoloRow for YoloPose {
+    fn class_id(&self) -> usize {
+        self.bbox.class_id
+    }
+
+    fn to_pixels(&self, width: f64, height: f64) -> PixelAnnotation {
+        let mut annotation = self.bbox.to_pixels(width, height);
+        let points: Vec<Point> = self
+            .points
+            .iter()
+            .map(|&(x, y, visibility)| Point {
+                x: x * width,
+                y: y * height,
+                visibility,
+            })
+            .collect();
+        annotation.keypoints = Some(Keypoint {
+            id: None,
+            bbox_id: 0,
+            point_count: points.len() as i32,
+            has_visibility: points.iter().any(|p| p.visibility.is_some()),
+            points,
+        });
+        annotation
+    }
+}
+
+/// Split a line into its class id and coordinate values
+fn parse_values(line: &str) -> Result<(usize, Vec<f64>), String> {
+    let mut tokens = line.split_whitespace();
+    let class_token = tokens.next().ok_or("empty line")?;
+    let class_id = class_token
+        .parse::<usize>()
+        .map_err(|_| format!("invalid class id '{}'", class_token))?;
+
+    // NaN and infinite values parse as f64 but no coordinate can hold them
+    let values = tokens
+        .map(|token| {
+            token
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .ok_or_else(|| format!("invalid coordinate '{}'", token))
+        })
+        .collect::<Result<Vec<f64>, String>>()?;
+
+    Ok((class_id, values))
+}
+
+/// Parse a single `class cx cy w h` line
+pub fn parse_line(line: &str) -> Result<YoloBox, String> {
+    let (class_id, values) = parse_values(line)?;
+    let [cx, cy, w, h] = values[..] else {
+        return Err(format!("expected 5 values, found {}", values.len() + 1));
+    };
+
+    Ok(YoloBox {
+        class_id,
+        cx,
+        cy,
+        w,
+        h,
+    })
+}
+
+/// Parse a `class x1 y1 x2 y2 ...` polygon line (at least 3 points)
+pub fn parse_polygon_line(line: &str) -> Result<YoloPolygon, String> {
+    let (class_id, values) = parse_values(line)?;
+    if values.len() < 6 || values.len() % 2 != 0 {
+        return Err(format!(
+            "expected an even number of at least 6 coordinates, found {}",
+            values.len()
+        ));
+    }
+
+    Ok(YoloPolygon {
+        class_id,
+        points: values.chunks_exact(2).map(|xy| (xy[0], xy[1])).collect(),
+    })
+}
+
+/// Parse a `class x1 y1 x2 y2 x3 y3 x4 y4` oriented box line
+pub fn parse_obb_line(line: &str) -> Result<YoloObb, String> {
+    let (class_id, values) = parse_values(line)?;
+    let [x1, y1, x2, y2, x3, y3, x4, y4] = values[..] else {
+        return Err(format!("expected 9 values, found {}", values.len() + 1));
+    };
+
+    Ok(YoloObb {
+        class_id,
+        corners: [(x1, y1), (x2, y2), (x3, y3), (x4, y4)],
+    })
+}
+
+/// Parse a pose line for `kpt_shape = [count, dims]`, dims being 2 (x, y)
+/// or 3 (x, y, visibility)
+pub fn parse_pose_line(line: &str, count: usize, dims: usize) -> Result<YoloPose, String> {
+    let (class_id, values) = parse_values(line)?;
+    let expected = 4 + count * dims;
+    if values.len() != expected {
+        return Err(format!(
+            "expected {} values for {} keypoints with {} dims, found {}",
+            expected + 1,
+            count,
+            dims,
+            values.len() + 1
+        ));
+    }
+
+    let points = values[4..]
+        .chunks_exact(dims)
+        .map(|p| (p[0], p[1], p.get(2).copied()))
+        .collect();
+
+    Ok(YoloPose {
+        bbox: YoloBox {
+            class_id,
+            cx: values[0],
+            cy: values[1],
+            w: values[2],
+            h: values[3],
+        },
+        points,
+    })
+}
+
+/// Parse a label file with the given line parser, returning the valid rows
+/// and the per-line errors
+pub fn parse_label_file<R>(
+    path: &Path,
+    parse: impl Fn(&str) -> Result<R, String>,
+) -> DatalintResult<(Vec<R>, Vec<String>)> {
+    let content = fs::read_to_string(path)?;
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse(line) {
+            Ok(row) => rows.push(row),
+            Err(e) => errors.push(format!("line {}: {}", i + 1, e)),
+        }
+    }
+
+    Ok((rows, errors))
+}
+
+/// Import a YOLO dataset: apply data.yaml (labels, splits, keypoint metadata)
+/// and parse the label files for the given task
+pub fn import_dataset(
+    conn: &Connection,
+    dataset_root: &Path,
+    dataset_task: &DatasetTask,
+    scope: ImportScope,
+    progress: &dyn ProgressSink,
+) -> DatalintResult<ImportStats> {
+    let config = match DataYaml::find(dataset_root) {
+        Some(path) => DataYaml::load(&path)?,
+        None => DataYaml::default(),
+    };
+
+    let label_ids = apply_config(conn, dataset_root, &config)?;
+
+    match dataset_task {
+        DatasetTask::ObjectDetection => {
+            import_annotations(conn, dataset_root, label_ids, parse_line, scope, progress)
+        }
+        DatasetTask::InstanceSegmentation => import_annotations(
+            conn,
+            dataset_root,
+            label_ids,
+            parse_polygon_line,
+            scope,
+            progress,
+        ),
+        DatasetTask::ObbDetection => import_annotations(
+            conn,
+            dataset_root,
+            label_ids,
+            parse_obb_line,
+            scope,
+            progress,
+        ),
+        DatasetTask::PoseEstimation => {
+            let (count, dims) = match config.keypoint_shape() {
+                Some((count, dims @ (2 | 3))) => (count, dims),
+                Some((_, dims)) => {
+                    return Err(DatalintError::Core(format!(
+                        "Invalid kpt_shape: keypoints must have 2 or 3 dims, found {}",
+                        dims
+                    )))
+                }
+                None => {
+                    return Err(DatalintError::Core(match &config.kpt_shape {
+                        Some(shape) => format!(
+                            "Invalid kpt_shape: expected [keypoints, dims], found {:?}",
+                            shape
+                        ),
+                        None => "Pose datasets require kpt_shape in data.yaml".to_string(),
+                    }))
+                }
+            };
+            import_annotations(
+                conn,
+                dataset_root,
+                label_ids,
+                move |line| parse_pose_line(line, count, dims),
+                scope,
+                progress,
+            )
+        }
+        _ => Ok(ImportStats::default()),
+    }
+}
+
+/// Seed labels in class-id order, assign declared splits and store keypoint metadata.
+/// Returns the label id for each class id.
+pub fn apply_config(
+    conn: &Connection,
+    dataset_root: &Path,
+    config: &DataYaml,
+) -> DatalintResult<HashMap<usize, i32>> {
+    let keypoint_names = config
+        .keypoint_names()
+        .map(|names| serde_json::to_string(&names))
+        .transpose()
+        .map_err(|e| DatalintError::Generic(e.to_string()))?;
+    let keypoint_skeleton = config
+        .skeleton
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| DatalintError::Generic(e.to_string()))?;
+    if keypoint_names.is_some() || keypoint_skeleton.is_some() {
+        MetadataQueries::set_keypoints(
+            conn,
+            keypoint_names.as_deref(),
+            keypoint_skeleton.as_deref(),
+        )?;
+    }
+
+    let mut label_ids = HashMap::new();
+    for (class_id, name) in config.class_names() {
+        label_ids.insert(class_id, LabelQueries::get_or_create(conn, &name, None)?);
+    }
+
+    let splits = config.splits();
+    if !splits.is_empty() {
+        // Declared splits replace the path-based guess from the scanner
+        ImageQueries::reset_splits(conn)?;
+    }
+
+    for (split, entries) in splits {
+        for entry in entries {
+            let Some(path) = config.relative_to_root(entry, dataset_root) else {
+                continue;
+            };
+
+            if is_list_file(&path) {
+                // Image list file, one path per line relative to the dataset root
+                let Ok(content) = fs::read_to_string(dataset_root.join(&path)) else {
+                    continue;
+                };
+                for line in content.lines().map(str::trim).filter(|l| !l.is_empty()) {
+                    let Some(image_path) = config.relative_to_root(line, dataset_root) else {
+                        continue;
+                    };
+                    let (Some(dir), Some(filename)) = (image_path.parent(), image_path.file_name())
+                    else {
+                        continue;
+                    };
+                    ImageQueries::set_split_by_file(
+                        conn,
+                        &dir.to_string_lossy(),
+                        &filename.to_string_lossy(),
+                        split,
+                    )?;
+                }
+            } else {
+                ImageQueries::set_split_by_dir(conn, &path.to_string_lossy(), split)?;
+            }
+        }
+    }
+
+    Ok(label_ids)
+}
+
+/// Import YOLO labels for the images in `scope`, parsing each line with
+/// `parse`. Class ids missing from `label_ids` get a label named after the id.
+/// Label files matching no image are reported again on every import.
+pub fn import_annotations<R, F>(
+    conn: &Connection,
+    dataset_root: &Path,
+    mut label_ids: HashMap<usize, i32>,
+    parse: F,
+    scope: ImportScope,
+    progress: &dyn ProgressSink,
+) -> DatalintResult<ImportStats>
+where
+    R: YoloRow,
+    F: Fn(&str) -> Result<R, String> + Sync,
+{
+    let images = ImageQueries::get_all(conn)?;
+    let records: HashMap<String, LabelFile> = match scope {
+        ImportScope::All => HashMap::new(),
+        ImportScope::Changed { .. } => LabelFileQueries::get_all(conn)?
+            .into_iter()
+            .map(|record| (record.path.clone(), record))
+            .collect(),
+    };
+
+    // Images to read labels for: those in scope, and those whose label file
+    // appeared, disappeared or changed since it was recorded
+    let expected: Vec<(PathBuf, String)> = images
+        .par_iter()
+        .map(|image| {
+            let path = label_path_for(dataset_root, image);
+            let source = path
+                .strip_prefix(dataset_root)
+                .unwrap_or(&path)
+                .display()
+                .to_string();
+            (path, source)
+        })
+        .collect();
+    let targets: Vec<(&Image, &PathBuf, &String)> = images
+        .par_iter()
+        .zip(&expected)
+        .filter(|(image, (path, source))| match scope {
+            ImportScope::All => true,
+            ImportScope::Changed { .. } => {
+                scope.contains(image.id) || is_modified(records.get(source), path)
+            }
+        })
+        .map(|(image, (path, source))| (image, path, source))
+        .collect();
+
+    if let ImportScope::Changed { .. } = scope {
+        let image_ids: Vec<i32> = targets.iter().filter_map(|(image, ..)| image.id).collect();
+        AnnotationQueries::delete_by_images(conn, &image_ids)?;
+        let mut sources: Vec<String> = targets
+            .iter()
+            .map(|(_, _, source)| source.to_string())
+            .collect();
+        sources.extend(
+            records
+                .values()
+                .filter(|record| record.image_id.is_none())
+                .map(|record| record.path.clone()),
+        );
+        forget_sources(conn, &sources)?;
+    }
+
+    let mut stats = ImportStats::default();
+    let mut writer = AnnotationWriter::new();
+
+    // Read and parse label files in parallel, a chunk of images at a time so
+    // progress is reported as it goes. Images without one are backgrounds.
+    let mut done = 0;
+    for chunk in targets.chunks(PARSE_CHUNK) {
+        let parsed: Vec<_> = chunk
+            .par_iter()
+            .filter_map(|&(image, path, source)| {
+                if !path.is_file() {
+                    return None;
+                }
+                let result = parse_label_file(path, &parse);
+                Some((image, path, source, result))
+            })
+            .collect();
+
+        for (image, path, source, result) in parsed {
+            stats.label_files += 1;
+
+            let (rows, line_errors) = match result {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    stats.push_error(source, "unreadable", e);
+                    continue;
+                }
+            };
+            writer.push_label_file(label_file(
+                path,
+                source.clone(),
+                image.id,
+                rows.len() + line_errors.len(),
+            ));
+            for err in line_errors {
+                stats.push_error(source, "invalid", err);
+            }
+
+            let (Some(image_id), Some(width), Some(height)) = (image.id, image.width, image.height)
+            else {
+                if !rows.is_empty() {
+                    stats.push_error(
+                        source,
+                        "no_dimensions",
+                        "image dimensions unknown, labels skipped",
+                    );
+                }
+                continue;
+            };
+
+            for row in rows {
+                let PixelAnnotation {
+                    mut bbox,
+                    polygon,
+                    keypoints,
+                } = row.to_pixels(width as f64, height as f64);
+                let class_id = row.class_id();
+                let label_id = match label_ids.get(&class_id) {
+                    Some(&id) => id,
+                    None => {
+                        let id = LabelQueries::get_or_create(conn, &class_id.to_string(), None)?;
+                        label_ids.insert(class_id, id);
+                        id
+                    }
+                };
+
+                bbox.image_id = image_id;
+                bbox.label_id = label_id;
+                let bbox_id = writer.push_bbox(conn, bbox)?;
+
+                if let Some(vertices) = polygon {
+                    let segmentation = Segmentation {
+                        id: None,
+                        bbox_id,
+                        vertex_count: vertices.len() as i32,
+                        vertices,
+                    };
+                    writer.push_segmentation(segmentation);
+                }
+                if let Some(mut keypoint) = keypoints {
+                    keypoint.bbox_id = bbox_id;
+                    writer.push_keypoint(keypoint);
+                }
+                stats.annotations += 1;
+            }
+        }
+
+        done += chunk.len();
+        progress.update(Stage::Annotations, done, Some(targets.len()))?;
+    }
+
+    // Label files no image resolved to would otherwise go unnoticed
+    let matched: HashSet<PathBuf> = expected.into_iter().map(|(path, _)| path).collect();
+    for path in find_orphan_label_files(dataset_root, &matched) {
+        let source = path
+            .strip_prefix(dataset_root)
+            .unwrap_or(&path)
+            .display()
+            .to_string();
+        let annotation_count = fs::read_to_string(&path)
+            .map(|content| content.lines().filter(|l| !l.trim().is_empty()).count())
+            .unwrap_or(0);
+        stats.push_error(&source, "missing_image", "no matching image");
+        writer.push_label_file(label_file(&path, source, None, annotation_count));
+    }
+
+    writer.finish(conn)?;
+
+    Ok(stats)
+}
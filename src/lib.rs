@@ -1,45 +1,319 @@
 #![cfg_attr(debug_assertions, allow(dead_code))]
 
 use pyo3::prelude::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 // Internal modules
 pub mod cache;
 pub mod db;
 pub mod enums;
 pub mod errors;
+pub mod formats;
+pub mod lint;
+pub mod phash;
+pub mod probe;
+pub mod progress;
+pub mod py_cache;
+pub mod py_task;
+pub mod rle;
 pub mod scanner;
 
-use crate::cache::create_cache_db;
+use crate::cache::{create_cache_db, update_cache_db};
 use crate::enums::{DatasetTask, DatasetType};
+use crate::errors::DatalintResult;
+use crate::formats::detect::{detect_dataset_format, DetectedFormat};
+use crate::lint::{LintConfig, RuleInfo, RuleRegistry};
+use crate::progress::{ProgressSink, PyProgress};
+use crate::py_task::CacheTask;
+use crate::scanner::ScanOptions;
+
+/// How the format of a dataset given as `Unknown` was settled, appended to
+/// the cache summaries
+fn detection_note(dataset_type: &DatasetType, detected: Option<&DetectedFormat>) -> String {
+    match (dataset_type, detected) {
+        (_, Some(guess)) => format!(
+            "; detected {} dataset (confidence {:.2})",
+            guess.dataset_type.as_str(),
+            guess.confidence
+        ),
+        (DatasetType::Unknown, None) => {
+            "; could not detect the dataset format, cached images only".to_string()
+        }
+        _ => String::new(),
+    }
+}
+
+/// Build a cache and describe the outcome
+fn build_cache(
+    cache_path: &str,
+    dataset_path: &str,
+    dataset_type: &DatasetType,
+    dataset_task: &DatasetTask,
+    options: &ScanOptions,
+    progress: &dyn ProgressSink,
+) -> DatalintResult<String> {
+    let created = create_cache_db(
+        Path::new(cache_path),
+        Path::new(dataset_path),
+        dataset_type,
+        dataset_task,
+        options,
+        progress,
+    )?;
+    Ok(format!(
+        "Cache created at: {} with {} images ({} scan errors, {} annotation errors){}",
+        cache_path,
+        created.images,
+        created.scan_errors,
+        created.annotation_errors,
+        detection_note(dataset_type, created.detected.as_ref())
+    ))
+}
+
+/// Refresh a cache and describe the outcome
+fn refresh_cache(
+    cache_path: &str,
+    dataset_path: &str,
+    dataset_type: &DatasetType,
+    dataset_task: &DatasetTask,
+    options: &ScanOptions,
+    progress: &dyn ProgressSink,
+) -> DatalintResult<String> {
+    let update = update_cache_db(
+        Path::new(cache_path),
+        Path::new(dataset_path),
+        dataset_type,
+        dataset_task,
+        options,
+        progress,
+    )?;
+    Ok(format!(
+        "Cache updated at: {} ({} added, {} changed, {} removed, {} unchanged, {} scan errors, \
+         {} annotation errors){}",
+        cache_path,
+        update.added,
+        update.changed,
+        update.removed,
+        update.unchanged,
+        update.scan_errors,
+        update.annotation_errors,
+        detection_note(dataset_type, update.detected.as_ref())
+    ))
+}
 
 /// Create a cache database for a dataset
 ///
+/// The scan, inserts and annotation import run without holding the GIL.
+///
 /// Args:
 ///     cache_path (str): Path where the cache database will be created
 ///     dataset_path (str): Path to the dataset directory to scan
 ///     dataset_type (DatasetType): Type of dataset (YOLO, COCO, etc.)
 ///     dataset_task (DatasetTask): Task type (detect, segment, etc.)
+///     perceptual_hashes (bool): Also compute aHash, dHash and pHash for
+///         near-duplicate search (slower scan)
+///     full_decode (bool): Decode every image to detect truncated or damaged
+///         files (slower scan); by default only image headers are read
+///     progress (Callable[[str, int, int | None], Any] | tqdm | None): Called
+///         with the stage ("scan", "hash", "annotations" or "lint"), the units
+///         done and the total (None while unknown), or a tqdm-compatible bar
+///         to advance
 ///
 /// Returns:
-///     str: Success message with the cache location and image count
+///     str: Success message with the cache location, image count, number of
+///     files that could not be scanned and number of annotation problems, such
+///     as annotations referencing missing images (see `Cache.scan_errors`)
 ///
 /// Raises:
-///     RuntimeError: If cache creation fails
+///     RuntimeError: If a file already exists at `cache_path` or cache
+///         creation fails; a partially built cache is removed
+///     KeyboardInterrupt: On Ctrl-C. The partially built cache is removed.
 #[pyfunction]
+#[pyo3(signature = (cache_path, dataset_path, dataset_type, dataset_task, perceptual_hashes=false, full_decode=false, progress=None))]
+#[allow(clippy::too_many_arguments)]
 fn create_cache(
+    py: Python<'_>,
     cache_path: String,
     dataset_path: String,
     dataset_type: DatasetType,
     dataset_task: DatasetTask,
+    perceptual_hashes: bool,
+    full_decode: bool,
+    progress: Option<Py<PyAny>>,
 ) -> PyResult<String> {
-    let cache = PathBuf::from(&cache_path);
-    let dataset = PathBuf::from(&dataset_path);
-    let image_count = create_cache_db(&cache, &dataset, &dataset_type, &dataset_task)?;
-    Ok(format!(
-        "Cache created at: {} with {} images",
-        cache_path, image_count
-    ))
+    let options = ScanOptions {
+        perceptual_hashes,
+        full_decode,
+    };
+    let progress = PyProgress::new(progress);
+    let message = py.allow_threads(|| {
+        build_cache(
+            &cache_path,
+            &dataset_path,
+            &dataset_type,
+            &dataset_task,
+            &options,
+            &progress,
+        )
+    })?;
+    Ok(message)
+}
+
+/// Create a cache database on a background thread
+///
+/// Takes the same arguments as `create_cache`. `progress` is called from the
+/// background thread.
+///
+/// Returns:
+///     CacheTask: Handle to poll, wait on, cancel or `await`; resolves to the
+///     message `create_cache` returns
+#[pyfunction]
+#[pyo3(signature = (cache_path, dataset_path, dataset_type, dataset_task, perceptual_hashes=false, full_decode=false, progress=None))]
+#[allow(clippy::too_many_arguments)]
+fn create_cache_async(
+    py: Python<'_>,
+    cache_path: String,
+    dataset_path: String,
+    dataset_type: DatasetType,
+    dataset_task: DatasetTask,
+    perceptual_hashes: bool,
+    full_decode: bool,
+    progress: Option<Py<PyAny>>,
+) -> PyResult<CacheTask> {
+    let options = ScanOptions {
+        perceptual_hashes,
+        full_decode,
+    };
+    CacheTask::spawn(py, progress, move |progress| {
+        build_cache(
+            &cache_path,
+            &dataset_path,
+            &dataset_type,
+            &dataset_task,
+            &options,
+            progress,
+        )
+    })
+}
+
+/// Refresh a cache database against the dataset on disk
+///
+/// Only new or modified files are hashed and read again, rows for deleted files
+/// are removed and annotations are re-imported for new and modified images and
+/// label files. Changes to data.yaml or a COCO file re-import every annotation.
+/// The cache is written in one transaction. A missing cache is created.
+/// The refresh runs without holding the GIL.
+///
+/// Args:
+///     cache_path (str): Path of the cache database to refresh
+///     dataset_path (str): Path to the dataset directory the cache was built from
+///     dataset_type (DatasetType): Type of dataset (YOLO, COCO, etc.)
+///     dataset_task (DatasetTask): Task type (detect, segment, etc.)
+///     perceptual_hashes (bool): Also compute perceptual hashes, including for
+///         unchanged images cached without them
+///     full_decode (bool): Decode every image, including unchanged images
+///         whose header only was read
+///     progress (Callable[[str, int, int | None], Any] | tqdm | None): Called
+///         with the stage ("scan", "hash", "annotations" or "lint"), the units
+///         done and the total (None while unknown), or a tqdm-compatible bar
+///         to advance
+///
+/// Returns:
+///     str: Summary of added, changed, removed and unchanged images, scan
+///     errors and annotation problems
+///
+/// Raises:
+///     RuntimeError: If the cache belongs to another dataset or the refresh fails
+///     KeyboardInterrupt: On Ctrl-C. The cache is left as it was, and a
+///         cache being created is removed.
+#[pyfunction]
+#[pyo3(signature = (cache_path, dataset_path, dataset_type, dataset_task, perceptual_hashes=false, full_decode=false, progress=None))]
+#[allow(clippy::too_many_arguments)]
+fn update_cache(
+    py: Python<'_>,
+    cache_path: String,
+    dataset_path: String,
+    dataset_type: DatasetType,
+    dataset_task: DatasetTask,
+    perceptual_hashes: bool,
+    full_decode: bool,
+    progress: Option<Py<PyAny>>,
+) -> PyResult<String> {
+    let options = ScanOptions {
+        perceptual_hashes,
+        full_decode,
+    };
+    let progress = PyProgress::new(progress);
+    let message = py.allow_threads(|| {
+        refresh_cache(
+            &cache_path,
+            &dataset_path,
+            &dataset_type,
+            &dataset_task,
+            &options,
+            &progress,
+        )
+    })?;
+    Ok(message)
+}
+
+/// Refresh a cache database on a background thread
+///
+/// Takes the same arguments as `update_cache`. `progress` is called from the
+/// background thread.
+///
+/// Returns:
+///     CacheTask: Handle to poll, wait on, cancel or `await`; resolves to the
+///     summary `update_cache` returns
+#[pyfunction]
+#[pyo3(signature = (cache_path, dataset_path, dataset_type, dataset_task, perceptual_hashes=false, full_decode=false, progress=None))]
+#[allow(clippy::too_many_arguments)]
+fn update_cache_async(
+    py: Python<'_>,
+    cache_path: String,
+    dataset_path: String,
+    dataset_type: DatasetType,
+    dataset_task: DatasetTask,
+    perceptual_hashes: bool,
+    full_decode: bool,
+    progress: Option<Py<PyAny>>,
+) -> PyResult<CacheTask> {
+    let options = ScanOptions {
+        perceptual_hashes,
+        full_decode,
+    };
+    CacheTask::spawn(py, progress, move |progress| {
+        refresh_cache(
+            &cache_path,
+            &dataset_path,
+            &dataset_type,
+            &dataset_task,
+            &options,
+            progress,
+        )
+    })
+}
+
+/// Guess the format of a dataset directory
+///
+/// Args:
+///     dataset_path (str): Path to the dataset directory to inspect
+///
+/// Returns:
+///     list[DetectedFormat]: Candidate formats ranked by confidence, most
+///     likely first; empty when no format left any trace
+#[pyfunction]
+fn detect_dataset_type(dataset_path: String) -> Vec<DetectedFormat> {
+    detect_dataset_format(&PathBuf::from(dataset_path))
+}
+
+/// List the built-in lint rules
+///
+/// Returns:
+///     list[RuleInfo]: Id, severity, description and applicable tasks of each rule
+#[pyfunction]
+fn list_rules() -> Vec<RuleInfo> {
+    RuleRegistry::with_builtin(&LintConfig::default()).infos()
 }
 
 /// Datalint Core Python module
@@ -49,7 +323,20 @@ mod _datalint_core {
 
     // Export functions and classes
     #[pymodule_export]
-    use crate::{create_cache, DatasetTask, DatasetType};
+    use crate::db::models::{Bbox, CacheMetadata, Finding, Image, Label, ScanError};
+    #[pymodule_export]
+    use crate::enums::Severity;
+    #[pymodule_export]
+    use crate::lint::{DuplicateCluster, NearDuplicateGroup};
+    #[pymodule_export]
+    use crate::py_cache::{ArrowTable, Cache};
+    #[pymodule_export]
+    use crate::py_task::CacheTask;
+    #[pymodule_export]
+    use crate::{
+        create_cache, create_cache_async, detect_dataset_type, list_rules, update_cache,
+        update_cache_async, DatasetTask, DatasetType, DetectedFormat, LintConfig, RuleInfo,
+    };
 
     // Module initialization
     #[pymodule_init]
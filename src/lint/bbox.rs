@@ -0,0 +1,342 @@
+//! Bounding box geometry rules
+use super::Rule;
+use crate::db::models::Finding;
+use crate::enums::{DatasetTask, Severity};
+use crate::errors::DatalintResult;
+use duckdb::{params, Connection, Params};
+use serde_json::{json, Value};
+
+/// Tasks whose annotations carry boxes
+pub(crate) const BOX_TASKS: &[DatasetTask] = &[
+    DatasetTask::ObjectDetection,
+    DatasetTask::InstanceSegmentation,
+    DatasetTask::ObbDetection,
+    DatasetTask::PoseEstimation,
+];
+
+/// Pixels a box edge may pass the image border before it is reported,
+/// absorbing rounding from normalized coordinates
+const BOUNDS_TOLERANCE: f64 = 0.5;
+
+const SELECT_BOXES: &str = r#"
+    SELECT b.id, b.image_id, b.x1, b.y1, b.x2, b.y2, b.w, b.h, b.area,
+           i.width, i.height
+    FROM bboxes b
+    JOIN images i ON i.id = b.image_id
+"#;
+
+const OUTSIDE_IMAGE: &str = r#"
+    i.width IS NOT NULL AND i.height IS NOT NULL
+    AND (b.x2 <= 0 OR b.y2 <= 0 OR b.x1 >= i.width OR b.y1 >= i.height)
+"#;
+
+/// A box with the size of its image
+struct BoxGeometry {
+    id: i32,
+    image_id: i32,
+    x1: f64,
+    y1: f64,
+    x2: f64,
+    y2: f64,
+    w: f64,
+    h: f64,
+    area: f64,
+    image_width: Option<i32>,
+    image_height: Option<i32>,
+}
+
+impl BoxGeometry {
+    fn finding(&self, rule: &dyn Rule, message: String, extra: Value) -> Finding {
+        let mut finding = Finding::new(rule.id(), rule.severity(), message);
+        finding.image_id = Some(self.image_id);
+        finding.bbox_id = Some(self.id);
+        finding.extra = Some(extra);
+        finding
+    }
+
+    fn corners(&self) -> Value {
+        json!([self.x1, self.y1, self.x2, self.y2])
+    }
+
+    fn image_size(&self) -> String {
+        match (self.image_width, self.image_height) {
+            (Some(width), Some(height)) => format!("{}x{}", width, height),
+            _ => "unknown size".to_string(),
+        }
+    }
+}
+
+/// Boxes matching a filter over `bboxes b JOIN images i`, in id order
+fn select_boxes(
+    conn: &Connection,
+    filter: &str,
+    params: impl Params,
+) -> DatalintResult<Vec<BoxGeometry>> {
+    let sql = format!("{} WHERE ({}) ORDER BY b.id", SELECT_BOXES, filter);
+    let mut stmt = conn.prepare(&sql)?;
+
+    let results = stmt.query_map(params, |row| {
+        Ok(BoxGeometry {
+            id: row.get(0)?,
+            image_id: row.get(1)?,
+            x1: row.get(2)?,
+            y1: row.get(3)?,
+            x2: row.get(4)?,
+            y2: row.get(5)?,
+            w: row.get(6)?,
+            h: row.get(7)?,
+            area: row.get(8)?,
+            image_width: row.get(9)?,
+            image_height: row.get(10)?,
+        })
+    })?;
+
+    let mut vec = Vec::new();
+    for result in results {
+        vec.push(result?);
+    }
+    Ok(vec)
+}
+
+/// Boxes that cross the image border
+pub struct OutOfBounds;
+
+impl Rule for OutOfBounds {
+    fn id(&self) -> &str {
+        "bbox.out_of_bounds"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn description(&self) -> &str {
+        "Box extends past the image border"
+    }
+
+    fn tasks(&self) -> &[DatasetTask] {
+        BOX_TASKS
+    }
+
+    fn check(&self, conn: &Connection) -> DatalintResult<Vec<Finding>> {
+        let filter = format!(
+            r#"
+            i.width IS NOT NULL AND i.height IS NOT NULL
+            AND (b.x1 < -? OR b.y1 < -? OR b.x2 > i.width + ? OR b.y2 > i.height + ?)
+            AND NOT ({})
+            "#,
+            OUTSIDE_IMAGE
+        );
+        let tolerance = BOUNDS_TOLERANCE;
+        let boxes = select_boxes(
+            conn,
+            &filter,
+            params![tolerance, tolerance, tolerance, tolerance],
+        )?;
+
+        Ok(boxes
+            .iter()
+            .map(|bbox| {
+                let width = bbox.image_width.unwrap_or_default() as f64;
+                let height = bbox.image_height.unwrap_or_default() as f64;
+                let overflow = json!({
+                    "left": (-bbox.x1).max(0.0),
+                    "top": (-bbox.y1).max(0.0),
+                    "right": (bbox.x2 - width).max(0.0),
+                    "bottom": (bbox.y2 - height).max(0.0),
+                });
+                bbox.finding(
+                    self,
+                    format!(
+                        "box {} extends past the border of the {} image",
+                        bbox.id,
+                        bbox.image_size()
+                    ),
+                    json!({ "box": bbox.corners(), "overflow": overflow }),
+                )
+            })
+            .collect())
+    }
+}
+
+/// Boxes with no overlap with their image at all
+pub struct OutsideImage;
+
+impl Rule for OutsideImage {
+    fn id(&self) -> &str {
+        "bbox.outside_image"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn description(&self) -> &str {
+        "Box lies entirely outside the image"
+    }
+
+    fn tasks(&self) -> &[DatasetTask] {
+        BOX_TASKS
+    }
+
+    fn check(&self, conn: &Connection) -> DatalintResult<Vec<Finding>> {
+        let boxes = select_boxes(conn, OUTSIDE_IMAGE, params![])?;
+
+        Ok(boxes
+            .iter()
+            .map(|bbox| {
+                bbox.finding(
+                    self,
+                    format!(
+                        "box {} lies outside the {} image",
+                        bbox.id,
+                        bbox.image_size()
+                    ),
+                    json!({ "box": bbox.corners() }),
+                )
+            })
+            .collect())
+    }
+}
+
+/// Boxes with zero or negative width or height
+pub struct Degenerate;
+
+impl Rule for Degenerate {
+    fn id(&self) -> &str {
+        "bbox.degenerate"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn description(&self) -> &str {
+        "Box has zero or negative width or height"
+    }
+
+    fn tasks(&self) -> &[DatasetTask] {
+        BOX_TASKS
+    }
+
+    fn check(&self, conn: &Connection) -> DatalintResult<Vec<Finding>> {
+        let boxes = select_boxes(conn, "b.x1 >= b.x2 OR b.y1 >= b.y2", params![])?;
+
+        Ok(boxes
+            .iter()
+            .map(|bbox| {
+                bbox.finding(
+                    self,
+                    format!(
+                        "box {} has zero or negative size ({:.1}x{:.1})",
+                        bbox.id, bbox.w, bbox.h
+                    ),
+                    json!({ "box": bbox.corners(), "width": bbox.w, "height": bbox.h }),
+                )
+            })
+            .collect())
+    }
+}
+
+/// Boxes below an absolute or image-relative area
+pub struct Tiny {
+    pub min_area: f64,
+    pub min_relative_area: f64,
+}
+
+impl Rule for Tiny {
+    fn id(&self) -> &str {
+        "bbox.tiny"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn description(&self) -> &str {
+        "Box area is below the configured minimum"
+    }
+
+    fn tasks(&self) -> &[DatasetTask] {
+        BOX_TASKS
+    }
+
+    fn check(&self, conn: &Connection) -> DatalintResult<Vec<Finding>> {
+        // Degenerate boxes are reported by their own rule
+        let filter = r#"
+            b.x1 < b.x2 AND b.y1 < b.y2
+            AND (b.area < ? OR (i.width > 0 AND i.height > 0 AND b.area < ? * i.width * i.height))
+        "#;
+        let boxes = select_boxes(conn, filter, params![self.min_area, self.min_relative_area])?;
+
+        Ok(boxes
+            .iter()
+            .map(|bbox| {
+                let relative_area = match (bbox.image_width, bbox.image_height) {
+                    (Some(width), Some(height)) if width > 0 && height > 0 => {
+                        Some(bbox.area / (width as f64 * height as f64))
+                    }
+                    _ => None,
+                };
+                bbox.finding(
+                    self,
+                    format!("box {} covers only {:.2} px²", bbox.id, bbox.area),
+                    json!({
+                        "area": bbox.area,
+                        "relative_area": relative_area,
+                        "min_area": self.min_area,
+                        "min_relative_area": self.min_relative_area,
+                    }),
+                )
+            })
+            .collect())
+    }
+}
+
+/// Boxes far wider than tall or the other way round
+pub struct ExtremeAspectRatio {
+    pub max_ratio: f64,
+}
+
+impl Rule for ExtremeAspectRatio {
+    fn id(&self) -> &str {
+        "bbox.extreme_aspect_ratio"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn description(&self) -> &str {
+        "Box aspect ratio exceeds the configured maximum"
+    }
+
+    fn tasks(&self) -> &[DatasetTask] {
+        BOX_TASKS
+    }
+
+    fn check(&self, conn: &Connection) -> DatalintResult<Vec<Finding>> {
+        let filter = "b.w > 0 AND b.h > 0 AND GREATEST(b.w / b.h, b.h / b.w) > ?";
+        let boxes = select_boxes(conn, filter, params![self.max_ratio])?;
+
+        Ok(boxes
+            .iter()
+            .map(|bbox| {
+                let ratio = (bbox.w / bbox.h).max(bbox.h / bbox.w);
+                bbox.finding(
+                    self,
+                    format!(
+                        "box {} has aspect ratio {:.1} ({:.1}x{:.1})",
+                        bbox.id, ratio, bbox.w, bbox.h
+                    ),
+                    json!({
+                        "aspect_ratio": ratio,
+                        "width": bbox.w,
+                        "height": bbox.h,
+                        "max_aspect_ratio": self.max_ratio,
+                    }),
+                )
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,94 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// Thresholds used by the configurable rules. Frozen so that every value
+/// goes through the checks in `new`.
+#[pyclass(frozen, get_all)]
+#[derive(Debug, Clone)]
+pub struct LintConfig {
+    /// Boxes below this area in square pixels are reported as tiny
+    pub min_box_area: f64,
+    /// Boxes covering less than this fraction of their image are reported as tiny
+    pub min_box_relative_area: f64,
+    /// Boxes whose longer side exceeds the shorter one by more than this
+    /// factor are reported
+    pub max_aspect_ratio: f64,
+    /// Same-label box pairs at or above this IoU are reported as duplicates
+    pub duplicate_iou: f64,
+    /// Box pairs with different labels at or above this IoU are reported as
+    /// conflicting
+    pub conflict_iou: f64,
+    /// Images whose pHash differs in at most this many bits are reported as
+    /// near-duplicates
+    pub near_duplicate_distance: u32,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            min_box_area: 16.0,
+            min_box_relative_area: 1e-5,
+            max_aspect_ratio: 20.0,
+            duplicate_iou: 0.9,
+            conflict_iou: 0.8,
+            near_duplicate_distance: 6,
+        }
+    }
+}
+
+#[pymethods]
+impl LintConfig {
+    #[new]
+    #[pyo3(signature = (
+        min_box_area=16.0,
+        min_box_relative_area=1e-5,
+        max_aspect_ratio=20.0,
+        duplicate_iou=0.9,
+        conflict_iou=0.8,
+        near_duplicate_distance=6,
+    ))]
+    fn new(
+        min_box_area: f64,
+        min_box_relative_area: f64,
+        max_aspect_ratio: f64,
+        duplicate_iou: f64,
+        conflict_iou: f64,
+        near_duplicate_distance: u32,
+    ) -> PyResult<Self> {
+        if min_box_area < 0.0 || min_box_relative_area < 0.0 {
+            return Err(PyValueError::new_err(
+                "minimum box areas must not be negative",
+            ));
+        }
+        if max_aspect_ratio < 1.0 {
+            return Err(PyValueError::new_err("max_aspect_ratio must be at least 1"));
+        }
+        for iou in [duplicate_iou, conflict_iou] {
+            if !(iou > 0.0 && iou <= 1.0) {
+                return Err(PyValueError::new_err("IoU thresholds must be in (0, 1]"));
+            }
+        }
+
+        Ok(Self {
+            min_box_area,
+            min_box_relative_area,
+            max_aspect_ratio,
+            duplicate_iou,
+            conflict_iou,
+            near_duplicate_distance,
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "LintConfig(min_box_area={:?}, min_box_relative_area={:?}, max_aspect_ratio={:?}, \
+             duplicate_iou={:?}, conflict_iou={:?}, near_duplicate_distance={})",
+            self.min_box_area,
+            self.min_box_relative_area,
+            self.max_aspect_ratio,
+            self.duplicate_iou,
+            self.conflict_iou,
+            self.near_duplicate_distance
+        )
+    }
+}
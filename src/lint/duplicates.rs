@@ -0,0 +1,465 @@
+//! Duplicate images: exact copies matched by file hash, near-duplicates by
+//! perceptual hash distance
+use super::Rule;
+use crate::db::models::{Finding, Image};
+use crate::db::queries::ImageQueries;
+use crate::enums::Severity;
+use crate::errors::{DatalintError, DatalintResult};
+use crate::phash::BkTree;
+use duckdb::Connection;
+use pyo3::prelude::*;
+use serde_json::json;
+
+/// Preference for the copy to keep: evaluation splits first, so removing
+/// duplicates never changes a validation or test set
+const KEEP_ORDER: &[&str] = &["test", "val", "train", "unknown"];
+
+fn split_rank(split: Option<&str>) -> usize {
+    split
+        .and_then(|split| KEEP_ORDER.iter().position(|s| *s == split))
+        .unwrap_or(KEEP_ORDER.len())
+}
+
+/// Images with identical file contents, with a suggested copy to keep
+#[pyclass(frozen, get_all)]
+#[derive(Debug, Clone)]
+pub struct DuplicateCluster {
+    pub file_hash: String,
+    /// Every copy, in id order
+    pub images: Vec<Image>,
+    /// Distinct splits the copies belong to
+    pub splits: Vec<String>,
+    /// Image id to keep
+    pub keep: i32,
+    /// Image ids to remove
+    pub remove: Vec<i32>,
+}
+
+/// Distinct splits of a group of copies, the id to keep and the ids to remove
+fn keep_plan(images: &[Image]) -> (Vec<String>, i32, Vec<i32>) {
+    let mut splits: Vec<String> = Vec::new();
+    for image in images {
+        let split = image.split.clone().unwrap_or_else(|| "unknown".to_string());
+        if !splits.contains(&split) {
+            splits.push(split);
+        }
+    }
+    splits.sort_by_key(|split| split_rank(Some(split)));
+
+    let keep = images
+        .iter()
+        .min_by_key(|image| (split_rank(image.split.as_deref()), image.id))
+        .and_then(|image| image.id)
+        .unwrap_or_default();
+    let remove = images
+        .iter()
+        .filter_map(|image| image.id)
+        .filter(|id| *id != keep)
+        .collect();
+
+    (splits, keep, remove)
+}
+
+fn split_list(splits: &[String]) -> String {
+    let splits: Vec<String> = splits.iter().map(|s| format!("'{}'", s)).collect();
+    format!("[{}]", splits.join(", "))
+}
+
+/// One finding per copy that differs from the kept image's file, pointing at
+/// the kept image
+fn copy_findings(
+    rule: &dyn Rule,
+    images: &[Image],
+    keep: i32,
+    relation: &str,
+    extra: serde_json::Value,
+) -> Vec<Finding> {
+    let Some(kept) = images.iter().find(|image| image.id == Some(keep)) else {
+        return Vec::new();
+    };
+
+    images
+        .iter()
+        .filter(|image| image.id != Some(keep))
+        .map(|image| {
+            let mut finding = Finding::new(
+                rule.id(),
+                rule.severity(),
+                format!(
+                    "{} ({}) is {} {} ({})",
+                    image.path(),
+                    image.split.as_deref().unwrap_or("unknown"),
+                    relation,
+                    kept.path(),
+                    kept.split.as_deref().unwrap_or("unknown"),
+                ),
+            );
+            finding.image_id = image.id;
+            finding.extra = Some(extra.clone());
+            finding.path = Some(image.path());
+            finding
+        })
+        .collect()
+}
+
+impl DuplicateCluster {
+    fn from_images(file_hash: String, images: Vec<Image>) -> Self {
+        let (splits, keep, remove) = keep_plan(&images);
+
+        Self {
+            file_hash,
+            images,
+            splits,
+            keep,
+            remove,
+        }
+    }
+
+    pub fn crosses_splits(&self) -> bool {
+        self.splits.len() > 1
+    }
+
+    /// One finding per copy to remove
+    fn findings(&self, rule: &dyn Rule) -> Vec<Finding> {
+        let extra = json!({
+            "file_hash": self.file_hash,
+            "keep_image_id": self.keep,
+            "splits": self.splits,
+        });
+        copy_findings(rule, &self.images, self.keep, "identical to", extra)
+    }
+}
+
+#[pymethods]
+impl DuplicateCluster {
+    /// Whether the copies span more than one split (train/val leakage)
+    #[getter(crosses_splits)]
+    fn py_crosses_splits(&self) -> bool {
+        self.crosses_splits()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "DuplicateCluster(file_hash='{}', images={}, splits={}, keep={})",
+            self.file_hash,
+            self.images.len(),
+            split_list(&self.splits),
+            self.keep
+        )
+    }
+}
+
+/// Group images sharing a file hash, ordered by hash
+pub fn find_duplicate_images(conn: &Connection) -> DatalintResult<Vec<DuplicateCluster>> {
+    let mut clusters = Vec::new();
+    let mut current: Vec<Image> = Vec::new();
+
+    for image in ImageQueries::get_duplicates(conn)? {
+        if current
+            .last()
+            .is_some_and(|last| last.file_hash != image.file_hash)
+        {
+            let images = std::mem::take(&mut current);
+            clusters.push(DuplicateCluster::from_images(
+                images[0].file_hash.clone(),
+                images,
+            ));
+        }
+        current.push(image);
+    }
+    if !current.is_empty() {
+        clusters.push(DuplicateCluster::from_images(
+            current[0].file_hash.clone(),
+            current,
+        ));
+    }
+
+    Ok(clusters)
+}
+
+/// Identical files within one split
+pub struct DuplicateImage;
+
+impl Rule for DuplicateImage {
+    fn id(&self) -> &str {
+        "image.duplicate"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn description(&self) -> &str {
+        "Image file is an exact copy of another image in the same split"
+    }
+
+    fn check(&self, conn: &Connection) -> DatalintResult<Vec<Finding>> {
+        Ok(find_duplicate_images(conn)?
+            .iter()
+            .filter(|cluster| !cluster.crosses_splits())
+            .flat_map(|cluster| cluster.findings(self))
+            .collect())
+    }
+}
+
+/// Identical files in different splits
+pub struct SplitLeakage;
+
+impl Rule for SplitLeakage {
+    fn id(&self) -> &str {
+        "image.split_leakage"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn description(&self) -> &str {
+        "Image file also appears in another split"
+    }
+
+    fn check(&self, conn: &Connection) -> DatalintResult<Vec<Finding>> {
+        Ok(find_duplicate_images(conn)?
+            .iter()
+            .filter(|cluster| cluster.crosses_splits())
+            .flat_map(|cluster| cluster.findings(self))
+            .collect())
+    }
+}
+
+/// Visually similar images, linked by perceptual hash distance
+#[pyclass(frozen, get_all)]
+#[derive(Debug, Clone)]
+pub struct NearDuplicateGroup {
+    /// Every member, in id order
+    pub images: Vec<Image>,
+    /// Distinct splits the members belong to
+    pub splits: Vec<String>,
+    /// Largest Hamming distance of a link within the group
+    pub distance: u32,
+    /// Image id to keep
+    pub keep: i32,
+    /// Image ids to remove
+    pub remove: Vec<i32>,
+}
+
+impl NearDuplicateGroup {
+    pub fn crosses_splits(&self) -> bool {
+        self.splits.len() > 1
+    }
+
+    /// One finding per member that is not a byte copy of the kept image,
+    /// exact copies being reported by the file hash rules
+    fn findings(&self, rule: &dyn Rule) -> Vec<Finding> {
+        let kept_hash = self
+            .images
+            .iter()
+            .find(|image| image.id == Some(self.keep))
+            .map(|image| image.file_hash.as_str());
+        let images: Vec<Image> = self
+            .images
+            .iter()
+            .filter(|image| {
+                image.id == Some(self.keep) || Some(image.file_hash.as_str()) != kept_hash
+            })
+            .cloned()
+            .collect();
+
+        let extra = json!({
+            "keep_image_id": self.keep,
+            "distance": self.distance,
+            "splits": self.splits,
+        });
+        copy_findings(rule, &images, self.keep, "a near-duplicate of", extra)
+    }
+}
+
+#[pymethods]
+impl NearDuplicateGroup {
+    /// Whether the members span more than one split
+    #[getter(crosses_splits)]
+    fn py_crosses_splits(&self) -> bool {
+        self.crosses_splits()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "NearDuplicateGroup(images={}, splits={}, distance={}, keep={})",
+            self.images.len(),
+            split_list(&self.splits),
+            self.distance,
+            self.keep
+        )
+    }
+}
+
+/// Perceptual hash of an image by kind: `ahash`, `dhash` or `phash`
+fn perceptual_hash(image: &Image, kind: &str) -> DatalintResult<Option<u64>> {
+    let hash = match kind {
+        "ahash" => image.ahash,
+        "dhash" => image.dhash,
+        "phash" => image.phash,
+        other => {
+            return Err(DatalintError::Core(format!(
+                "Unknown hash '{}', expected one of: ahash, dhash, phash",
+                other
+            )))
+        }
+    };
+    Ok(hash.map(|hash| hash as u64))
+}
+
+fn find_root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+/// Group images whose perceptual hashes are within `max_distance` bits,
+/// transitively. Only groups holding at least two distinct files are kept;
+/// images cached without perceptual hashes are ignored.
+pub fn find_near_duplicate_images(
+    conn: &Connection,
+    kind: &str,
+    max_distance: u32,
+) -> DatalintResult<Vec<NearDuplicateGroup>> {
+    let mut images = Vec::new();
+    let mut hashes = Vec::new();
+    for image in ImageQueries::get_all(conn)? {
+        if let Some(hash) = perceptual_hash(&image, kind)? {
+            images.push(image);
+            hashes.push(hash);
+        }
+    }
+
+    let mut tree = BkTree::new();
+    let mut parents: Vec<usize> = (0..images.len()).collect();
+    let mut link_distance = vec![0u32; images.len()];
+    for (i, &hash) in hashes.iter().enumerate() {
+        for (j, distance) in tree.find(hash, max_distance) {
+            let (a, b) = (find_root(&mut parents, i), find_root(&mut parents, j));
+            let widest = distance.max(link_distance[a]).max(link_distance[b]);
+            if a != b {
+                parents[a] = b;
+            }
+            link_distance[b] = widest;
+        }
+        tree.insert(hash, i);
+    }
+
+    let mut members: Vec<Vec<usize>> = vec![Vec::new(); images.len()];
+    for i in 0..images.len() {
+        let root = find_root(&mut parents, i);
+        members[root].push(i);
+    }
+
+    let mut groups = Vec::new();
+    for (root, indices) in members.into_iter().enumerate() {
+        let group: Vec<Image> = indices.iter().map(|&i| images[i].clone()).collect();
+        let first_hash = group.first().map(|image| &image.file_hash);
+        if group
+            .iter()
+            .all(|image| Some(&image.file_hash) == first_hash)
+        {
+            continue;
+        }
+
+        let (splits, keep, remove) = keep_plan(&group);
+        groups.push(NearDuplicateGroup {
+            images: group,
+            splits,
+            distance: link_distance[root],
+            keep,
+            remove,
+        });
+    }
+
+    groups.sort_by_key(|group| group.images.first().and_then(|image| image.id));
+    Ok(groups)
+}
+
+/// Info finding when readable images were cached without a pHash, since
+/// near-duplicate rules cannot compare them and would otherwise stay silent
+fn missing_phash_finding(rule: &dyn Rule, conn: &Connection) -> DatalintResult<Option<Finding>> {
+    let (missing, images) = ImageQueries::count_without_phash(conn)?;
+    if missing == 0 {
+        return Ok(None);
+    }
+
+    let mut finding = Finding::new(
+        rule.id(),
+        Severity::Info,
+        format!(
+            "{} of {} images have no perceptual hash and were not compared; \
+             rescan with perceptual hashes enabled",
+            missing, images
+        ),
+    );
+    finding.extra = Some(json!({ "missing": missing, "images": images }));
+    Ok(Some(finding))
+}
+
+/// Near-duplicate groups routed to a rule, after the missing hash notice
+fn near_duplicate_findings(
+    rule: &dyn Rule,
+    conn: &Connection,
+    max_distance: u32,
+    crosses_splits: bool,
+) -> DatalintResult<Vec<Finding>> {
+    let mut findings: Vec<Finding> = missing_phash_finding(rule, conn)?.into_iter().collect();
+    findings.extend(
+        find_near_duplicate_images(conn, "phash", max_distance)?
+            .iter()
+            .filter(|group| group.crosses_splits() == crosses_splits)
+            .flat_map(|group| group.findings(rule)),
+    );
+    Ok(findings)
+}
+
+/// Visually similar images within one split
+pub struct NearDuplicate {
+    pub max_distance: u32,
+}
+
+impl Rule for NearDuplicate {
+    fn id(&self) -> &str {
+        "image.near_duplicate"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn description(&self) -> &str {
+        "Image is a resized, recompressed or slightly edited copy of another image in the same split"
+    }
+
+    fn check(&self, conn: &Connection) -> DatalintResult<Vec<Finding>> {
+        near_duplicate_findings(self, conn, self.max_distance, false)
+    }
+}
+
+/// Visually similar images in different splits
+pub struct NearDuplicateLeakage {
+    pub max_distance: u32,
+}
+
+impl Rule for NearDuplicateLeakage {
+    fn id(&self) -> &str {
+        "image.near_duplicate_leakage"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn description(&self) -> &str {
+        "Image has a near-duplicate in another split"
+    }
+
+    fn check(&self, conn: &Connection) -> DatalintResult<Vec<Finding>> {
+        near_duplicate_findings(self, conn, self.max_distance, true)
+    }
+}
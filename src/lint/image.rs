@@ -0,0 +1,17 @@
+//! Image file rules
+use super::SqlRule;
+use crate::enums::Severity;
+
+/// Files the scanner could not decode
+pub const CORRUPTED_IMAGE: SqlRule = SqlRule {
+    id: "image.corrupted",
+    severity: Severity::Error,
+    description: "Image file cannot be decoded",
+    tasks: &[],
+    sql: r#"
+        SELECT id, NULL, 'image cannot be decoded: ' || filename, NULL,
+               image_path(relative_path, filename)
+        FROM images WHERE is_corrupted = 1
+        ORDER BY id
+    "#,
+};
@@ -0,0 +1,55 @@
+//! Label file rules for formats with one annotation file per image (YOLO, VOC)
+use super::bbox::BOX_TASKS;
+use super::SqlRule;
+use crate::enums::Severity;
+
+/// Label files no image resolved to
+pub const ORPHAN_LABEL_FILE: SqlRule = SqlRule {
+    id: "label.orphan_file",
+    severity: Severity::Warning,
+    description: "Label file has no matching image",
+    tasks: BOX_TASKS,
+    sql: r#"
+        SELECT NULL, NULL, 'label file has no matching image: ' || path,
+               '{"annotation_count": ' || annotation_count || '}', path
+        FROM label_files WHERE image_id IS NULL
+        ORDER BY path
+    "#,
+};
+
+/// Label files without a single annotation line or object
+pub const EMPTY_LABEL_FILE: SqlRule = SqlRule {
+    id: "label.empty_file",
+    severity: Severity::Info,
+    description: "Label file is empty, its image is treated as background",
+    tasks: BOX_TASKS,
+    sql: r#"
+        SELECT image_id, NULL, 'empty label file, image treated as background: ' || path,
+               NULL, path
+        FROM label_files WHERE annotation_count = 0 AND image_id IS NOT NULL
+        ORDER BY path
+    "#,
+};
+
+/// Images without a label file. Images under a directory or with a name
+/// marking them as background or negatives are intentional and skipped, as
+/// are corrupted images, which have their own rule.
+pub const MISSING_LABEL_FILE: SqlRule = SqlRule {
+    id: "label.missing_file",
+    severity: Severity::Warning,
+    description: "Image has no label file and is not marked as background",
+    tasks: BOX_TASKS,
+    sql: r#"
+        SELECT i.id, NULL, 'no label file for ' || image_path(i.relative_path, i.filename),
+               NULL, image_path(i.relative_path, i.filename)
+        FROM images i
+        JOIN cache_metadata m ON m.id = 1 AND m.dataset_type IN ('yolo', 'voc')
+        WHERE i.is_corrupted = 0
+          AND NOT EXISTS (SELECT 1 FROM label_files f WHERE f.image_id = i.id)
+          AND NOT regexp_matches(
+              lower(image_path(i.relative_path, i.name)),
+              '(^|[/\\_.-])(backgrounds?|negatives?|bg)([/\\_.-]|$)'
+          )
+        ORDER BY i.id
+    "#,
+};
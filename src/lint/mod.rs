@@ -0,0 +1,129 @@
+//! Lint rules over a cache database
+//!
+//! A rule inspects the cache and returns findings; the runner replaces the
+//! rule's previous rows in the `findings` table with them. Rules are either
+//! a single SQL query (`SqlRule`) or any Rust type implementing `Rule`.
+pub mod bbox;
+pub mod config;
+pub mod duplicates;
+pub mod image;
+pub mod label_files;
+pub mod overlap;
+pub mod registry;
+pub mod runner;
+
+pub use config::LintConfig;
+pub use duplicates::{
+    find_duplicate_images, find_near_duplicate_images, DuplicateCluster, NearDuplicateGroup,
+};
+pub use registry::RuleRegistry;
+pub use runner::{run_rules, LintSummary};
+
+use crate::db::models::Finding;
+use crate::enums::{DatasetTask, Severity};
+use crate::errors::DatalintResult;
+use duckdb::{params, Connection};
+use pyo3::prelude::*;
+
+/// A check over the cache that reports findings
+pub trait Rule: Send + Sync {
+    /// Stable identifier, e.g. `image.corrupted`
+    fn id(&self) -> &str;
+
+    fn severity(&self) -> Severity;
+
+    fn description(&self) -> &str;
+
+    /// Tasks the rule applies to, empty for every task
+    fn tasks(&self) -> &[DatasetTask] {
+        &[]
+    }
+
+    fn applies_to(&self, task: &DatasetTask) -> bool {
+        self.tasks().is_empty() || self.tasks().contains(task)
+    }
+
+    /// Inspect the cache and return this rule's findings
+    fn check(&self, conn: &Connection) -> DatalintResult<Vec<Finding>>;
+}
+
+/// A rule backed by one query returning `image_id, bbox_id, message, extra, path`
+/// rows, where all but the message may be NULL (extra is JSON text)
+pub struct SqlRule {
+    pub id: &'static str,
+    pub severity: Severity,
+    pub description: &'static str,
+    pub tasks: &'static [DatasetTask],
+    pub sql: &'static str,
+}
+
+impl Rule for SqlRule {
+    fn id(&self) -> &str {
+        self.id
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    fn description(&self) -> &str {
+        self.description
+    }
+
+    fn tasks(&self) -> &[DatasetTask] {
+        self.tasks
+    }
+
+    fn check(&self, conn: &Connection) -> DatalintResult<Vec<Finding>> {
+        let mut stmt = conn.prepare(self.sql)?;
+
+        let results = stmt.query_map(params![], |row| {
+            let mut finding = Finding::new(self.id, self.severity, row.get::<_, String>(2)?);
+            finding.image_id = row.get(0)?;
+            finding.bbox_id = row.get(1)?;
+            finding.extra = row
+                .get::<_, Option<String>>(3)?
+                .and_then(|e| serde_json::from_str(&e).ok());
+            finding.path = row.get(4)?;
+            Ok(finding)
+        })?;
+
+        let mut vec = Vec::new();
+        for result in results {
+            vec.push(result?);
+        }
+        Ok(vec)
+    }
+}
+
+/// Description of a registered rule
+#[pyclass(frozen, get_all)]
+#[derive(Debug, Clone)]
+pub struct RuleInfo {
+    pub id: String,
+    pub severity: Severity,
+    pub description: String,
+    pub tasks: Vec<String>,
+}
+
+impl RuleInfo {
+    pub fn from_rule(rule: &dyn Rule) -> Self {
+        Self {
+            id: rule.id().to_string(),
+            severity: rule.severity(),
+            description: rule.description().to_string(),
+            tasks: rule
+                .tasks()
+                .iter()
+                .map(|task| task.as_str().to_string())
+                .collect(),
+        }
+    }
+}
+
+#[pymethods]
+impl RuleInfo {
+    fn __repr__(&self) -> String {
+        format!("RuleInfo(id='{}', severity={})", self.id, self.severity)
+    }
+}
@@ -0,0 +1,223 @@
+//! Overlapping box rules
+//!
+//! Pairs are found per image with a sweep along x: boxes are visited by left
+//! edge and only compared with those whose right edge has not been passed, so
+//! images with thousands of scattered boxes stay far from quadratic. Rotated
+//! boxes are compared by their axis-aligned envelope.
+use super::bbox::BOX_TASKS;
+use super::Rule;
+use crate::db::models::Finding;
+use crate::enums::{DatasetTask, Severity};
+use crate::errors::DatalintResult;
+use duckdb::{params, Connection};
+use serde_json::json;
+
+const SELECT_BOXES: &str = r#"
+    SELECT b.id, b.image_id, b.label_id, l.name, b.x1, b.y1, b.x2, b.y2
+    FROM bboxes b
+    JOIN labels l ON l.id = b.label_id
+    WHERE b.x1 < b.x2 AND b.y1 < b.y2
+    ORDER BY b.image_id, b.x1, b.id
+"#;
+
+struct SweepBox {
+    id: i32,
+    image_id: i32,
+    label_id: i32,
+    label: String,
+    x1: f64,
+    y1: f64,
+    x2: f64,
+    y2: f64,
+}
+
+impl SweepBox {
+    fn area(&self) -> f64 {
+        (self.x2 - self.x1) * (self.y2 - self.y1)
+    }
+
+    fn iou(&self, other: &SweepBox) -> f64 {
+        let w = self.x2.min(other.x2) - self.x1.max(other.x1);
+        let h = self.y2.min(other.y2) - self.y1.max(other.y1);
+        if w <= 0.0 || h <= 0.0 {
+            return 0.0;
+        }
+        let intersection = w * h;
+        intersection / (self.area() + other.area() - intersection)
+    }
+}
+
+/// Two boxes of one image, `first` having the lower id
+struct OverlapPair<'a> {
+    first: &'a SweepBox,
+    second: &'a SweepBox,
+    iou: f64,
+}
+
+/// Calls `visit` with the valid boxes of each image in turn, sorted by left
+/// edge, so only one image's boxes are held at a time
+fn for_each_image(conn: &Connection, mut visit: impl FnMut(&[SweepBox])) -> DatalintResult<()> {
+    let mut stmt = conn.prepare(SELECT_BOXES)?;
+    let mut rows = stmt.query(params![])?;
+
+    let mut boxes: Vec<SweepBox> = Vec::new();
+    while let Some(row) = rows.next()? {
+        let current = SweepBox {
+            id: row.get(0)?,
+            image_id: row.get(1)?,
+            label_id: row.get(2)?,
+            label: row.get(3)?,
+            x1: row.get(4)?,
+            y1: row.get(5)?,
+            x2: row.get(6)?,
+            y2: row.get(7)?,
+        };
+        if boxes
+            .first()
+            .is_some_and(|first| first.image_id != current.image_id)
+        {
+            visit(&boxes);
+            boxes.clear();
+        }
+        boxes.push(current);
+    }
+    if !boxes.is_empty() {
+        visit(&boxes);
+    }
+    Ok(())
+}
+
+/// Pairs of one image's boxes reaching `min_iou`, keeping those `accept` allows
+fn overlapping_pairs<'a>(
+    boxes: &'a [SweepBox],
+    min_iou: f64,
+    accept: impl Fn(&SweepBox, &SweepBox) -> bool,
+) -> Vec<OverlapPair<'a>> {
+    let mut pairs = Vec::new();
+    let mut active: Vec<&SweepBox> = Vec::new();
+
+    for current in boxes {
+        // Boxes ending left of this one cannot overlap it or any later one
+        active.retain(|other| other.x2 > current.x1);
+
+        for &other in &active {
+            if !accept(other, current) {
+                continue;
+            }
+            let iou = other.iou(current);
+            if iou >= min_iou {
+                let (first, second) = if other.id < current.id {
+                    (other, current)
+                } else {
+                    (current, other)
+                };
+                pairs.push(OverlapPair { first, second, iou });
+            }
+        }
+        active.push(current);
+    }
+
+    pairs.sort_by_key(|pair| (pair.second.id, pair.first.id));
+    pairs
+}
+
+/// Same-label boxes covering nearly the same region
+pub struct Duplicate {
+    pub min_iou: f64,
+}
+
+impl Rule for Duplicate {
+    fn id(&self) -> &str {
+        "bbox.duplicate"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn description(&self) -> &str {
+        "Two boxes with the same label overlap above the duplicate IoU"
+    }
+
+    fn tasks(&self) -> &[DatasetTask] {
+        BOX_TASKS
+    }
+
+    fn check(&self, conn: &Connection) -> DatalintResult<Vec<Finding>> {
+        let mut findings = Vec::new();
+        for_each_image(conn, |boxes| {
+            let pairs = overlapping_pairs(boxes, self.min_iou, |a, b| a.label_id == b.label_id);
+            findings.extend(pairs.iter().map(|pair| {
+                let mut finding = Finding::new(
+                    self.id(),
+                    self.severity(),
+                    format!(
+                        "box {} duplicates box {} ('{}', IoU {:.2})",
+                        pair.second.id, pair.first.id, pair.second.label, pair.iou
+                    ),
+                );
+                finding.image_id = Some(pair.second.image_id);
+                finding.bbox_id = Some(pair.second.id);
+                finding.extra = Some(json!({
+                    "other_bbox_id": pair.first.id,
+                    "iou": pair.iou,
+                }));
+                finding
+            }));
+        })?;
+        Ok(findings)
+    }
+}
+
+/// Boxes with different labels covering nearly the same region
+pub struct LabelConflict {
+    pub min_iou: f64,
+}
+
+impl Rule for LabelConflict {
+    fn id(&self) -> &str {
+        "bbox.label_conflict"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn description(&self) -> &str {
+        "Two boxes with different labels overlap above the conflict IoU"
+    }
+
+    fn tasks(&self) -> &[DatasetTask] {
+        BOX_TASKS
+    }
+
+    fn check(&self, conn: &Connection) -> DatalintResult<Vec<Finding>> {
+        let mut findings = Vec::new();
+        for_each_image(conn, |boxes| {
+            let pairs = overlapping_pairs(boxes, self.min_iou, |a, b| a.label_id != b.label_id);
+            findings.extend(pairs.iter().map(|pair| {
+                let mut finding = Finding::new(
+                    self.id(),
+                    self.severity(),
+                    format!(
+                        "box {} ('{}') overlaps box {} ('{}') with IoU {:.2}",
+                        pair.second.id,
+                        pair.second.label,
+                        pair.first.id,
+                        pair.first.label,
+                        pair.iou
+                    ),
+                );
+                finding.image_id = Some(pair.second.image_id);
+                finding.bbox_id = Some(pair.second.id);
+                finding.extra = Some(json!({
+                    "other_bbox_id": pair.first.id,
+                    "other_label": pair.first.label,
+                    "iou": pair.iou,
+                }));
+                finding
+            }));
+        })?;
+        Ok(findings)
+    }
+}
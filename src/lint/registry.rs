@@ -0,0 +1,94 @@
+use super::{bbox, duplicates, image, label_files, overlap, LintConfig, Rule, RuleInfo};
+use crate::enums::DatasetTask;
+use crate::errors::{DatalintError, DatalintResult};
+
+/// Ordered set of rules, looked up by id
+#[derive(Default)]
+pub struct RuleRegistry {
+    rules: Vec<Box<dyn Rule>>,
+}
+
+impl RuleRegistry {
+    /// An empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry holding every built-in rule, with thresholds from `config`
+    pub fn with_builtin(config: &LintConfig) -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(image::CORRUPTED_IMAGE));
+        registry.register(Box::new(duplicates::DuplicateImage));
+        registry.register(Box::new(duplicates::SplitLeakage));
+        registry.register(Box::new(duplicates::NearDuplicate {
+            max_distance: config.near_duplicate_distance,
+        }));
+        registry.register(Box::new(duplicates::NearDuplicateLeakage {
+            max_distance: config.near_duplicate_distance,
+        }));
+        registry.register(Box::new(label_files::ORPHAN_LABEL_FILE));
+        registry.register(Box::new(label_files::EMPTY_LABEL_FILE));
+        registry.register(Box::new(label_files::MISSING_LABEL_FILE));
+        registry.register(Box::new(bbox::OutOfBounds));
+        registry.register(Box::new(bbox::OutsideImage));
+        registry.register(Box::new(bbox::Degenerate));
+        registry.register(Box::new(bbox::Tiny {
+            min_area: config.min_box_area,
+            min_relative_area: config.min_box_relative_area,
+        }));
+        registry.register(Box::new(bbox::ExtremeAspectRatio {
+            max_ratio: config.max_aspect_ratio,
+        }));
+        registry.register(Box::new(overlap::Duplicate {
+            min_iou: config.duplicate_iou,
+        }));
+        registry.register(Box::new(overlap::LabelConflict {
+            min_iou: config.conflict_iou,
+        }));
+        registry
+    }
+
+    /// Add a rule; ids must be unique
+    pub fn register(&mut self, rule: Box<dyn Rule>) {
+        assert!(
+            self.get(rule.id()).is_none(),
+            "rule '{}' registered twice",
+            rule.id()
+        );
+        self.rules.push(rule);
+    }
+
+    pub fn get(&self, id: &str) -> Option<&dyn Rule> {
+        self.rules
+            .iter()
+            .find(|rule| rule.id() == id)
+            .map(|rule| rule.as_ref())
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &dyn Rule> {
+        self.rules.iter().map(|rule| rule.as_ref())
+    }
+
+    /// Rules to run for a task, optionally narrowed to the given ids
+    pub fn select(
+        &self,
+        task: &DatasetTask,
+        ids: Option<&[String]>,
+    ) -> DatalintResult<Vec<&dyn Rule>> {
+        let Some(ids) = ids else {
+            return Ok(self.rules().filter(|rule| rule.applies_to(task)).collect());
+        };
+
+        ids.iter()
+            .map(|id| {
+                self.get(id)
+                    .ok_or_else(|| DatalintError::Core(format!("Unknown rule '{}'", id)))
+            })
+            .collect()
+    }
+
+    /// Descriptions of every registered rule
+    pub fn infos(&self) -> Vec<RuleInfo> {
+        self.rules().map(RuleInfo::from_rule).collect()
+    }
+}
@@ -0,0 +1,51 @@
+use super::RuleRegistry;
+use crate::db::queries::FindingQueries;
+use crate::db::Database;
+use crate::enums::DatasetTask;
+use crate::errors::DatalintResult;
+use crate::progress::{ProgressSink, Stage};
+
+/// Outcome of a lint run
+#[derive(Debug, Clone, Default)]
+pub struct LintSummary {
+    /// Findings per rule, in run order
+    pub by_rule: Vec<(String, usize)>,
+}
+
+impl LintSummary {
+    pub fn total(&self) -> usize {
+        self.by_rule.iter().map(|(_, count)| count).sum()
+    }
+}
+
+/// Run the rules applicable to a task (or exactly the given ids) and store
+/// their findings, replacing what each rule reported before. Cancelling
+/// through `progress` keeps the findings of the rules that already ran.
+pub fn run_rules(
+    db: &mut Database,
+    registry: &RuleRegistry,
+    task: &DatasetTask,
+    ids: Option<&[String]>,
+    progress: &dyn ProgressSink,
+) -> DatalintResult<LintSummary> {
+    let mut summary = LintSummary::default();
+
+    let rules = registry.select(task, ids)?;
+    for (i, rule) in rules.iter().enumerate() {
+        progress.update(Stage::Lint, i, Some(rules.len()))?;
+
+        let mut findings = rule.check(db.conn())?;
+
+        let tx = db.transaction()?;
+        FindingQueries::delete_by_rule(&tx, rule.id())?;
+        FindingQueries::append(&tx, &mut findings)?;
+        tx.commit()?;
+
+        summary
+            .by_rule
+            .push((rule.id().to_string(), findings.len()));
+    }
+    progress.update(Stage::Lint, rules.len(), Some(rules.len()))?;
+
+    Ok(summary)
+}
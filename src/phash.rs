@@ -0,0 +1,156 @@
+//! Perceptual image hashes and Hamming-distance search
+//!
+//! All hashes are 64 bits, row-major with the first bit in the most
+//! significant position. They are stored as signed BIGINT columns, so the
+//! bits round-trip through `as i64` / `as u64`.
+
+use image::imageops::FilterType;
+use image::DynamicImage;
+use std::f64::consts::PI;
+
+/// Average hash: 8x8 grayscale thumbnail, bit set where brighter than the mean
+pub fn ahash(img: &DynamicImage) -> u64 {
+    let pixels = img.resize_exact(8, 8, FilterType::Triangle).to_luma8();
+    let mean = pixels.iter().map(|&p| p as u32).sum::<u32>() / 64;
+    bits(pixels.iter().map(|&p| p as u32 > mean))
+}
+
+/// Difference hash: 9x8 grayscale thumbnail, bit set where a pixel is darker
+/// than its right neighbour
+pub fn dhash(img: &DynamicImage) -> u64 {
+    let pixels = img.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    bits((0..8).flat_map(|y| {
+        let pixels = &pixels;
+        (0..8).map(move |x| pixels.get_pixel(x, y)[0] < pixels.get_pixel(x + 1, y)[0])
+    }))
+}
+
+/// DCT hash: 32x32 grayscale thumbnail, bit set where a low-frequency DCT
+/// coefficient exceeds their median
+pub fn phash(img: &DynamicImage) -> u64 {
+    const SIZE: usize = 32;
+    let pixels = img
+        .resize_exact(SIZE as u32, SIZE as u32, FilterType::Triangle)
+        .to_luma8();
+    let values: Vec<f64> = pixels.iter().map(|&p| p as f64).collect();
+
+    // Separable 2D DCT-II, only the top-left 8x8 coefficients are needed
+    let cos: Vec<f64> = (0..8 * SIZE)
+        .map(|i| {
+            let (u, x) = (i / SIZE, i % SIZE);
+            ((2 * x + 1) as f64 * u as f64 * PI / (2 * SIZE) as f64).cos()
+        })
+        .collect();
+
+    let mut rows = vec![0.0; 8 * SIZE];
+    for y in 0..SIZE {
+        for u in 0..8 {
+            rows[u * SIZE + y] = (0..SIZE)
+                .map(|x| values[y * SIZE + x] * cos[u * SIZE + x])
+                .sum();
+        }
+    }
+
+    let mut coefficients = [0.0; 64];
+    for v in 0..8 {
+        for u in 0..8 {
+            coefficients[v * 8 + u] = (0..SIZE)
+                .map(|y| rows[u * SIZE + y] * cos[v * SIZE + y])
+                .sum();
+        }
+    }
+
+    // The DC term only carries overall brightness
+    let mut sorted: Vec<f64> = coefficients[1..].to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    bits(coefficients.iter().map(|&c| c > median))
+}
+
+fn bits(values: impl Iterator<Item = bool>) -> u64 {
+    values.fold(0u64, |hash, bit| (hash << 1) | bit as u64)
+}
+
+/// Number of differing bits
+pub fn hamming(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+struct Node {
+    hash: u64,
+    item: usize,
+    children: Vec<(u32, usize)>,
+}
+
+/// BK-tree over 64-bit hashes for radius queries under Hamming distance
+#[derive(Default)]
+pub struct BkTree {
+    nodes: Vec<Node>,
+}
+
+impl BkTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a hash, tagged with the caller's item index
+    pub fn insert(&mut self, hash: u64, item: usize) {
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            hash,
+            item,
+            children: Vec::new(),
+        });
+        if index == 0 {
+            return;
+        }
+
+        let mut current = 0;
+        loop {
+            let distance = hamming(self.nodes[current].hash, hash);
+            match self.nodes[current]
+                .children
+                .iter()
+                .find(|(d, _)| *d == distance)
+            {
+                Some(&(_, child)) => current = child,
+                None => {
+                    self.nodes[current].children.push((distance, index));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Items within `max_distance` of `hash`, with their distance
+    pub fn find(&self, hash: u64, max_distance: u32) -> Vec<(usize, u32)> {
+        let mut found = Vec::new();
+        if self.nodes.is_empty() {
+            return found;
+        }
+
+        let mut stack = vec![0];
+        while let Some(current) = stack.pop() {
+            let node = &self.nodes[current];
+            let distance = hamming(node.hash, hash);
+            if distance <= max_distance {
+                found.push((node.item, distance));
+            }
+            // Triangle inequality: only subtrees at |d - distance| <= max can match
+            for &(d, child) in &node.children {
+                if d.abs_diff(distance) <= max_distance {
+                    stack.push(child);
+                }
+            }
+        }
+        found
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
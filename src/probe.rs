@@ -0,0 +1,306 @@
+//! Image size and color type read from the container header
+//!
+//! Parsing the header touches a few hundred bytes at most, where decoding
+//! inflates every pixel. The reported color type is the one `image` decodes
+//! to, so a probed image is cached exactly like a decoded one. Variants the
+//! `image` decoders reject or convert unusually (12-bit or lossless JPEG,
+//! indexed or CMYK TIFF, ...) are not probed, leaving them to a full decode.
+
+use image::ColorType;
+
+/// Size and color type declared by an image header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageHeader {
+    pub width: u32,
+    pub height: u32,
+    pub color: ColorType,
+}
+
+/// Read the header of a JPEG, PNG, WebP, BMP or TIFF file. Returns `None` for
+/// other formats and for truncated or unsupported headers.
+pub fn probe(data: &[u8]) -> Option<ImageHeader> {
+    let header = if data.starts_with(&[0xFF, 0xD8]) {
+        jpeg(data)
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png(data)
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        webp(data)
+    } else if data.starts_with(b"BM") {
+        bmp(data)
+    } else if data.starts_with(b"II*\0") || data.starts_with(b"MM\0*") {
+        tiff(data)
+    } else {
+        None
+    }?;
+
+    (header.width > 0 && header.height > 0).then_some(header)
+}
+
+fn bytes<const N: usize>(data: &[u8], offset: usize) -> Option<[u8; N]> {
+    data.get(offset..offset.checked_add(N)?)?.try_into().ok()
+}
+
+fn be16(data: &[u8], offset: usize) -> Option<u16> {
+    bytes(data, offset).map(u16::from_be_bytes)
+}
+
+fn be32(data: &[u8], offset: usize) -> Option<u32> {
+    bytes(data, offset).map(u32::from_be_bytes)
+}
+
+fn le16(data: &[u8], offset: usize) -> Option<u16> {
+    bytes(data, offset).map(u16::from_le_bytes)
+}
+
+fn le24(data: &[u8], offset: usize) -> Option<u32> {
+    let [a, b, c] = bytes(data, offset)?;
+    Some(u32::from_le_bytes([a, b, c, 0]))
+}
+
+fn le32(data: &[u8], offset: usize) -> Option<u32> {
+    bytes(data, offset).map(u32::from_le_bytes)
+}
+
+/// Walk the marker segments up to the first frame header (SOFn)
+fn jpeg(data: &[u8]) -> Option<ImageHeader> {
+    let mut pos = 2;
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        // Any number of fill bytes may precede a marker
+        while *data.get(pos)? == 0xFF {
+            pos += 1;
+        }
+        let marker = data[pos];
+        pos += 1;
+
+        match marker {
+            // Standalone markers carry no length
+            0x01 | 0xD0..=0xD8 => continue,
+            // Baseline, extended sequential and progressive Huffman frames
+            0xC0..=0xC2 => {
+                let precision = *data.get(pos + 2)?;
+                let height = be16(data, pos + 3)?;
+                let width = be16(data, pos + 5)?;
+                let color = match *data.get(pos + 7)? {
+                    1 => ColorType::L8,
+                    // YCbCr, CMYK and YCCK are all decoded to RGB
+                    3 | 4 => ColorType::Rgb8,
+                    _ => return None,
+                };
+                // A height of 0 is only resolved by a DNL marker after the scan
+                return (precision == 8).then_some(ImageHeader {
+                    width: width as u32,
+                    height: height as u32,
+                    color,
+                });
+            }
+            // Other frame types, or image data before any frame header
+            0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF | 0xD9 | 0xDA => return None,
+            _ => {}
+        }
+
+        let length = be16(data, pos)? as usize;
+        if length < 2 {
+            return None;
+        }
+        pos += length;
+    }
+}
+
+/// IHDR, then the chunks before the first IDAT for a transparency chunk
+fn png(data: &[u8]) -> Option<ImageHeader> {
+    if data.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let width = be32(data, 16)?;
+    let height = be32(data, 20)?;
+    let bit_depth = *data.get(24)?;
+    let color_type = *data.get(25)?;
+
+    let mut pos = 8;
+    let mut transparency = false;
+    loop {
+        let length = be32(data, pos)? as usize;
+        match data.get(pos + 4..pos + 8)? {
+            b"tRNS" => transparency = true,
+            b"IDAT" => break,
+            _ => {}
+        }
+        pos = pos.checked_add(length)?.checked_add(12)?;
+    }
+
+    // Depths below 8 are expanded, palettes to RGB, tRNS to an alpha channel
+    let wide = match bit_depth {
+        1 | 2 | 4 | 8 => false,
+        16 => true,
+        _ => return None,
+    };
+    let color = match (color_type, wide, transparency) {
+        (0, false, false) => ColorType::L8,
+        (0, true, false) => ColorType::L16,
+        (0, false, true) | (4, false, _) => ColorType::La8,
+        (0, true, true) | (4, true, _) => ColorType::La16,
+        (2, false, false) | (3, false, false) => ColorType::Rgb8,
+        (2, true, false) => ColorType::Rgb16,
+        (2, false, true) | (3, false, true) | (6, false, _) => ColorType::Rgba8,
+        (2, true, true) | (6, true, _) => ColorType::Rgba16,
+        _ => return None,
+    };
+
+    Some(ImageHeader {
+        width,
+        height,
+        color,
+    })
+}
+
+/// The first chunk: a lossy (VP8), lossless (VP8L) or extended (VP8X) header
+fn webp(data: &[u8]) -> Option<ImageHeader> {
+    let (width, height, alpha) = match data.get(12..16)? {
+        b"VP8 " => {
+            if data.get(23..26)? != [0x9D, 0x01, 0x2A] {
+                return None;
+            }
+            let width = le16(data, 26)? & 0x3FFF;
+            let height = le16(data, 28)? & 0x3FFF;
+            (width as u32, height as u32, false)
+        }
+        b"VP8L" => {
+            if *data.get(20)? != 0x2F {
+                return None;
+            }
+            let bits = le32(data, 21)?;
+            if bits >> 29 != 0 {
+                return None;
+            }
+            let width = (bits & 0x3FFF) + 1;
+            let height = ((bits >> 14) & 0x3FFF) + 1;
+            (width, height, bits >> 28 & 1 != 0)
+        }
+        b"VP8X" => {
+            let flags = *data.get(20)?;
+            let width = le24(data, 24)? + 1;
+            let height = le24(data, 27)? + 1;
+            (width, height, flags & 0x10 != 0)
+        }
+        _ => return None,
+    };
+
+    Some(ImageHeader {
+        width,
+        height,
+        color: if alpha {
+            ColorType::Rgba8
+        } else {
+            ColorType::Rgb8
+        },
+    })
+}
+
+/// The DIB header after the 14-byte file header
+fn bmp(data: &[u8]) -> Option<ImageHeader> {
+    let header_size = le32(data, 14)?;
+    let (width, height, bits) = if header_size == 12 {
+        // OS/2 core header, 16-bit dimensions
+        (
+            le16(data, 18)? as i32,
+            le16(data, 20)? as i32,
+            le16(data, 24)?,
+        )
+    } else {
+        (
+            le32(data, 18)? as i32,
+            le32(data, 22)? as i32,
+            le16(data, 28)?,
+        )
+    };
+    if !matches!(bits, 1 | 4 | 8 | 16 | 24 | 32) {
+        return None;
+    }
+
+    // Only bit fields with a non-zero alpha mask (V3 headers and later) keep
+    // an alpha channel, plain 32-bit pixels are read as RGB
+    let bitfields = header_size >= 40 && matches!(le32(data, 30)?, 3 | 6);
+    let alpha = bitfields && matches!(bits, 16 | 32) && header_size >= 56 && le32(data, 66)? != 0;
+
+    // Negative heights mark top-down rows
+    Some(ImageHeader {
+        width: u32::try_from(width).ok()?,
+        height: height.unsigned_abs(),
+        color: if alpha {
+            ColorType::Rgba8
+        } else {
+            ColorType::Rgb8
+        },
+    })
+}
+
+/// The first IFD: dimensions, sample layout and photometric interpretation
+fn tiff(data: &[u8]) -> Option<ImageHeader> {
+    let big_endian = data.starts_with(b"MM");
+    let u16_at = |offset: usize| {
+        if big_endian {
+            be16(data, offset)
+        } else {
+            le16(data, offset)
+        }
+    };
+    let u32_at = |offset: usize| {
+        if big_endian {
+            be32(data, offset)
+        } else {
+            le32(data, offset)
+        }
+    };
+
+    let ifd = u32_at(4)? as usize;
+    let entries = u16_at(ifd)? as usize;
+
+    let (mut width, mut height) = (None, None);
+    let mut bits = 1;
+    let mut samples = 1;
+    let mut photometric = None;
+    for i in 0..entries {
+        let entry = ifd + 2 + i * 12;
+        let tag = u16_at(entry)?;
+        let field_type = u16_at(entry + 2)?;
+        let count = u32_at(entry + 4)?;
+        // SHORT values sit left-aligned in the 4-byte value field, LONG ones
+        // fill it. BitsPerSample with more than two samples is stored
+        // elsewhere, but all samples must share a depth, so the first is read.
+        let value = || match (field_type, count) {
+            (3, 1 | 2) => u16_at(entry + 8).map(u32::from),
+            (3, _) => u16_at(u32_at(entry + 8)? as usize).map(u32::from),
+            (4, 1) => u32_at(entry + 8),
+            _ => None,
+        };
+        match tag {
+            256 => width = value(),
+            257 => height = value(),
+            258 => bits = value()?,
+            262 => photometric = value(),
+            277 => samples = value()?,
+            _ => {}
+        }
+    }
+
+    let color = match (photometric?, samples, bits) {
+        (0 | 1, 1, 8) => ColorType::L8,
+        (0 | 1, 1, 16) => ColorType::L16,
+        (0 | 1, 2, 8) => ColorType::La8,
+        (0 | 1, 2, 16) => ColorType::La16,
+        (2, 3, 8) => ColorType::Rgb8,
+        (2, 3, 16) => ColorType::Rgb16,
+        (2, 4, 8) => ColorType::Rgba8,
+        (2, 4, 16) => ColorType::Rgba16,
+        _ => return None,
+    };
+
+    Some(ImageHeader {
+        width: width?,
+        height: height?,
+        color,
+    })
+}
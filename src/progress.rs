@@ -0,0 +1,167 @@
+//! Progress reporting and cancellation for long-running operations
+//!
+//! Scanning, hashing, annotation import and linting report how far they got
+//! to a `ProgressSink`. A sink that returns an error cancels the operation:
+//! the error propagates out, and the open transaction is rolled back as it
+//! drops. A cache being built is removed as a whole, since the scan commits
+//! its image batches as it goes.
+use crate::errors::{DatalintError, DatalintResult};
+use pyo3::prelude::*;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Minimum time between two calls into Python for the same stage
+const PY_REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Phase of a long-running operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Listing image files, counted as they are found
+    Scan,
+    /// Hashing and reading image files. While building a cache the listing
+    /// runs alongside, so the total is unknown until it completes.
+    Hash,
+    /// Importing annotations, counted in images for YOLO, in files for VOC
+    /// and in annotations per file for COCO
+    Annotations,
+    /// Running lint rules
+    Lint,
+}
+
+impl Stage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stage::Scan => "scan",
+            Stage::Hash => "hash",
+            Stage::Annotations => "annotations",
+            Stage::Lint => "lint",
+        }
+    }
+}
+
+/// Receives progress from long-running operations
+pub trait ProgressSink: Sync {
+    /// `done` units of `stage` are finished, out of `total` when known.
+    /// Returning an error cancels the operation with that error.
+    fn update(&self, stage: Stage, done: usize, total: Option<usize>) -> DatalintResult<()>;
+}
+
+/// Sink that ignores progress and never cancels
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {
+    fn update(&self, _stage: Stage, _done: usize, _total: Option<usize>) -> DatalintResult<()> {
+        Ok(())
+    }
+}
+
+/// What a Python progress argument is driven as
+enum PyTarget {
+    /// Called as `callback(stage, done, total)`
+    Callback(Py<PyAny>),
+    /// A tqdm-compatible bar: `update(n)`, `total`, and optionally `reset`
+    /// and `set_description`
+    Bar(Py<PyAny>),
+}
+
+#[derive(Default)]
+struct PyState {
+    stage: Option<Stage>,
+    done: usize,
+    total: Option<usize>,
+    reported_at: Option<Instant>,
+}
+
+/// Forwards progress to a Python callback or progress bar, and cancels the
+/// operation on Ctrl-C.
+///
+/// Pending signals are checked on every update, so `KeyboardInterrupt` (or
+/// any exception raised by the callback) stops the operation and is raised
+/// to the caller. Calls into the callback are throttled, except for stage
+/// changes and completion.
+pub struct PyProgress {
+    target: Option<PyTarget>,
+    state: Mutex<PyState>,
+}
+
+impl PyProgress {
+    /// Wrap the `progress` argument of a Python entry point. Without one,
+    /// updates only check for signals.
+    pub fn new(progress: Option<Py<PyAny>>) -> Self {
+        let target = progress.map(|obj| {
+            Python::with_gil(|py| {
+                if obj.bind(py).is_callable() {
+                    PyTarget::Callback(obj)
+                } else {
+                    PyTarget::Bar(obj)
+                }
+            })
+        });
+        Self {
+            target,
+            state: Mutex::new(PyState::default()),
+        }
+    }
+
+    fn report(
+        &self,
+        py: Python<'_>,
+        stage: Stage,
+        done: usize,
+        total: Option<usize>,
+    ) -> PyResult<()> {
+        let Some(target) = &self.target else {
+            return Ok(());
+        };
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| DatalintError::Generic("Progress lock poisoned".to_string()))?;
+
+        let new_stage = state.stage != Some(stage);
+        let finished = total == Some(done);
+        let due = state
+            .reported_at
+            .map_or(true, |at| at.elapsed() >= PY_REPORT_INTERVAL);
+        if !(new_stage || finished || due) {
+            return Ok(());
+        }
+
+        match target {
+            PyTarget::Callback(callback) => {
+                callback.call1(py, (stage.as_str(), done, total))?;
+            }
+            PyTarget::Bar(bar) => {
+                let bar = bar.bind(py);
+                if new_stage {
+                    if bar.hasattr("reset")? {
+                        bar.call_method1("reset", (total,))?;
+                    }
+                    if bar.hasattr("set_description")? {
+                        bar.call_method1("set_description", (stage.as_str(),))?;
+                    }
+                    state.done = 0;
+                } else if total != state.total {
+                    bar.setattr("total", total)?;
+                }
+                bar.call_method1("update", (done.saturating_sub(state.done),))?;
+            }
+        }
+
+        state.stage = Some(stage);
+        state.done = done;
+        state.total = total;
+        state.reported_at = Some(Instant::now());
+        Ok(())
+    }
+}
+
+impl ProgressSink for PyProgress {
+    fn update(&self, stage: Stage, done: usize, total: Option<usize>) -> DatalintResult<()> {
+        Python::with_gil(|py| {
+            py.check_signals()?;
+            self.report(py, stage, done, total)
+        })
+        .map_err(DatalintError::Python)
+    }
+}
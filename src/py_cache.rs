@@ -0,0 +1,436 @@
+//! Python handle over an existing cache database
+use crate::cache::create_cache_db;
+use crate::db::models::{Bbox, CacheMetadata, Finding, Image, Label, ScanError};
+use crate::db::queries::{
+    BboxQueries, ClassificationQueries, ExportQueries, FindingQueries, ImageQueries, LabelQueries,
+    ScanErrorQueries,
+};
+use crate::db::Database;
+use crate::enums::{DatasetTask, DatasetType};
+use crate::errors::{DatalintError, DatalintResult};
+use crate::lint::{
+    find_duplicate_images, find_near_duplicate_images, run_rules, DuplicateCluster, LintConfig,
+    NearDuplicateGroup, RuleRegistry,
+};
+use crate::progress::{ProgressSink, PyProgress};
+use crate::py_task::CacheTask;
+use crate::scanner::ScanOptions;
+use duckdb::arrow::datatypes::SchemaRef;
+use duckdb::arrow::ffi::FFI_ArrowSchema;
+use duckdb::arrow::ffi_stream::FFI_ArrowArrayStream;
+use duckdb::arrow::record_batch::{RecordBatch, RecordBatchIterator};
+use pyo3::prelude::*;
+use pyo3::types::PyCapsule;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, TryLockError};
+
+/// Query result held as Arrow record batches.
+///
+/// Implements the Arrow PyCapsule interface, so `pyarrow.table(t)` or
+/// `polars.DataFrame(t)` take the batches without a row-by-row copy.
+#[pyclass(frozen)]
+pub struct ArrowTable {
+    schema: SchemaRef,
+    batches: Vec<RecordBatch>,
+}
+
+#[pymethods]
+impl ArrowTable {
+    /// Export the batches as an `arrow_array_stream` capsule
+    #[pyo3(signature = (requested_schema=None))]
+    fn __arrow_c_stream__<'py>(
+        &self,
+        py: Python<'py>,
+        requested_schema: Option<Bound<'py, PyAny>>,
+    ) -> PyResult<Bound<'py, PyCapsule>> {
+        // Casting to a requested schema is optional in the protocol
+        let _ = requested_schema;
+        let reader = RecordBatchIterator::new(
+            self.batches.clone().into_iter().map(Ok),
+            self.schema.clone(),
+        );
+        let stream = FFI_ArrowArrayStream::new(Box::new(reader));
+        let name = CString::new("arrow_array_stream").unwrap();
+        PyCapsule::new(py, stream, Some(name))
+    }
+
+    /// Export the schema as an `arrow_schema` capsule
+    fn __arrow_c_schema__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyCapsule>> {
+        let schema = FFI_ArrowSchema::try_from(self.schema.as_ref())
+            .map_err(|e| DatalintError::Core(format!("Arrow schema export failed: {}", e)))?;
+        let name = CString::new("arrow_schema").unwrap();
+        PyCapsule::new(py, schema, Some(name))
+    }
+
+    /// Column names
+    #[getter]
+    fn column_names(&self) -> Vec<String> {
+        self.schema
+            .fields()
+            .iter()
+            .map(|field| field.name().clone())
+            .collect()
+    }
+
+    /// Total number of rows
+    #[getter]
+    fn num_rows(&self) -> usize {
+        self.batches.iter().map(RecordBatch::num_rows).sum()
+    }
+
+    /// Convert to a `pyarrow.Table`
+    fn to_pyarrow<'py>(slf: &Bound<'py, Self>) -> PyResult<Bound<'py, PyAny>> {
+        let pyarrow = slf.py().import("pyarrow")?;
+        pyarrow.call_method1("table", (slf,))
+    }
+
+    /// Convert to a `polars.DataFrame`
+    fn to_polars<'py>(slf: &Bound<'py, Self>) -> PyResult<Bound<'py, PyAny>> {
+        let polars = slf.py().import("polars")?;
+        polars.call_method1("DataFrame", (slf,))
+    }
+
+    /// Convert to a `pandas.DataFrame` (through pyarrow)
+    fn to_pandas<'py>(slf: &Bound<'py, Self>) -> PyResult<Bound<'py, PyAny>> {
+        Self::to_pyarrow(slf)?.call_method0("to_pandas")
+    }
+
+    fn __len__(&self) -> usize {
+        self.num_rows()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "ArrowTable(columns={:?}, num_rows={})",
+            self.column_names(),
+            self.num_rows()
+        )
+    }
+}
+
+/// An open cache database
+#[pyclass(frozen)]
+pub struct Cache {
+    path: PathBuf,
+    db: Mutex<Database>,
+}
+
+impl Cache {
+    /// Open a cache file that must already exist
+    pub fn open_path(path: &Path) -> DatalintResult<Self> {
+        if !path.is_file() {
+            return Err(DatalintError::Core(format!(
+                "Cache not found: {}",
+                path.display()
+            )));
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            db: Mutex::new(Database::open(path)?),
+        })
+    }
+
+    /// Build a cache for a dataset and open it
+    pub fn create_path(
+        cache_path: &Path,
+        dataset_path: &Path,
+        dataset_type: &DatasetType,
+        dataset_task: &DatasetTask,
+        options: &ScanOptions,
+        progress: &dyn ProgressSink,
+    ) -> DatalintResult<Self> {
+        create_cache_db(
+            cache_path,
+            dataset_path,
+            dataset_type,
+            dataset_task,
+            options,
+            progress,
+        )?;
+        Self::open_path(cache_path)
+    }
+
+    /// Lock the database for a query.
+    ///
+    /// A lint running without the GIL holds the lock while it takes the GIL to
+    /// report progress, so a contended lock is waited for with the GIL released.
+    pub fn db(&self) -> DatalintResult<MutexGuard<'_, Database>> {
+        loop {
+            match self.db.try_lock() {
+                Ok(db) => return Ok(db),
+                Err(TryLockError::WouldBlock) => {
+                    Python::with_gil(|py| py.allow_threads(|| drop(self.db.lock())));
+                }
+                Err(TryLockError::Poisoned(_)) => {
+                    return Err(DatalintError::Generic("Cache lock poisoned".to_string()))
+                }
+            }
+        }
+    }
+
+    /// Run lint rules and store their findings, returning the count per rule
+    pub fn run_lint(
+        &self,
+        rules: Option<&[String]>,
+        config: &LintConfig,
+        progress: &dyn ProgressSink,
+    ) -> DatalintResult<HashMap<String, usize>> {
+        let mut db = self.db()?;
+        let metadata = db
+            .get_cache_metadata()?
+            .ok_or_else(|| DatalintError::Core("Cache has no metadata".to_string()))?;
+        let task = DatasetTask::from_str(&metadata.dataset_task).map_err(DatalintError::Core)?;
+
+        let registry = RuleRegistry::with_builtin(config);
+        let summary = run_rules(&mut db, &registry, &task, rules, progress)?;
+        Ok(summary.by_rule.into_iter().collect())
+    }
+}
+
+#[pymethods]
+impl Cache {
+    /// Open an existing cache database
+    #[staticmethod]
+    fn open(path: String) -> PyResult<Self> {
+        Ok(Self::open_path(Path::new(&path))?)
+    }
+
+    /// Build a cache for a dataset without holding the GIL and open it.
+    /// `progress` is a callback taking `(stage, done, total)` or a
+    /// tqdm-compatible bar.
+    #[staticmethod]
+    #[pyo3(signature = (cache_path, dataset_path, dataset_type, dataset_task, perceptual_hashes=false, full_decode=false, progress=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn create(
+        py: Python<'_>,
+        cache_path: String,
+        dataset_path: String,
+        dataset_type: DatasetType,
+        dataset_task: DatasetTask,
+        perceptual_hashes: bool,
+        full_decode: bool,
+        progress: Option<Py<PyAny>>,
+    ) -> PyResult<Self> {
+        let options = ScanOptions {
+            perceptual_hashes,
+            full_decode,
+        };
+        let progress = PyProgress::new(progress);
+        let cache = py.allow_threads(|| {
+            Self::create_path(
+                Path::new(&cache_path),
+                Path::new(&dataset_path),
+                &dataset_type,
+                &dataset_task,
+                &options,
+                &progress,
+            )
+        })?;
+        Ok(cache)
+    }
+
+    /// Build a cache on a background thread. Returns a `CacheTask` that
+    /// resolves to the opened `Cache`.
+    #[staticmethod]
+    #[pyo3(signature = (cache_path, dataset_path, dataset_type, dataset_task, perceptual_hashes=false, full_decode=false, progress=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn create_async(
+        py: Python<'_>,
+        cache_path: String,
+        dataset_path: String,
+        dataset_type: DatasetType,
+        dataset_task: DatasetTask,
+        perceptual_hashes: bool,
+        full_decode: bool,
+        progress: Option<Py<PyAny>>,
+    ) -> PyResult<CacheTask> {
+        let options = ScanOptions {
+            perceptual_hashes,
+            full_decode,
+        };
+        CacheTask::spawn(py, progress, move |progress| {
+            Self::create_path(
+                Path::new(&cache_path),
+                Path::new(&dataset_path),
+                &dataset_type,
+                &dataset_task,
+                &options,
+                progress,
+            )
+        })
+    }
+
+    /// Path of the cache file
+    #[getter]
+    fn path(&self) -> String {
+        self.path.to_string_lossy().to_string()
+    }
+
+    /// Dataset information recorded when the cache was created
+    #[getter]
+    fn metadata(&self) -> PyResult<CacheMetadata> {
+        let metadata = self.db()?.get_cache_metadata()?;
+        Ok(metadata.ok_or_else(|| DatalintError::Core("Cache has no metadata".to_string()))?)
+    }
+
+    /// All labels, in id order
+    fn labels(&self) -> PyResult<Vec<Label>> {
+        Ok(LabelQueries::get_all(self.db()?.conn())?)
+    }
+
+    /// All images, or only those of one split
+    #[pyo3(signature = (split=None))]
+    fn images(&self, split: Option<&str>) -> PyResult<Vec<Image>> {
+        let db = self.db()?;
+        let images = match split {
+            Some(split) => ImageQueries::get_by_split(db.conn(), split)?,
+            None => ImageQueries::get_all(db.conn())?,
+        };
+        Ok(images)
+    }
+
+    /// Files the latest scan could not list, read or insert, and annotation
+    /// problems such as annotations referencing missing images. `stage` keeps
+    /// only walk, read, insert or annotation errors.
+    #[pyo3(signature = (stage=None))]
+    fn scan_errors(&self, stage: Option<&str>) -> PyResult<Vec<ScanError>> {
+        let db = self.db()?;
+        let errors = match stage {
+            Some(stage) => ScanErrorQueries::get_by_stage(db.conn(), stage)?,
+            None => ScanErrorQueries::get_all(db.conn())?,
+        };
+        Ok(errors)
+    }
+
+    /// Boxes annotated on an image
+    fn bboxes(&self, image_id: i32) -> PyResult<Vec<Bbox>> {
+        Ok(BboxQueries::get_by_image(self.db()?.conn(), image_id)?)
+    }
+
+    /// Number of images per split
+    fn count_by_split(&self) -> PyResult<HashMap<String, i32>> {
+        let counts = ImageQueries::count_by_split(self.db()?.conn())?;
+        Ok(counts.into_iter().collect())
+    }
+
+    /// Number of annotations (boxes and image-level labels) per label name
+    fn count_by_label(&self) -> PyResult<HashMap<String, i32>> {
+        let db = self.db()?;
+        let names: HashMap<i32, String> = LabelQueries::get_all(db.conn())?
+            .into_iter()
+            .filter_map(|label| Some((label.id?, label.name)))
+            .collect();
+
+        let mut counts: HashMap<String, i32> = HashMap::new();
+        let bbox_counts = BboxQueries::count_by_label(db.conn())?;
+        let class_counts = ClassificationQueries::count_by_label(db.conn())?;
+        for (label_id, count) in bbox_counts.into_iter().chain(class_counts) {
+            if let Some(name) = names.get(&label_id) {
+                *counts.entry(name.clone()).or_default() += count;
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Groups of byte-identical images, each with a suggested copy to keep.
+    /// The kept copy prefers test, then val, then train, so evaluation sets
+    /// stay unchanged when the others are removed.
+    #[pyo3(signature = (cross_split_only=false))]
+    fn duplicate_images(&self, cross_split_only: bool) -> PyResult<Vec<DuplicateCluster>> {
+        let clusters = find_duplicate_images(self.db()?.conn())?;
+        Ok(clusters
+            .into_iter()
+            .filter(|cluster| !cross_split_only || cluster.crosses_splits())
+            .collect())
+    }
+
+    /// Groups of visually similar images, linked when their perceptual hash
+    /// (`ahash`, `dhash` or `phash`) differs in at most `max_distance` bits.
+    /// Needs a cache built with `perceptual_hashes=True`.
+    #[pyo3(signature = (hash="phash", max_distance=6, cross_split_only=false))]
+    fn near_duplicate_images(
+        &self,
+        hash: &str,
+        max_distance: u32,
+        cross_split_only: bool,
+    ) -> PyResult<Vec<NearDuplicateGroup>> {
+        let groups = find_near_duplicate_images(self.db()?.conn(), hash, max_distance)?;
+        Ok(groups
+            .into_iter()
+            .filter(|group| !cross_split_only || group.crosses_splits())
+            .collect())
+    }
+
+    /// Run lint rules without holding the GIL and store their findings,
+    /// returning the count per rule. Without `rules`, every built-in rule
+    /// applicable to the dataset task runs; `config` overrides the default rule
+    /// thresholds. `progress` is a callback taking `(stage, done, total)` or a
+    /// tqdm-compatible bar, advanced per rule.
+    #[pyo3(signature = (rules=None, config=None, progress=None))]
+    fn lint(
+        &self,
+        py: Python<'_>,
+        rules: Option<Vec<String>>,
+        config: Option<LintConfig>,
+        progress: Option<Py<PyAny>>,
+    ) -> PyResult<HashMap<String, usize>> {
+        let config = config.unwrap_or_default();
+        let progress = PyProgress::new(progress);
+        let summary = py.allow_threads(|| self.run_lint(rules.as_deref(), &config, &progress))?;
+        Ok(summary)
+    }
+
+    /// Run lint rules on a background thread. Returns a `CacheTask` that
+    /// resolves to the count per rule; queries on this cache wait until the
+    /// lint finishes.
+    #[pyo3(signature = (rules=None, config=None, progress=None))]
+    fn lint_async(
+        slf: &Bound<'_, Self>,
+        rules: Option<Vec<String>>,
+        config: Option<LintConfig>,
+        progress: Option<Py<PyAny>>,
+    ) -> PyResult<CacheTask> {
+        let cache = slf.clone().unbind();
+        let config = config.unwrap_or_default();
+        CacheTask::spawn(slf.py(), progress, move |progress| {
+            cache.get().run_lint(rules.as_deref(), &config, progress)
+        })
+    }
+
+    /// Stored findings, optionally only those of one rule or one image
+    #[pyo3(signature = (rule_id=None, image_id=None))]
+    fn findings(&self, rule_id: Option<&str>, image_id: Option<i32>) -> PyResult<Vec<Finding>> {
+        let db = self.db()?;
+        let findings = match (rule_id, image_id) {
+            (Some(rule_id), image_id) => FindingQueries::get_by_rule(db.conn(), rule_id)?
+                .into_iter()
+                .filter(|finding| image_id.is_none() || finding.image_id == image_id)
+                .collect(),
+            (None, Some(image_id)) => FindingQueries::get_by_image(db.conn(), image_id)?,
+            (None, None) => FindingQueries::get_all(db.conn())?,
+        };
+        Ok(findings)
+    }
+
+    /// Number of stored findings per rule
+    fn count_by_rule(&self) -> PyResult<HashMap<String, i32>> {
+        let counts = FindingQueries::count_by_rule(self.db()?.conn())?;
+        Ok(counts.into_iter().collect())
+    }
+
+    /// Export a table as Arrow: images, labels, bboxes, segmentations, masks,
+    /// keypoints, classifications, findings, label_files, scan_errors, or the
+    /// joined bbox_details and classification_details views (annotations with
+    /// label name and image path)
+    fn to_arrow(&self, table: &str) -> PyResult<ArrowTable> {
+        let (schema, batches) = ExportQueries::fetch(self.db()?.conn(), table)?;
+        Ok(ArrowTable { schema, batches })
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Cache('{}')", self.path.display())
+    }
+}
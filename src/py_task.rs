@@ -0,0 +1,156 @@
+//! Background cache builds and lint runs for async Python callers
+use crate::errors::{DatalintError, DatalintResult};
+use crate::progress::{ProgressSink, PyProgress, Stage};
+use pyo3::exceptions::asyncio::CancelledError;
+use pyo3::prelude::*;
+use pyo3::types::{PyCFunction, PyDict, PyTuple};
+use pyo3::IntoPyObjectExt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Progress and cancellation shared between a task and its handle
+#[derive(Default)]
+struct TaskState {
+    cancelled: AtomicBool,
+    latest: Mutex<Option<(Stage, usize, Option<usize>)>>,
+}
+
+/// Sink of a background task: records the latest update for polling, forwards
+/// it to the caller's progress argument and stops the task once cancelled
+struct TaskProgress {
+    state: Arc<TaskState>,
+    forward: PyProgress,
+}
+
+impl ProgressSink for TaskProgress {
+    fn update(&self, stage: Stage, done: usize, total: Option<usize>) -> DatalintResult<()> {
+        if self.state.cancelled.load(Ordering::Acquire) {
+            return Err(DatalintError::Python(CancelledError::new_err(
+                "Task cancelled",
+            )));
+        }
+        if let Ok(mut latest) = self.state.latest.lock() {
+            *latest = Some((stage, done, total));
+        }
+        self.forward.update(stage, done, total)
+    }
+}
+
+/// Handle on a cache build or lint running on a background thread.
+///
+/// Poll it with `done()` and `progress`, block on `result()`, or `await` it
+/// from an asyncio event loop. `cancel()` stops the task at its next progress
+/// update, with the same rollback as Ctrl-C on the blocking call.
+#[pyclass(frozen)]
+pub struct CacheTask {
+    state: Arc<TaskState>,
+    /// `concurrent.futures.Future` resolved by the worker thread
+    future: Py<PyAny>,
+}
+
+impl CacheTask {
+    /// Run `work` on a new thread without the GIL and return its handle
+    pub fn spawn<T, F>(py: Python<'_>, progress: Option<Py<PyAny>>, work: F) -> PyResult<Self>
+    where
+        T: for<'py> IntoPyObject<'py> + Send + 'static,
+        F: FnOnce(&dyn ProgressSink) -> DatalintResult<T> + Send + 'static,
+    {
+        let future = py
+            .import("concurrent.futures")?
+            .getattr("Future")?
+            .call0()?;
+        // A running future can no longer be cancelled through the Future API,
+        // so only `cancel()` below stops the work
+        future.call_method0("set_running_or_notify_cancel")?;
+        let future = future.unbind();
+
+        let state = Arc::new(TaskState::default());
+        let sink = TaskProgress {
+            state: state.clone(),
+            forward: PyProgress::new(progress),
+        };
+        let target = future.clone_ref(py);
+        thread::spawn(move || {
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| work(&sink)))
+                .unwrap_or_else(|_| Err(DatalintError::Core("Task panicked".to_string())));
+            Python::with_gil(|py| {
+                let resolved = match outcome {
+                    Ok(value) => value
+                        .into_py_any(py)
+                        .and_then(|value| target.call_method1(py, "set_result", (value,))),
+                    Err(e) => {
+                        let err = PyErr::from(e);
+                        target.call_method1(py, "set_exception", (err.value(py),))
+                    }
+                };
+                if let Err(e) = resolved {
+                    e.write_unraisable(py, None);
+                }
+            });
+        });
+
+        Ok(Self { state, future })
+    }
+}
+
+#[pymethods]
+impl CacheTask {
+    /// Whether the task finished, failed or was cancelled
+    fn done(&self, py: Python<'_>) -> PyResult<bool> {
+        self.future.call_method0(py, "done")?.extract(py)
+    }
+
+    /// Wait for the task and return its result, raising its error. Raises
+    /// `TimeoutError` if it is still running after `timeout` seconds.
+    #[pyo3(signature = (timeout=None))]
+    fn result(&self, py: Python<'_>, timeout: Option<f64>) -> PyResult<Py<PyAny>> {
+        self.future.call_method1(py, "result", (timeout,))
+    }
+
+    /// Ask the task to stop at its next progress update, after which it fails
+    /// with `asyncio.CancelledError`. Returns False if it already finished.
+    /// True only means the request was made while it ran: a task past its
+    /// last progress update still completes, which `result()` tells.
+    fn cancel(&self, py: Python<'_>) -> PyResult<bool> {
+        self.state.cancelled.store(true, Ordering::Release);
+        Ok(!self.done(py)?)
+    }
+
+    /// Latest `(stage, done, total)` reported by the task, None before the
+    /// first update
+    #[getter]
+    fn progress(&self) -> Option<(&'static str, usize, Option<usize>)> {
+        let latest = *self.state.latest.lock().ok()?;
+        latest.map(|(stage, done, total)| (stage.as_str(), done, total))
+    }
+
+    /// Await the result from the running event loop. Cancelling the awaiting
+    /// coroutine cancels the task.
+    fn __await__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let awaitable = py
+            .import("asyncio")?
+            .call_method1("wrap_future", (&self.future,))?;
+
+        let state = self.state.clone();
+        let on_done = PyCFunction::new_closure(
+            py,
+            None,
+            None,
+            move |args: &Bound<'_, PyTuple>, _: Option<&Bound<'_, PyDict>>| -> PyResult<()> {
+                if args.get_item(0)?.call_method0("cancelled")?.extract()? {
+                    state.cancelled.store(true, Ordering::Release);
+                }
+                Ok(())
+            },
+        )?;
+        awaitable.call_method1("add_done_callback", (on_done,))?;
+        awaitable.call_method0("__await__")
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        let status = if self.done(py)? { "done" } else { "running" };
+        Ok(format!("CacheTask({})", status))
+    }
+}
@@ -0,0 +1,369 @@
+//! COCO-compatible run-length encoding for binary masks
+//!
+//! Masks are laid out column-major (Fortran order) like pycocotools, and runs
+//! alternate background/foreground starting with background.
+
+use crate::errors::{DatalintError, DatalintResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Run-length encoded binary mask
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rle {
+    pub height: u32,
+    pub width: u32,
+    pub counts: Vec<u64>,
+}
+
+impl Rle {
+    /// Encode a column-major mask where any non-zero value is foreground
+    pub fn encode(mask: &[u8], height: u32, width: u32) -> Self {
+        let mut counts = Vec::new();
+        let mut current = 0u8;
+        let mut run = 0u64;
+
+        for &value in mask {
+            let value = (value != 0) as u8;
+            if value != current {
+                counts.push(run);
+                run = 0;
+                current = value;
+            }
+            run += 1;
+        }
+        counts.push(run);
+
+        Self {
+            height,
+            width,
+            counts,
+        }
+    }
+
+    /// Decode into a column-major mask of 0/1 values
+    pub fn decode(&self) -> Vec<u8> {
+        let len = self.height as usize * self.width as usize;
+        let mut mask = vec![0u8; len];
+        let mut idx = 0usize;
+
+        for (i, &count) in self.counts.iter().enumerate() {
+            let end = (idx + count as usize).min(len);
+            if i % 2 == 1 {
+                mask[idx..end].fill(1);
+            }
+            idx = end;
+        }
+
+        mask
+    }
+
+    /// Parse the compressed string form used in COCO json files
+    pub fn from_compressed(counts: &str, height: u32, width: u32) -> DatalintResult<Self> {
+        let bytes = counts.as_bytes();
+        let mut values: Vec<u64> = Vec::new();
+        let mut p = 0;
+
+        while p < bytes.len() {
+            let mut x: i64 = 0;
+            let mut k = 0;
+            loop {
+                let c = bytes[p] as i64 - 48;
+                if !(0..64).contains(&c) || k >= 12 {
+                    return Err(DatalintError::Core(format!(
+                        "Invalid RLE counts at byte {}",
+                        p
+                    )));
+                }
+                x |= (c & 0x1f) << (5 * k);
+                p += 1;
+                k += 1;
+                if c & 0x20 == 0 {
+                    if c & 0x10 != 0 {
+                        x |= -1i64 << (5 * k);
+                    }
+                    break;
+                }
+                if p >= bytes.len() {
+                    return Err(DatalintError::Core("Truncated RLE counts".to_string()));
+                }
+            }
+
+            let m = values.len();
+            if m > 2 {
+                x += values[m - 2] as i64;
+            }
+            if x < 0 {
+                return Err(DatalintError::Core("Negative RLE run length".to_string()));
+            }
+            values.push(x as u64);
+        }
+
+        let rle = Self {
+            height,
+            width,
+            counts: values,
+        };
+        rle.validate()?;
+        Ok(rle)
+    }
+
+    /// Serialize to the compressed string form used in COCO json files
+    pub fn to_compressed(&self) -> String {
+        let mut out = String::new();
+
+        for (i, &count) in self.counts.iter().enumerate() {
+            let mut x = count as i64;
+            if i > 2 {
+                x -= self.counts[i - 2] as i64;
+            }
+            loop {
+                let mut c = x & 0x1f;
+                x >>= 5;
+                let more = if c & 0x10 != 0 { x != -1 } else { x != 0 };
+                if more {
+                    c |= 0x20;
+                }
+                out.push((c as u8 + 48) as char);
+                if !more {
+                    break;
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Check that the runs cover exactly height * width pixels
+    pub fn validate(&self) -> DatalintResult<()> {
+        let total: u64 = self.counts.iter().sum();
+        let expected = self.height as u64 * self.width as u64;
+        if total != expected {
+            return Err(DatalintError::Core(format!(
+                "RLE covers {} pixels, expected {}x{} = {}",
+                total, self.height, self.width, expected
+            )));
+        }
+        Ok(())
+    }
+
+    /// Number of foreground pixels
+    pub fn area(&self) -> u64 {
+        self.counts.iter().skip(1).step_by(2).sum()
+    }
+
+    /// Bounding box (x, y, w, h) of the foreground, None for an empty mask
+    pub fn bbox(&self) -> Option<(f64, f64, f64, f64)> {
+        let h = self.height as u64;
+        if h == 0 {
+            return None;
+        }
+
+        let (mut xmin, mut ymin, mut xmax, mut ymax) = (u64::MAX, u64::MAX, 0, 0);
+        let mut idx = 0u64;
+        for (i, &count) in self.counts.iter().enumerate() {
+            if i % 2 == 1 && count > 0 {
+                let (start, end) = (idx, idx + count - 1);
+                let (x0, y0, x1, y1) = (start / h, start % h, end / h, end % h);
+                xmin = xmin.min(x0);
+                xmax = xmax.max(x1);
+                if x0 != x1 {
+                    // Run wraps over a column boundary
+                    ymin = 0;
+                    ymax = h - 1;
+                } else {
+                    ymin = ymin.min(y0);
+                    ymax = ymax.max(y1);
+                }
+            }
+            idx += count;
+        }
+
+        if xmin == u64::MAX {
+            return None;
+        }
+        Some((
+            xmin as f64,
+            ymin as f64,
+            (xmax - xmin + 1) as f64,
+            (ymax - ymin + 1) as f64,
+        ))
+    }
+
+    /// Rasterize polygons (pixel coordinates) into a mask.
+    ///
+    /// A pixel is foreground when its center lies inside any polygon. This is
+    /// close to, but not bit-identical with, pycocotools `frPyObjects`. Runs are
+    /// built column by column from edge crossings, so the cost follows the
+    /// polygons' width rather than the image size.
+    pub fn from_polygons(polygons: &[Vec<(f64, f64)>], height: u32, width: u32) -> Self {
+        let (h, w) = (height as u64, width as u64);
+        let polygons: Vec<&Vec<(f64, f64)>> = polygons.iter().filter(|p| p.len() >= 3).collect();
+        let (min_x, max_x) = polygons
+            .iter()
+            .flat_map(|p| p.iter())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &(x, _)| {
+                (lo.min(x), hi.max(x))
+            });
+
+        let mut counts: Vec<u64> = Vec::new();
+        // End of the last foreground run, as a column-major pixel index
+        let mut last_end = 0u64;
+        let mut crossings: Vec<f64> = Vec::new();
+        let mut spans: Vec<(u64, u64)> = Vec::new();
+
+        let first = (min_x - 0.5).ceil().max(0.0) as u64;
+        let last = ((max_x - 0.5).ceil().max(0.0) as u64).min(w);
+        for x in first..last {
+            let xc = x as f64 + 0.5;
+            spans.clear();
+            for polygon in &polygons {
+                crossings.clear();
+                for (i, &(x0, y0)) in polygon.iter().enumerate() {
+                    let (x1, y1) = polygon[(i + 1) % polygon.len()];
+                    if (x0 <= xc) != (x1 <= xc) {
+                        crossings.push(y0 + (xc - x0) * (y1 - y0) / (x1 - x0));
+                    }
+                }
+                crossings.sort_by(|a, b| a.total_cmp(b));
+                for span in crossings.chunks_exact(2) {
+                    let start = ((span[0] - 0.5).ceil().max(0.0) as u64).min(h);
+                    let end = ((span[1] - 0.5).ceil().max(0.0) as u64).min(h);
+                    if start < end {
+                        spans.push((start, end));
+                    }
+                }
+            }
+            spans.sort_unstable();
+
+            // Overlapping polygons and runs across a column boundary merge
+            for &(start, end) in &spans {
+                let (start, end) = (x * h + start, x * h + end);
+                if end <= last_end {
+                    continue;
+                }
+                if start <= last_end && !counts.is_empty() {
+                    *counts.last_mut().unwrap() += end - last_end;
+                } else {
+                    counts.push(start - last_end);
+                    counts.push(end - start);
+                }
+                last_end = end;
+            }
+        }
+
+        if counts.is_empty() || last_end < h * w {
+            counts.push(h * w - last_end);
+        }
+        Self {
+            height,
+            width,
+            counts,
+        }
+    }
+
+    /// Trace the outer boundary of each 4-connected foreground region.
+    ///
+    /// Vertices lie on pixel corners and collinear points are dropped. Holes
+    /// are not returned since COCO polygons cannot express them.
+    pub fn to_polygons(&self) -> Vec<Vec<(f64, f64)>> {
+        let (h, w) = (self.height as i64, self.width as i64);
+        let mask = self.decode();
+        let is_set =
+            |x: i64, y: i64| x >= 0 && y >= 0 && x < w && y < h && mask[(x * h + y) as usize] != 0;
+
+        // Boundary edges oriented clockwise (y down), foreground on the right
+        let mut edges: HashMap<(i64, i64), Vec<(i64, i64)>> = HashMap::new();
+        for x in 0..w {
+            for y in 0..h {
+                if !is_set(x, y) {
+                    continue;
+                }
+                if !is_set(x, y - 1) {
+                    edges.entry((x, y)).or_default().push((x + 1, y));
+                }
+                if !is_set(x + 1, y) {
+                    edges.entry((x + 1, y)).or_default().push((x + 1, y + 1));
+                }
+                if !is_set(x, y + 1) {
+                    edges.entry((x + 1, y + 1)).or_default().push((x, y + 1));
+                }
+                if !is_set(x - 1, y) {
+                    edges.entry((x, y + 1)).or_default().push((x, y));
+                }
+            }
+        }
+
+        let mut starts: Vec<(i64, i64)> = edges.keys().copied().collect();
+        starts.sort_unstable();
+
+        let mut polygons = Vec::new();
+        for start in starts {
+            while let Some(first) = edges.get_mut(&start).and_then(Vec::pop) {
+                let mut loop_points = vec![start];
+                let mut prev = start;
+                let mut current = first;
+
+                while current != start {
+                    loop_points.push(current);
+                    let direction = (current.0 - prev.0, current.1 - prev.1);
+                    let Some(outgoing) = edges.get_mut(&current).filter(|o| !o.is_empty()) else {
+                        break;
+                    };
+                    // Prefer right turns so diagonal neighbours stay separate regions
+                    let right = (-direction.1, direction.0);
+                    let straight = direction;
+                    let pick = [right, straight]
+                        .iter()
+                        .find_map(|d| {
+                            outgoing
+                                .iter()
+                                .position(|&(nx, ny)| (nx - current.0, ny - current.1) == *d)
+                        })
+                        .unwrap_or(0);
+                    let next = outgoing.swap_remove(pick);
+                    prev = current;
+                    current = next;
+                }
+
+                let simplified = simplify_collinear(&loop_points);
+                if simplified.len() >= 3 && signed_area(&simplified) > 0.0 {
+                    polygons.push(
+                        simplified
+                            .into_iter()
+                            .map(|(x, y)| (x as f64, y as f64))
+                            .collect(),
+                    );
+                }
+            }
+        }
+
+        polygons
+    }
+}
+
+/// Drop vertices lying on a straight line between their neighbours
+fn simplify_collinear(points: &[(i64, i64)]) -> Vec<(i64, i64)> {
+    let n = points.len();
+    (0..n)
+        .filter(|&i| {
+            let (px, py) = points[(i + n - 1) % n];
+            let (cx, cy) = points[i];
+            let (nx, ny) = points[(i + 1) % n];
+            (cx - px) * (ny - cy) - (cy - py) * (nx - cx) != 0
+        })
+        .map(|i| points[i])
+        .collect()
+}
+
+/// Shoelace area, positive for clockwise loops in image coordinates
+fn signed_area(points: &[(i64, i64)]) -> f64 {
+    let n = points.len();
+    let twice: i64 = (0..n)
+        .map(|i| {
+            let (x0, y0) = points[i];
+            let (x1, y1) = points[(i + 1) % n];
+            x0 * y1 - x1 * y0
+        })
+        .sum();
+    twice as f64 / 2.0
+}
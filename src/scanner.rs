@@ -1,28 +1,57 @@
-use crate::db::models::Image;
+use crate::db::models::{Image, ScanError};
 use crate::db::queries::ImageQueries;
+use crate::db::Database;
 use crate::errors::{DatalintError, DatalintResult};
+use crate::phash;
+use crate::probe::{probe, ImageHeader};
+use crate::progress::{ProgressSink, Stage};
+use chrono::Utc;
+use duckdb::Connection;
+use image::ColorType;
+use rayon::iter::Either;
 use rayon::prelude::*;
 use std::fs;
+use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::sync_channel;
+use std::thread;
+use std::time::UNIX_EPOCH;
 use walkdir::WalkDir;
 use xxhash_rust::xxh3::xxh3_64;
 
+/// Optional work done while scanning images
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScanOptions {
+    /// Compute aHash, dHash and pHash from the decoded image
+    pub perceptual_hashes: bool,
+    /// Decode every image in full so truncated or damaged pixel data is
+    /// marked corrupted. Otherwise size and color type come from the header.
+    pub full_decode: bool,
+}
+
+/// Processed batches buffered between the scan workers and the database writer
+const PIPELINE_DEPTH: usize = 2;
+
+/// Files listed between two progress updates while walking the dataset
+const WALK_REPORT_EVERY: usize = 1000;
+
 /// Supported image extensions
 const IMAGE_EXTENSIONS: &[&str] = &[
     "jpg", "jpeg", "png", "bmp", "gif", "webp", "tiff", "tif", "ico", "svg",
 ];
 
 /// Check if a path has an image extension
-fn is_image_file(path: &Path) -> bool {
+pub(crate) fn is_image_file(path: &Path) -> bool {
     path.extension()
         .and_then(|ext| ext.to_str())
         .map(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
         .unwrap_or(false)
 }
 
-/// Process a single image file
-fn process_image(path: &Path, dataset_root: &Path) -> DatalintResult<Image> {
-    // Get relative path from dataset root
+/// Split an image path into its directory relative to the dataset root and
+/// its filename, the key images are stored under
+pub(crate) fn image_key(path: &Path, dataset_root: &Path) -> DatalintResult<(String, String)> {
     let relative_path = path
         .parent()
         .and_then(|p| p.strip_prefix(dataset_root).ok())
@@ -36,6 +65,60 @@ fn process_image(path: &Path, dataset_root: &Path) -> DatalintResult<Image> {
         .to_string_lossy()
         .to_string();
 
+    Ok((relative_path, filename))
+}
+
+/// Modification time in nanoseconds since the Unix epoch
+pub(crate) fn file_mtime(metadata: &fs::Metadata) -> Option<i64> {
+    let modified = metadata.modified().ok()?;
+    let since_epoch = modified.duration_since(UNIX_EPOCH).ok()?;
+    i64::try_from(since_epoch.as_nanos()).ok()
+}
+
+/// Classify an error for the scan error log
+fn error_kind(err: &DatalintError) -> &'static str {
+    match err {
+        DatalintError::Io(e) => match e.kind() {
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::PermissionDenied => "permission_denied",
+            _ => "io",
+        },
+        DatalintError::Database(_) => "database",
+        DatalintError::Core(_) | DatalintError::Generic(_) => "invalid",
+        DatalintError::Python(_) => "python",
+    }
+}
+
+/// Build a scan error log entry for a file, at the given stage
+/// (`walk`, `read` or `insert`)
+pub(crate) fn scan_error(
+    path: &Path,
+    dataset_root: &Path,
+    stage: &str,
+    err: &DatalintError,
+) -> ScanError {
+    ScanError {
+        id: None,
+        path: path
+            .strip_prefix(dataset_root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string(),
+        stage: stage.to_string(),
+        kind: error_kind(err).to_string(),
+        message: err.to_string(),
+        created_at: Utc::now().to_rfc3339(),
+    }
+}
+
+/// Process a single image file
+pub(crate) fn process_image(
+    path: &Path,
+    dataset_root: &Path,
+    options: &ScanOptions,
+) -> DatalintResult<Image> {
+    let (relative_path, filename) = image_key(path, dataset_root)?;
+
     // Extract basename without extension
     let name = path
         .file_stem()
@@ -46,40 +129,50 @@ fn process_image(path: &Path, dataset_root: &Path) -> DatalintResult<Image> {
     // Get file metadata
     let metadata = fs::metadata(path)?;
     let file_size = metadata.len() as i64;
+    let file_mtime = file_mtime(&metadata);
 
     // Hash file with xxHash (super fast)
     let file_data = fs::read(path)?;
     let hash = format!("{:016x}", xxh3_64(&file_data));
 
-    // Try to decode image for dimensions and format
-    let (width, height, channels, format, is_corrupted) = match image::load_from_memory(&file_data)
-    {
-        Ok(img) => {
-            let w = img.width() as i32;
-            let h = img.height() as i32;
-            let c = match img.color() {
-                image::ColorType::L8 | image::ColorType::La8 => 1,
-                image::ColorType::Rgb8 | image::ColorType::Rgb16 | image::ColorType::Rgb32F => 3,
-                image::ColorType::Rgba8 | image::ColorType::Rgba16 | image::ColorType::Rgba32F => 4,
-                _ => 3, // Default to RGB
-            };
-            let fmt = path
-                .extension()
-                .and_then(|e| e.to_str())
-                .map(|s| s.to_lowercase())
-                .unwrap_or_else(|| "unknown".to_string());
-
-            (Some(w), Some(h), Some(c), Some(fmt), false)
-        }
-        Err(_) => {
-            // Image is corrupted or unsupported
-            let fmt = path
-                .extension()
-                .and_then(|e| e.to_str())
-                .map(|s| s.to_lowercase());
-
-            (None, None, None, fmt, true)
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|s| s.to_lowercase());
+
+    // Read size and color type from the header unless the pixels are needed
+    let mut hashes = (None, None, None);
+    let decode = options.full_decode || options.perceptual_hashes;
+    let probed = if decode { None } else { probe(&file_data) };
+    let mut is_decoded = false;
+    // Headers in an unknown or unusual layout are decoded to learn the size
+    let header = probed.or_else(|| {
+        let img = image::load_from_memory(&file_data).ok()?;
+        is_decoded = true;
+        if options.perceptual_hashes {
+            hashes = (
+                Some(phash::ahash(&img) as i64),
+                Some(phash::dhash(&img) as i64),
+                Some(phash::phash(&img) as i64),
+            );
         }
+        Some(ImageHeader {
+            width: img.width(),
+            height: img.height(),
+            color: img.color(),
+        })
+    });
+
+    let (width, height, channels, format, is_corrupted) = match header {
+        Some(header) => (
+            Some(header.width as i32),
+            Some(header.height as i32),
+            Some(channel_count(header.color)),
+            Some(extension.unwrap_or_else(|| "unknown".to_string())),
+            false,
+        ),
+        // Image is corrupted or unsupported
+        None => (None, None, None, extension, true),
     };
 
     // Infer split from path (train/val/test)
@@ -114,94 +207,244 @@ fn process_image(path: &Path, dataset_root: &Path) -> DatalintResult<Image> {
         height,
         channels,
         file_size: Some(file_size),
+        file_mtime,
         file_hash: hash,
         is_corrupted,
+        ahash: hashes.0,
+        dhash: hashes.1,
+        phash: hashes.2,
+        is_decoded,
     })
 }
 
-/// Scan a directory for all images
-pub fn scan_images(dataset_path: &Path) -> DatalintResult<Vec<Image>> {
+/// Channel count stored for a decoded color type
+fn channel_count(color: ColorType) -> i32 {
+    match color {
+        ColorType::L8 | ColorType::La8 => 1,
+        ColorType::Rgb8 | ColorType::Rgb16 | ColorType::Rgb32F => 3,
+        ColorType::Rgba8 | ColorType::Rgba16 | ColorType::Rgba32F => 4,
+        _ => 3, // Default to RGB
+    }
+}
+
+/// Walk the dataset, handing each image path to `visit` until it returns
+/// false, and return the entries that could not be listed
+fn walk_image_paths(dataset_path: &Path, mut visit: impl FnMut(PathBuf) -> bool) -> Vec<ScanError> {
+    let mut errors = Vec::new();
+    for entry in WalkDir::new(dataset_path) {
+        match entry {
+            Ok(entry) if entry.file_type().is_file() && is_image_file(entry.path()) => {
+                if !visit(entry.into_path()) {
+                    break;
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                let path = e.path().unwrap_or(dataset_path).to_path_buf();
+                let kind = e.io_error().map(|io| io.kind()).unwrap_or(ErrorKind::Other);
+                let mut error = scan_error(
+                    &path,
+                    dataset_path,
+                    "walk",
+                    &std::io::Error::from(kind).into(),
+                );
+                error.message = e.to_string();
+                if e.loop_ancestor().is_some() {
+                    error.kind = "loop".to_string();
+                }
+                errors.push(error);
+            }
+        }
+    }
+    errors
+}
+
+fn check_dataset_path(dataset_path: &Path) -> DatalintResult<()> {
     if !dataset_path.exists() {
         return Err(DatalintError::Core(format!(
             "Dataset path does not exist: {}",
             dataset_path.display()
         )));
     }
+    Ok(())
+}
+
+/// Collect the paths of all image files below the dataset root, along with
+/// the entries that could not be listed
+pub fn find_image_paths(
+    dataset_path: &Path,
+    progress: &dyn ProgressSink,
+) -> DatalintResult<(Vec<PathBuf>, Vec<ScanError>)> {
+    check_dataset_path(dataset_path)?;
 
-    // Collect all image paths first
-    let image_paths: Vec<PathBuf> = WalkDir::new(dataset_path)
-        .into_iter()
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| entry.file_type().is_file())
-        .map(|entry| entry.into_path())
-        .filter(|path| is_image_file(path))
-        .collect();
-
-    if image_paths.is_empty() {
-        return Ok(Vec::new());
+    let mut paths = Vec::new();
+    let mut cancelled = None;
+    let errors = walk_image_paths(dataset_path, |path| {
+        paths.push(path);
+        if paths.len() % WALK_REPORT_EVERY == 0 {
+            if let Err(e) = progress.update(Stage::Scan, paths.len(), None) {
+                cancelled = Some(e);
+                return false;
+            }
+        }
+        true
+    });
+    if let Some(e) = cancelled {
+        return Err(e);
     }
+    progress.update(Stage::Scan, paths.len(), Some(paths.len()))?;
 
-    // Process images in parallel using rayon
-    let images: Vec<Image> = image_paths
-        .par_iter()
-        .filter_map(|path| process_image(path, dataset_path).ok())
-        .collect();
+    Ok((paths, errors))
+}
 
-    Ok(images)
+/// Hash and probe or decode image files in parallel. Files that cannot be
+/// read are returned as scan errors; undecodable ones are kept and marked
+/// corrupted.
+pub fn process_images(
+    image_paths: &[PathBuf],
+    dataset_path: &Path,
+    options: &ScanOptions,
+) -> (Vec<Image>, Vec<ScanError>) {
+    image_paths
+        .par_iter()
+        .partition_map(|path| match process_image(path, dataset_path, options) {
+            Ok(image) => Either::Left(image),
+            Err(e) => Either::Right(scan_error(path, dataset_path, "read", &e)),
+        })
 }
 
-/// Batch insert images into database
-pub fn insert_images_batch(
-    db: &mut crate::db::Database,
-    images: &[Image],
+/// Scan a directory and insert its images as they are read.
+///
+/// A walker thread feeds paths through a bounded channel to a worker that
+/// hashes and probes them in parallel, one batch at a time; batches go
+/// through a second bounded channel to the calling thread, which appends them
+/// to the database. Memory stays flat however large the dataset, every batch
+/// is committed as soon as it is written, and images keep the walk order.
+///
+/// Progress is reported as [`Stage::Hash`] after every batch, with the total
+/// unknown until the walk completes. Cancelling stops the walk and keeps the
+/// batches already written.
+/// Returns the number of images inserted and the files that failed.
+pub fn scan_images_into(
+    db: &Database,
+    dataset_path: &Path,
+    options: &ScanOptions,
     batch_size: usize,
-) -> DatalintResult<()> {
-    if images.is_empty() {
-        return Ok(());
-    }
+    progress: &dyn ProgressSink,
+) -> DatalintResult<(usize, Vec<ScanError>)> {
+    check_dataset_path(dataset_path)?;
 
-    let mut success_count = 0;
+    let (path_tx, path_rx) = sync_channel::<PathBuf>(batch_size);
+    let (batch_tx, batch_rx) = sync_channel::<(Vec<Image>, Vec<ScanError>)>(PIPELINE_DEPTH);
+    let found = AtomicUsize::new(0);
+    let walked = AtomicBool::new(false);
+
+    let mut inserted = 0;
+    let mut processed = 0;
     let mut errors = Vec::new();
+    let (walk_errors, outcome) = thread::scope(|scope| {
+        // The walker owns the sender, so finishing the walk closes the channel.
+        // A closed channel means the writer stopped, so the walk stops too.
+        let walker = scope.spawn({
+            let (found, walked) = (&found, &walked);
+            move || {
+                let errors = walk_image_paths(dataset_path, |path| {
+                    found.fetch_add(1, Ordering::Relaxed);
+                    path_tx.send(path).is_ok()
+                });
+                walked.store(true, Ordering::Release);
+                errors
+            }
+        });
 
-    // Process in batches for transaction efficiency
-    for chunk in images.chunks(batch_size) {
-        // Start a transaction for this batch
-        match db.conn.transaction() {
-            Ok(tx) => {
-                for img in chunk {
-                    // Use the existing ImageQueries::insert
-                    match ImageQueries::insert(&tx, img) {
-                        Ok(_) => success_count += 1,
-                        Err(e) => {
-                            errors.push(format!("{}/{}: {}", img.relative_path, img.filename, e));
-                        }
-                    }
-                }
+        scope.spawn(move || loop {
+            let paths: Vec<PathBuf> = path_rx.iter().take(batch_size).collect();
+            if paths.is_empty() {
+                break;
+            }
+            if batch_tx
+                .send(process_images(&paths, dataset_path, options))
+                .is_err()
+            {
+                break;
+            }
+        });
 
-                if let Err(e) = tx.commit() {
-                    eprintln!("Failed to commit batch: {}", e);
-                    return Err(e.into());
-                }
+        // Leaving the loop early drops the receiver, which stops the workers
+        let mut outcome = Ok(());
+        for (mut images, read_errors) in batch_rx {
+            processed += images.len() + read_errors.len();
+            inserted += write_images(db.conn(), &mut images, &mut errors);
+            errors.extend(read_errors);
+
+            let total = walked
+                .load(Ordering::Acquire)
+                .then(|| found.load(Ordering::Relaxed));
+            if let Err(e) = progress.update(Stage::Hash, processed, total) {
+                outcome = Err(e);
+                break;
+            }
+        }
+
+        (walker.join(), outcome)
+    });
+    let walk_errors =
+        walk_errors.map_err(|_| DatalintError::Core("Dataset walk panicked".to_string()))?;
+    outcome?;
+
+    errors.splice(0..0, walk_errors);
+    Ok((inserted, errors))
+}
+
+/// Append a batch of images in one transaction, falling back to row-by-row
+/// inserts when the batch fails so only the offending images are lost.
+/// Failures are recorded as scan errors; returns the number of images written.
+fn write_images(conn: &Connection, images: &mut [Image], errors: &mut Vec<ScanError>) -> usize {
+    if append_images(conn, images).is_ok() {
+        return images.len();
+    }
+
+    let mut written = 0;
+    for img in images.iter_mut() {
+        match ImageQueries::insert(conn, img) {
+            Ok(id) => {
+                img.id = Some(id as i32);
+                written += 1;
             }
             Err(e) => {
-                eprintln!("Failed to start transaction: {}", e);
-                return Err(e.into());
+                let path = Path::new(&img.relative_path).join(&img.filename);
+                errors.push(scan_error(&path, Path::new(""), "insert", &e));
             }
         }
     }
+    written
+}
 
-    println!("Inserted {} images", success_count);
+/// The appender flushes full chunks on its own, so a failed batch is only
+/// undone as a whole inside a transaction
+fn append_images(conn: &Connection, images: &mut [Image]) -> DatalintResult<()> {
+    let tx = conn.unchecked_transaction()?;
+    if let Err(e) = ImageQueries::append(&tx, images) {
+        // Ids from the failed batch were never written
+        images.iter_mut().for_each(|image| image.id = None);
+        return Err(e);
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Batch insert images into database, filling in their ids, and return the
+/// images that failed
+pub fn insert_images_batch(
+    db: &mut Database,
+    images: &mut [Image],
+    batch_size: usize,
+) -> DatalintResult<Vec<ScanError>> {
+    let mut errors = Vec::new();
 
-    // TODO create log erro file
-    // if !errors.is_empty() {
-    //     eprintln!("Failed to insert {} images:", errors.len());
-    //     for (i, err) in errors.iter().take(5).enumerate() {
-    //         eprintln!("  {}. {}", i + 1, err);
-    //     }
-    //     if errors.len() > 5 {
-    //         eprintln!("  ... and {} more errors", errors.len() - 5);
-    //     }
-    // }
+    for chunk in images.chunks_mut(batch_size) {
+        write_images(db.conn(), chunk, &mut errors);
+    }
 
-    Ok(())
+    Ok(errors)
 }
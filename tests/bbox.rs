@@ -0,0 +1,169 @@
+mod common;
+
+use common::image;
+use datalint_core::db::models::{Bbox, Image, Label};
+use datalint_core::db::queries::LabelQueries;
+use datalint_core::db::Database;
+use datalint_core::lint::bbox::{Degenerate, ExtremeAspectRatio, OutOfBounds, OutsideImage, Tiny};
+use datalint_core::lint::Rule;
+
+const INSIDE: i32 = 1;
+const STRADDLING: i32 = 2;
+const OUTSIDE: i32 = 3;
+const ZERO_WIDTH: i32 = 4;
+const TINY: i32 = 5;
+const THIN: i32 = 6;
+const WITHIN_TOLERANCE: i32 = 7;
+const PAST_TOLERANCE: i32 = 8;
+const TOUCHING_OUTSIDE: i32 = 9;
+const INVERTED: i32 = 10;
+const UNKNOWN_SIZE: i32 = 11;
+
+/// A 100x100 image holding one box of each kind, and an image of unknown size
+fn seeded() -> Database {
+    let mut db = Database::new_memory().unwrap();
+    let unknown_size = Image {
+        width: None,
+        height: None,
+        ..image("b.jpg")
+    };
+    db.batch_insert_images(&mut [image("a.jpg"), unknown_size])
+        .unwrap();
+    let label_id = LabelQueries::insert(
+        db.conn(),
+        &Label {
+            id: None,
+            name: "person".to_string(),
+            color: None,
+            supercategory: None,
+        },
+    )
+    .unwrap() as i32;
+
+    let corners = [
+        (1, 10.0, 10.0, 50.0, 50.0),
+        (1, 80.0, 80.0, 120.0, 120.0),
+        (1, 150.0, 150.0, 200.0, 200.0),
+        (1, 30.0, 30.0, 30.0, 60.0),
+        (1, 10.0, 10.0, 12.0, 12.0),
+        (1, 60.0, 10.0, 61.0, 60.0),
+        (1, -0.4, 20.0, 40.0, 100.5),
+        (1, 20.0, 20.0, 100.6, 40.0),
+        (1, 100.0, 10.0, 120.0, 20.0),
+        (1, 50.0, 50.0, 40.0, 60.0),
+        (2, -10.0, -10.0, 500.0, 500.0),
+    ];
+    let mut bboxes: Vec<Bbox> = corners
+        .iter()
+        .map(|&(image_id, x1, y1, x2, y2)| Bbox::new(image_id, label_id, x1, y1, x2, y2))
+        .collect();
+    let ids = db.batch_insert_bboxes(&mut bboxes).unwrap();
+    assert_eq!(ids, (1..=UNKNOWN_SIZE as i64).collect::<Vec<_>>());
+    db
+}
+
+/// Ids of the boxes a rule reports, in report order
+fn fired(db: &Database, rule: &dyn Rule) -> Vec<i32> {
+    rule.check(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|finding| finding.bbox_id.unwrap())
+        .collect()
+}
+
+#[test]
+fn out_of_bounds_reports_boxes_crossing_the_border() {
+    let db = seeded();
+    // Overflow within the tolerance, boxes fully outside and images of
+    // unknown size are left to other rules or not judged at all
+    assert_eq!(fired(&db, &OutOfBounds), vec![STRADDLING, PAST_TOLERANCE]);
+}
+
+#[test]
+fn out_of_bounds_tolerance_is_half_a_pixel() {
+    let db = seeded();
+    let findings = OutOfBounds.check(db.conn()).unwrap();
+    let past = findings
+        .iter()
+        .find(|finding| finding.bbox_id == Some(PAST_TOLERANCE))
+        .unwrap();
+    let right = past.extra.as_ref().unwrap()["overflow"]["right"]
+        .as_f64()
+        .unwrap();
+    assert!((right - 0.6).abs() < 1e-4);
+    assert!(!findings
+        .iter()
+        .any(|finding| finding.bbox_id == Some(WITHIN_TOLERANCE)));
+}
+
+#[test]
+fn outside_image_reports_boxes_with_no_overlap() {
+    let db = seeded();
+    // An edge lying on the border does not overlap the image either
+    assert_eq!(fired(&db, &OutsideImage), vec![OUTSIDE, TOUCHING_OUTSIDE]);
+}
+
+#[test]
+fn degenerate_reports_empty_and_inverted_boxes() {
+    let db = seeded();
+    assert_eq!(fired(&db, &Degenerate), vec![ZERO_WIDTH, INVERTED]);
+}
+
+#[test]
+fn tiny_checks_absolute_and_relative_area_but_skips_degenerate_boxes() {
+    let db = seeded();
+    let absolute = Tiny {
+        min_area: 60.0,
+        min_relative_area: 0.0,
+    };
+    assert_eq!(fired(&db, &absolute), vec![TINY, THIN]);
+
+    // 0.1% of a 100x100 image is 10 px²
+    let relative = Tiny {
+        min_area: 0.0,
+        min_relative_area: 0.001,
+    };
+    assert_eq!(fired(&db, &relative), vec![TINY]);
+
+    // Zero and negative areas stay below any minimum yet belong to Degenerate
+    let everything = Tiny {
+        min_area: f64::MAX,
+        min_relative_area: 0.0,
+    };
+    let ids = fired(&db, &everything);
+    assert!(!ids.contains(&ZERO_WIDTH));
+    assert!(!ids.contains(&INVERTED));
+    assert_eq!(ids.len(), 9);
+}
+
+#[test]
+fn extreme_aspect_ratio_reports_thin_boxes_only() {
+    let db = seeded();
+    assert_eq!(
+        fired(&db, &ExtremeAspectRatio { max_ratio: 20.0 }),
+        vec![THIN]
+    );
+    assert!(fired(&db, &ExtremeAspectRatio { max_ratio: 50.0 }).is_empty());
+}
+
+#[test]
+fn inside_box_fires_no_rule() {
+    let db = seeded();
+    let rules: Vec<Box<dyn Rule>> = vec![
+        Box::new(OutOfBounds),
+        Box::new(OutsideImage),
+        Box::new(Degenerate),
+        Box::new(Tiny {
+            min_area: 16.0,
+            min_relative_area: 0.0001,
+        }),
+        Box::new(ExtremeAspectRatio { max_ratio: 20.0 }),
+    ];
+    for rule in &rules {
+        assert!(
+            !fired(&db, rule.as_ref()).contains(&INSIDE),
+            "{} fired for the inside box",
+            rule.id()
+        );
+    }
+}
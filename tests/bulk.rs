@@ -0,0 +1,71 @@
+mod common;
+
+use common::{count, image};
+use datalint_core::db::models::{Bbox, Label, Segmentation};
+use datalint_core::db::queries::{BboxQueries, ImageQueries, LabelQueries};
+use datalint_core::db::Database;
+
+#[test]
+fn appended_rows_get_sequence_ids() {
+    let mut db = Database::new_memory().unwrap();
+
+    let mut images = vec![image("a.jpg"), image("b.jpg")];
+    let ids = db.batch_insert_images(&mut images).unwrap();
+    assert_eq!(ids, vec![1, 2]);
+
+    // Later INSERTs continue from the sequence instead of colliding
+    let id = ImageQueries::insert(db.conn(), &image("c.jpg")).unwrap();
+    assert_eq!(id, 3);
+
+    let stored = ImageQueries::get_all(db.conn()).unwrap();
+    assert_eq!(stored[1].filename, "b.jpg");
+    assert!(!stored[1].is_decoded);
+}
+
+#[test]
+fn appended_children_reference_appended_boxes() {
+    let mut db = Database::new_memory().unwrap();
+    db.batch_insert_images(&mut [image("a.jpg")]).unwrap();
+    let label_id = LabelQueries::insert(
+        db.conn(),
+        &Label {
+            id: None,
+            name: "person".to_string(),
+            color: None,
+            supercategory: None,
+        },
+    )
+    .unwrap() as i32;
+
+    let mut bboxes: Vec<Bbox> = (0..3)
+        .map(|i| Bbox::new(1, label_id, i as f64, 0.0, i as f64 + 10.0, 10.0))
+        .collect();
+    let ids = db.batch_insert_bboxes(&mut bboxes).unwrap();
+    assert_eq!(ids, vec![1, 2, 3]);
+    assert_eq!(bboxes[0].area, Some(100.0));
+
+    let tx = db.transaction().unwrap();
+    let mut segmentations = vec![Segmentation {
+        id: None,
+        bbox_id: 3,
+        vertices: vec![(2.0, 0.0), (12.0, 0.0), (12.0, 10.0)],
+        vertex_count: 3,
+    }];
+    BboxQueries::append_segmentations(&tx, &mut segmentations).unwrap();
+    tx.commit().unwrap();
+
+    assert_eq!(segmentations[0].id, Some(1));
+    assert_eq!(count(&db, "bboxes"), 3);
+    assert_eq!(count(&db, "segmentations"), 1);
+}
+
+#[test]
+fn failed_append_rolls_back_with_its_transaction() {
+    let mut db = Database::new_memory().unwrap();
+    db.batch_insert_images(&mut [image("a.jpg")]).unwrap();
+
+    // Same (relative_path, filename) as the stored image
+    let err = db.batch_insert_images(&mut [image("b.jpg"), image("a.jpg")]);
+    assert!(err.is_err());
+    assert_eq!(count(&db, "images"), 1);
+}
@@ -0,0 +1,207 @@
+mod common;
+
+use common::{count, TempDataset};
+use datalint_core::cache::update_cache_db;
+use datalint_core::db::queries::{ImageQueries, LabelQueries, ScanErrorQueries};
+use datalint_core::db::Database;
+use datalint_core::enums::{DatasetTask, DatasetType};
+use datalint_core::formats::cls::{class_for_dir, has_split_dirs, split_for_dir};
+use datalint_core::progress::NoProgress;
+use datalint_core::scanner::ScanOptions;
+use std::path::Path;
+
+#[test]
+fn maps_top_level_directories_to_splits() {
+    let cases = [
+        ("train/cat", Some("train")),
+        ("val/cat", Some("val")),
+        ("valid", Some("val")),
+        ("Validation/cat", Some("val")),
+        ("TEST/dog/extra", Some("test")),
+        // Only the first directory counts
+        ("cat/train", None),
+        ("training/cat", None),
+        ("", None),
+    ];
+    for (dir, split) in cases {
+        assert_eq!(split_for_dir(Path::new(dir)), split, "{:?}", dir);
+    }
+}
+
+#[test]
+fn takes_the_parent_directory_as_class() {
+    let cases = [
+        ("train/cat", Some("cat"), Some("cat")),
+        ("cat", Some("cat"), Some("cat")),
+        ("animals/pets/dog", Some("dog"), Some("dog")),
+        // Split directories are only skipped in a split layout, the root never
+        // is a class
+        ("train", None, Some("train")),
+        ("Val", None, Some("Val")),
+        ("", None, None),
+    ];
+    for (dir, with_splits, without_splits) in cases {
+        assert_eq!(
+            class_for_dir(Path::new(dir), true).as_deref(),
+            with_splits,
+            "{:?}",
+            dir
+        );
+        assert_eq!(
+            class_for_dir(Path::new(dir), false).as_deref(),
+            without_splits,
+            "{:?}",
+            dir
+        );
+    }
+}
+
+#[test]
+fn takes_top_level_directories_as_splits_only_when_all_are() {
+    assert!(has_split_dirs(["train/cat", "Valid/dog", "test", ""]));
+    assert!(!has_split_dirs(["train/cat", "val/cat", "extra/dog"]));
+    // Classes that happen to be called like a split
+    assert!(!has_split_dirs(["cat", "dog", "test"]));
+    assert!(!has_split_dirs(["", ""]));
+    assert!(!has_split_dirs([]));
+}
+
+#[test]
+fn imports_classes_and_split_directories() {
+    let dataset = TempDataset::new("cls-import");
+    dataset.image("Train/cat/1.png", 8, 8);
+    dataset.image("Train/dog/2.png", 8, 8);
+    dataset.image("valid/cat/3.png", 8, 8);
+    dataset.image("test/4.png", 8, 8);
+    dataset.image("5.png", 8, 8);
+
+    let db = dataset.create(DatasetType::Cls, DatasetTask::Classification);
+
+    let labels: Vec<String> = LabelQueries::get_all(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|label| label.name)
+        .collect();
+    assert_eq!(labels, vec!["cat", "dog"]);
+    assert_eq!(count(&db, "classifications"), 3);
+
+    // Images outside a split directory lose the scanner's guess
+    let mut splits: Vec<(String, Option<String>)> = ImageQueries::get_all(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|image| (image.filename, image.split))
+        .collect();
+    splits.sort();
+    let expected = [
+        ("1.png", "train"),
+        ("2.png", "train"),
+        ("3.png", "val"),
+        ("4.png", "test"),
+        ("5.png", "unknown"),
+    ];
+    assert_eq!(
+        splits,
+        expected
+            .iter()
+            .map(|(file, split)| (file.to_string(), Some(split.to_string())))
+            .collect::<Vec<_>>()
+    );
+
+    assert_eq!(
+        no_class(&db),
+        vec!["5.png".to_string(), "test/4.png".to_string()]
+    );
+}
+
+fn no_class(db: &Database) -> Vec<String> {
+    let mut paths: Vec<String> = ScanErrorQueries::get_by_stage(db.conn(), "annotation")
+        .unwrap()
+        .into_iter()
+        .map(|error| {
+            assert_eq!(error.kind, "no_class");
+            error.path
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// (filename, split, class) of every image
+fn classified(db: &Database) -> Vec<(String, String, Option<String>)> {
+    let mut stmt = db
+        .conn()
+        .prepare(
+            "SELECT i.filename, i.split, l.name FROM images i
+             LEFT JOIN classifications c ON c.image_id = i.id
+             LEFT JOIN labels l ON l.id = c.label_id
+             ORDER BY i.filename",
+        )
+        .unwrap();
+    stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+}
+
+#[test]
+fn split_names_are_classes_unless_every_top_level_directory_is_a_split() {
+    let dataset = TempDataset::new("cls-test-class");
+    dataset.image("train/cat/1.png", 8, 8);
+    dataset.image("val/dog/2.png", 8, 8);
+    dataset.image("test/3.png", 8, 8);
+    let db = dataset.create(DatasetType::Cls, DatasetTask::Classification);
+    assert_eq!(
+        classified(&db),
+        vec![
+            (
+                "1.png".to_string(),
+                "train".to_string(),
+                Some("cat".to_string())
+            ),
+            (
+                "2.png".to_string(),
+                "val".to_string(),
+                Some("dog".to_string())
+            ),
+            ("3.png".to_string(), "test".to_string(), None),
+        ]
+    );
+    drop(db);
+
+    // A class folder next to them turns `test` into a class too, also for
+    // the images that did not change
+    dataset.image("bird/4.png", 8, 8);
+    update_cache_db(
+        &dataset.cache(),
+        dataset.root(),
+        &DatasetType::Cls,
+        &DatasetTask::Classification,
+        &ScanOptions::default(),
+        &NoProgress,
+    )
+    .unwrap();
+    let db = Database::open(&dataset.cache()).unwrap();
+    let unknown = "unknown".to_string();
+    assert_eq!(
+        classified(&db),
+        vec![
+            (
+                "1.png".to_string(),
+                unknown.clone(),
+                Some("cat".to_string())
+            ),
+            (
+                "2.png".to_string(),
+                unknown.clone(),
+                Some("dog".to_string())
+            ),
+            (
+                "3.png".to_string(),
+                unknown.clone(),
+                Some("test".to_string())
+            ),
+            ("4.png".to_string(), unknown, Some("bird".to_string())),
+        ]
+    );
+    assert!(no_class(&db).is_empty());
+}
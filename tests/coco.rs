@@ -0,0 +1,212 @@
+mod common;
+
+use common::{count, TempDataset};
+use datalint_core::cache::update_cache_db;
+use datalint_core::db::queries::{BboxQueries, ImageQueries, LabelQueries, ScanErrorQueries};
+use datalint_core::db::Database;
+use datalint_core::enums::{DatasetTask, DatasetType};
+use datalint_core::progress::NoProgress;
+use datalint_core::scanner::ScanOptions;
+
+const INSTANCES: &str = r#"{
+    "images": [
+        {"id": 1, "file_name": "a.png", "width": 40, "height": 30},
+        {"id": 2, "file_name": "gone.png", "width": 40, "height": 30}
+    ],
+    "categories": [{"id": 1, "name": "cat", "supercategory": "animal"}],
+    "annotations": [
+        {"id": 10, "image_id": 1, "category_id": 1, "bbox": [1, 2, 10, 5]},
+        {"id": 11, "image_id": 2, "category_id": 1, "bbox": [1, 2, 10, 5]},
+        {"id": 12, "image_id": 2, "category_id": 1, "bbox": [3, 4, 10, 5]},
+        {"id": 13, "image_id": 9, "category_id": 1, "bbox": [3, 4, 10, 5]},
+        {"id": 14, "image_id": 1, "category_id": 7, "bbox": [3, 4, 10, 5]}
+    ]
+}"#;
+
+#[test]
+fn logs_annotations_referencing_missing_images() {
+    // A declared image that was not found is reported once, not again for
+    // each of its annotations
+    let dataset = TempDataset::new("coco-missing");
+    dataset.image("train/a.png", 40, 30);
+    dataset.write("annotations/instances_train.json", INSTANCES);
+
+    let db = dataset.create(DatasetType::Coco, DatasetTask::ObjectDetection);
+    assert_eq!(count(&db, "bboxes"), 1);
+
+    let errors = ScanErrorQueries::get_by_stage(db.conn(), "annotation").unwrap();
+    let logged: Vec<(&str, &str, &str)> = errors
+        .iter()
+        .map(|e| (e.path.as_str(), e.kind.as_str(), e.message.as_str()))
+        .collect();
+    let source = "annotations/instances_train.json";
+    assert_eq!(
+        logged,
+        vec![
+            (
+                source,
+                "missing_image",
+                "image 'gone.png' not found in dataset"
+            ),
+            (
+                source,
+                "unknown_category",
+                "annotation 14 has unknown category 7"
+            ),
+            (
+                source,
+                "missing_image",
+                "1 annotations reference missing image 9"
+            ),
+        ]
+    );
+
+    // Scan failures are kept apart
+    assert!(ScanErrorQueries::get_by_stage(db.conn(), "read")
+        .unwrap()
+        .is_empty());
+}
+
+const SEGMENTATIONS: &str = r#"{
+    "images": [{"id": 1, "file_name": "a.png", "width": 40, "height": 30}],
+    "categories": [{"id": 1, "name": "cat"}],
+    "annotations": [
+        {"id": 1, "image_id": 1, "category_id": 1, "bbox": [2, 2, 10, 5],
+         "segmentation": [[2, 2, 12, 2, 12, 7, 2, 7]]},
+        {"id": 2, "image_id": 1, "category_id": 1, "bbox": [20, 10, 3, 4],
+         "segmentation": {"counts": [610, 4, 26, 4, 26, 4, 526], "size": [30, 40]}},
+        {"id": 3, "image_id": 1, "category_id": 1, "bbox": [20, 10, 3, 4], "iscrowd": 1,
+         "segmentation": {"counts": "Rc04j0000d?", "size": [30, 40]}}
+    ]
+}"#;
+
+#[test]
+fn stores_polygons_as_outlines_and_rle_as_masks() {
+    let dataset = TempDataset::new("coco-segmentations");
+    dataset.image("train/a.png", 40, 30);
+    dataset.write("annotations/instances_train.json", SEGMENTATIONS);
+
+    let db = dataset.create(DatasetType::Coco, DatasetTask::InstanceSegmentation);
+    let image_id = ImageQueries::get_all(db.conn()).unwrap()[0].id.unwrap();
+    let bboxes = BboxQueries::get_by_image(db.conn(), image_id).unwrap();
+    assert_eq!(bboxes.len(), 3);
+
+    let masks: Vec<(usize, Option<(i64, bool)>)> = bboxes
+        .iter()
+        .map(|bbox| {
+            let masks = BboxQueries::get_masks(db.conn(), bbox.id.unwrap()).unwrap();
+            let first = masks.first().map(|mask| {
+                assert_eq!((mask.height, mask.width), (30, 40));
+                (mask.area, mask.is_crowd)
+            });
+            (masks.len(), first)
+        })
+        .collect();
+    // Polygons are only kept as outlines, both RLE objects are kept as given
+    assert_eq!(
+        masks,
+        vec![(0, None), (1, Some((12, false))), (1, Some((12, true)))]
+    );
+    let outline = &BboxQueries::get_segmentations(db.conn(), bboxes[0].id.unwrap()).unwrap()[0];
+    assert_eq!(outline.to_rle(30, 40).area(), 50);
+
+    // The instance mask is traced to a polygon; the crowd region is not
+    let vertices: Vec<(i32, String)> = {
+        let mut stmt = db
+            .conn()
+            .prepare(
+                "SELECT bbox_id, CAST(vertices AS VARCHAR) FROM segmentations ORDER BY bbox_id",
+            )
+            .unwrap();
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+    };
+    assert_eq!(
+        vertices,
+        vec![
+            (
+                bboxes[0].id.unwrap(),
+                "[[2.0,2.0],[12.0,2.0],[12.0,7.0],[2.0,7.0]]".to_string()
+            ),
+            (
+                bboxes[1].id.unwrap(),
+                "[[20.0,10.0],[23.0,10.0],[23.0,14.0],[20.0,14.0]]".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn logs_polygons_that_are_too_short_or_odd() {
+    let dataset = TempDataset::new("coco-bad-polygons");
+    dataset.image("train/a.png", 40, 30);
+    dataset.write(
+        "annotations/instances_train.json",
+        r#"{
+            "images": [{"id": 1, "file_name": "a.png", "width": 40, "height": 30}],
+            "categories": [{"id": 1, "name": "cat"}],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 1, "bbox": [2, 2, 10, 5],
+                 "segmentation": [[2, 2, 12, 2], [2, 2, 12, 2, 12, 7, 2], [2, 2, 12, 2, 12, 7]]}
+            ]
+        }"#,
+    );
+
+    let db = dataset.create(DatasetType::Coco, DatasetTask::InstanceSegmentation);
+    // The valid triangle is kept
+    assert_eq!(count(&db, "segmentations"), 1);
+    let messages: Vec<String> = ScanErrorQueries::get_by_stage(db.conn(), "annotation")
+        .unwrap()
+        .into_iter()
+        .map(|error| {
+            assert_eq!(error.kind, "invalid");
+            error.message
+        })
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            "annotation 1: polygon of 4 values is not a list of at least 3 x, y pairs",
+            "annotation 1: polygon of 7 values is not a list of at least 3 x, y pairs",
+        ]
+    );
+}
+
+#[test]
+fn refresh_writes_back_a_changed_supercategory() {
+    let dataset = TempDataset::new("coco-supercategory");
+    dataset.image("train/a.png", 40, 30);
+    let instances = |supercategory: &str| {
+        format!(
+            r#"{{
+                "images": [{{"id": 1, "file_name": "a.png", "width": 40, "height": 30}}],
+                "categories": [{{"id": 1, "name": "cat", "supercategory": "{}"}}],
+                "annotations": [{{"id": 1, "image_id": 1, "category_id": 1, "bbox": [2, 2, 10, 5]}}]
+            }}"#,
+            supercategory
+        )
+    };
+    dataset.write("annotations/instances_train.json", &instances("animal"));
+    drop(dataset.create(DatasetType::Coco, DatasetTask::ObjectDetection));
+
+    dataset.write("annotations/instances_train.json", &instances("pet"));
+    update_cache_db(
+        &dataset.cache(),
+        dataset.root(),
+        &DatasetType::Coco,
+        &DatasetTask::ObjectDetection,
+        &ScanOptions::default(),
+        &NoProgress,
+    )
+    .unwrap();
+
+    let db = Database::open(&dataset.cache()).unwrap();
+    let labels: Vec<(String, Option<String>)> = LabelQueries::get_all(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|label| (label.name, label.supercategory))
+        .collect();
+    assert_eq!(labels, vec![("cat".to_string(), Some("pet".to_string()))]);
+}
@@ -0,0 +1,111 @@
+//! Dataset fixtures shared by the integration tests
+#![allow(dead_code)]
+
+use datalint_core::cache::create_cache_db;
+use datalint_core::db::models::Image;
+use datalint_core::db::Database;
+use datalint_core::enums::{DatasetTask, DatasetType};
+use datalint_core::progress::NoProgress;
+use datalint_core::scanner::ScanOptions;
+use image::{DynamicImage, ImageFormat};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Dataset directory in the system temp dir, removed with its cache on drop
+pub struct TempDataset {
+    root: PathBuf,
+}
+
+impl TempDataset {
+    pub fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("datalint-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let _ = fs::remove_file(root.with_extension("db"));
+        fs::create_dir_all(&root).unwrap();
+        Self { root }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Cache file next to the dataset, so scans never see it
+    pub fn cache(&self) -> PathBuf {
+        self.root.with_extension("db")
+    }
+
+    /// Write a text file below the root, creating its directories
+    pub fn write(&self, relative: &str, content: &str) -> PathBuf {
+        let path = self.root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    /// Write a blank image below the root, encoded after its extension
+    pub fn image(&self, relative: &str, width: u32, height: u32) -> PathBuf {
+        let path = self.root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let format = ImageFormat::from_path(&path).unwrap();
+        DynamicImage::new_rgb8(width, height)
+            .save_with_format(&path, format)
+            .unwrap();
+        path
+    }
+
+    /// Build the cache and open it
+    pub fn create(&self, dataset_type: DatasetType, dataset_task: DatasetTask) -> Database {
+        create_cache_db(
+            &self.cache(),
+            &self.root,
+            &dataset_type,
+            &dataset_task,
+            &ScanOptions::default(),
+            &NoProgress,
+        )
+        .unwrap();
+        Database::open(&self.cache()).unwrap()
+    }
+}
+
+impl Drop for TempDataset {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+        let cache = self.cache();
+        let _ = fs::remove_file(cache.with_extension("db.wal"));
+        let _ = fs::remove_file(cache);
+    }
+}
+
+/// Number of rows in a table
+pub fn count(db: &Database, table: &str) -> i64 {
+    db.conn()
+        .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+}
+
+/// A 100x100 training image row, unique by filename, for tests that seed the
+/// database directly. Adjust fields with struct update syntax.
+pub fn image(filename: &str) -> Image {
+    Image {
+        id: None,
+        name: filename.trim_end_matches(".jpg").to_string(),
+        filename: filename.to_string(),
+        extension: Some("jpg".to_string()),
+        relative_path: "images/train".to_string(),
+        split: Some("train".to_string()),
+        width: Some(100),
+        height: Some(100),
+        channels: Some(3),
+        file_size: Some(1000),
+        file_mtime: None,
+        file_hash: filename.to_string(),
+        is_corrupted: false,
+        ahash: None,
+        dhash: None,
+        phash: None,
+        is_decoded: false,
+    }
+}
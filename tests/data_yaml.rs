@@ -0,0 +1,181 @@
+mod common;
+
+use common::TempDataset;
+use datalint_core::db::queries::{ImageQueries, LabelQueries};
+use datalint_core::enums::{DatasetTask, DatasetType};
+use datalint_core::formats::data_yaml::{is_list_file, DataYaml};
+use std::path::{Path, PathBuf};
+
+fn parse(yaml: &str) -> DataYaml {
+    serde_yaml::from_str(yaml).unwrap()
+}
+
+#[test]
+fn tells_split_directories_from_split_lists() {
+    let config = parse(
+        "train: [images/train, extra/train.txt]\n\
+         val: val.TXT\n\
+         test: images/test\n",
+    );
+    assert_eq!(
+        config.splits(),
+        vec![
+            ("train", vec!["images/train", "extra/train.txt"]),
+            ("val", vec!["val.TXT"]),
+            ("test", vec!["images/test"]),
+        ]
+    );
+    assert!(is_list_file(Path::new("extra/train.txt")));
+    assert!(!is_list_file(Path::new("images/train")));
+    assert_eq!(
+        config.split_lists(Path::new("/data")),
+        vec![PathBuf::from("extra/train.txt"), PathBuf::from("val.TXT")]
+    );
+}
+
+#[test]
+fn resolves_split_paths_against_the_yaml_or_its_path() {
+    let root = Path::new("/data/coco8");
+
+    // Without an absolute `path:` entries are relative to the yaml, with
+    // `./` and `../` prefixes of exports dropped
+    for path in ["", "path: ../datasets/coco8\n"] {
+        let config = parse(&format!("{}train: ../train/images\n", path));
+        assert_eq!(
+            config.relative_to_root("../train/images", root),
+            Some(PathBuf::from("train/images"))
+        );
+        assert_eq!(
+            config.relative_to_root("./val/images", root),
+            Some(PathBuf::from("val/images"))
+        );
+    }
+
+    // An absolute `path:` is the base of relative entries, and only entries
+    // inside the dataset resolve
+    let config = parse("path: /data/coco8\ntrain: images/train\n");
+    assert_eq!(
+        config.relative_to_root("images/train", root),
+        Some(PathBuf::from("images/train"))
+    );
+    assert_eq!(
+        config.relative_to_root("/data/coco8/images/val", root),
+        Some(PathBuf::from("images/val"))
+    );
+    let elsewhere = parse("path: /elsewhere\n");
+    assert_eq!(elsewhere.relative_to_root("images/train", root), None);
+    assert_eq!(
+        parse("path: /elsewhere\ntrain: train.txt\n").split_lists(root),
+        Vec::<PathBuf>::new()
+    );
+}
+
+#[test]
+fn reads_class_names_from_a_list_or_a_mapping() {
+    let expected = vec![(0, "cat".to_string()), (1, "dog".to_string())];
+    assert_eq!(parse("names: [cat, dog]\n").class_names(), expected);
+    assert_eq!(
+        parse("names:\n  1: dog\n  0: cat\n").class_names(),
+        expected
+    );
+
+    // Mapped ids may leave gaps
+    assert_eq!(
+        parse("names: {0: cat, 5: bird}\n").class_names(),
+        vec![(0, "cat".to_string()), (5, "bird".to_string())]
+    );
+    assert!(parse("nc: 2\n").class_names().is_empty());
+}
+
+#[test]
+fn reads_keypoint_metadata() {
+    let config = parse(
+        "names: [person]\n\
+         kpt_shape: [3, 3]\n\
+         flip_idx: [0, 2, 1]\n",
+    );
+    assert_eq!(config.keypoint_shape(), Some((3, 3)));
+    assert_eq!(config.flip_idx, Some(vec![0, 2, 1]));
+    // Unnamed keypoints are named after their index
+    assert_eq!(
+        config.keypoint_names(),
+        Some(vec!["0".to_string(), "1".to_string(), "2".to_string()])
+    );
+
+    let config = parse(
+        "kpt_shape: [2, 2]\n\
+         kpt_names:\n  0: [left_eye, right_eye]\n\
+         skeleton: [[0, 1]]\n",
+    );
+    assert_eq!(config.keypoint_shape(), Some((2, 2)));
+    assert_eq!(
+        config.keypoint_names(),
+        Some(vec!["left_eye".to_string(), "right_eye".to_string()])
+    );
+    assert_eq!(config.skeleton, Some(vec![[0, 1]]));
+
+    // A malformed shape is no shape at all
+    assert_eq!(parse("kpt_shape: [17]\n").keypoint_shape(), None);
+    assert_eq!(parse("names: [cat]\n").keypoint_names(), None);
+}
+
+#[test]
+fn loads_configs_and_reports_invalid_ones() {
+    let dataset = TempDataset::new("data-yaml-load");
+    let path = dataset.write("data.yaml", "train: images/train\nnames: [cat]\n");
+    let config = DataYaml::load(&path).unwrap();
+    assert!(config.is_dataset_config());
+    assert_eq!(config.splits(), vec![("train", vec!["images/train"])]);
+
+    let path = dataset.write("data.yaml", "names: [cat\n");
+    let err = DataYaml::load(&path).unwrap_err();
+    assert!(err.to_string().contains("Invalid "));
+
+    // Settings files parse, but declare nothing a dataset would
+    let path = dataset.write("data.yaml", "epochs: 10\n");
+    assert!(!DataYaml::load(&path).unwrap().is_dataset_config());
+}
+
+#[test]
+fn import_takes_splits_and_class_order_from_the_config() {
+    let dataset = TempDataset::new("data-yaml-import");
+    // Directory names that the path-based split guess would not recognize
+    dataset.image("set_a/x.png", 10, 10);
+    dataset.image("set_b/y.png", 10, 10);
+    dataset.image("set_b/z.png", 10, 10);
+    dataset.write("lists/holdout.txt", "./set_b/y.png\n\n");
+    dataset.write(
+        "data.yaml",
+        &format!(
+            "path: {}\ntrain: [set_a]\nval: lists/holdout.txt\nnames: {{1: dog, 0: cat}}\n",
+            dataset.root().display()
+        ),
+    );
+
+    let db = dataset.create(DatasetType::Yolo, DatasetTask::ObjectDetection);
+    let mut splits: Vec<(String, String)> = ImageQueries::get_all(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|image| (image.filename, image.split.unwrap()))
+        .collect();
+    splits.sort();
+    // Images no split declares are left unknown
+    assert_eq!(
+        splits,
+        vec![
+            ("x.png".to_string(), "train".to_string()),
+            ("y.png".to_string(), "val".to_string()),
+            ("z.png".to_string(), "unknown".to_string()),
+        ]
+    );
+
+    let labels: Vec<(Option<i32>, String)> = LabelQueries::get_all(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|label| (label.id, label.name))
+        .collect();
+    assert_eq!(
+        labels,
+        vec![(Some(1), "cat".to_string()), (Some(2), "dog".to_string())]
+    );
+}
@@ -0,0 +1,188 @@
+mod common;
+
+use common::TempDataset;
+use datalint_core::cache::{create_cache_db, update_cache_db};
+use datalint_core::enums::{DatasetTask, DatasetType};
+use datalint_core::formats::data_yaml::DataYaml;
+use datalint_core::formats::detect::{best_guess, detect_dataset_format};
+use datalint_core::progress::NoProgress;
+use datalint_core::scanner::ScanOptions;
+
+const COCO: &str = r#"{"images": [], "annotations": [], "categories": []}"#;
+
+const VOC: &str = r#"<annotation><filename>a.jpg</filename></annotation>"#;
+
+/// Detected formats with confidences rounded to two decimals
+fn ranking(dataset: &TempDataset) -> Vec<(DatasetType, f64)> {
+    detect_dataset_format(dataset.root())
+        .into_iter()
+        .map(|format| {
+            let confidence = (format.confidence * 100.0).round() / 100.0;
+            (format.dataset_type, confidence)
+        })
+        .collect()
+}
+
+fn guess(dataset: &TempDataset) -> Option<DatasetType> {
+    best_guess(dataset.root()).map(|format| format.dataset_type)
+}
+
+#[test]
+fn detects_yolo() {
+    let dataset = TempDataset::new("detect-yolo");
+    dataset.write("data.yaml", "train: images/train\nnames: [cat]\n");
+    dataset.image("images/train/a.png", 4, 4);
+    dataset.write("labels/train/a.txt", "0 0.5 0.5 0.2 0.2\n");
+
+    assert_eq!(ranking(&dataset), vec![(DatasetType::Yolo, 1.0)]);
+    let format = best_guess(dataset.root()).unwrap();
+    assert_eq!(format.evidence[0], "found dataset config 'data.yaml'");
+}
+
+#[test]
+fn detects_yolo_from_labels_alone() {
+    let dataset = TempDataset::new("detect-yolo-labels");
+    dataset.image("images/train/a.png", 4, 4);
+    dataset.write("labels/train/a.txt", "0 0.5 0.5 0.2 0.2\n");
+
+    assert_eq!(ranking(&dataset), vec![(DatasetType::Yolo, 0.6)]);
+    assert_eq!(guess(&dataset), Some(DatasetType::Yolo));
+}
+
+#[test]
+fn detects_both_coco_layouts() {
+    let roboflow = TempDataset::new("detect-coco");
+    roboflow.image("train/a.png", 4, 4);
+    roboflow.write("train/_annotations.coco.json", COCO);
+    assert_eq!(ranking(&roboflow), vec![(DatasetType::Coco, 0.95)]);
+
+    let classic = TempDataset::new("detect-coco-classic");
+    classic.image("train2017/a.png", 4, 4);
+    classic.write("annotations/instances_train2017.json", COCO);
+    assert_eq!(ranking(&classic), vec![(DatasetType::CocoClassic, 0.95)]);
+
+    // Json files without the COCO keys only hint at the layout
+    let other = TempDataset::new("detect-coco-other");
+    other.image("train2017/a.png", 4, 4);
+    other.write("annotations/notes.json", "{}");
+    assert_eq!(ranking(&other), vec![(DatasetType::CocoClassic, 0.5)]);
+}
+
+#[test]
+fn detects_voc() {
+    let dataset = TempDataset::new("detect-voc");
+    dataset.image("VOC2012/JPEGImages/a.jpg", 4, 4);
+    dataset.write("VOC2012/Annotations/a.xml", VOC);
+    dataset.write("VOC2012/ImageSets/Main/train.txt", "a\n");
+
+    assert_eq!(ranking(&dataset), vec![(DatasetType::Voc, 1.0)]);
+}
+
+#[test]
+fn detects_class_folders() {
+    let dataset = TempDataset::new("detect-cls");
+    dataset.image("train/cat/a.png", 4, 4);
+    dataset.image("train/dog/b.png", 4, 4);
+    dataset.image("val/cat/c.png", 4, 4);
+
+    assert_eq!(ranking(&dataset), vec![(DatasetType::Cls, 0.9)]);
+    assert_eq!(guess(&dataset), Some(DatasetType::Cls));
+}
+
+#[test]
+fn ignores_unrelated_yaml_files() {
+    let dataset = TempDataset::new("detect-settings");
+    dataset.write("settings.yaml", "epochs: 10\nbatch: 16\n");
+    dataset.image("train/cat/a.png", 4, 4);
+    dataset.image("train/dog/b.png", 4, 4);
+
+    assert_eq!(DataYaml::find(dataset.root()), None);
+    assert_eq!(ranking(&dataset), vec![(DatasetType::Cls, 0.9)]);
+
+    // The same fallback picks up a real config under any name
+    dataset.write("settings.yaml", "names: [cat, dog]\n");
+    assert_eq!(
+        DataYaml::find(dataset.root()),
+        Some(dataset.root().join("settings.yaml"))
+    );
+    assert_eq!(
+        ranking(&dataset),
+        vec![(DatasetType::Cls, 0.8), (DatasetType::Yolo, 0.4)]
+    );
+}
+
+#[test]
+fn ranks_mixed_layouts() {
+    // COCO json next to YOLO labels: both are reported, strongest first
+    let dataset = TempDataset::new("detect-mixed");
+    dataset.image("train/a.png", 4, 4);
+    dataset.write("train/_annotations.coco.json", COCO);
+    dataset.write("labels/train/a.txt", "0 0.5 0.5 0.2 0.2\n");
+
+    assert_eq!(
+        ranking(&dataset),
+        vec![(DatasetType::Coco, 0.95), (DatasetType::Yolo, 0.6)]
+    );
+    assert_eq!(guess(&dataset), Some(DatasetType::Coco));
+}
+
+#[test]
+fn leaves_weak_evidence_undecided() {
+    // Label files that are not YOLO rows
+    let dataset = TempDataset::new("detect-weak");
+    dataset.image("images/a.png", 4, 4);
+    dataset.write("labels/a.txt", "a cat on a mat\n");
+    assert_eq!(ranking(&dataset), vec![(DatasetType::Yolo, 0.3)]);
+    assert_eq!(guess(&dataset), None);
+
+    // A single class folder is not a classification dataset
+    let single = TempDataset::new("detect-single-class");
+    single.image("cat/a.png", 4, 4);
+    assert!(ranking(&single).is_empty());
+    assert_eq!(guess(&single), None);
+
+    let empty = TempDataset::new("detect-empty");
+    assert!(ranking(&empty).is_empty());
+}
+
+#[test]
+fn cache_results_carry_the_detected_format() {
+    let create = |dataset: &TempDataset| {
+        create_cache_db(
+            &dataset.cache(),
+            dataset.root(),
+            &DatasetType::Unknown,
+            &DatasetTask::ObjectDetection,
+            &ScanOptions::default(),
+            &NoProgress,
+        )
+        .unwrap()
+    };
+
+    let dataset = TempDataset::new("detect-cache");
+    dataset.write("data.yaml", "names: [cat]\n");
+    dataset.image("images/train/a.png", 4, 4);
+    dataset.write("labels/train/a.txt", "0 0.5 0.5 0.2 0.2\n");
+    let created = create(&dataset);
+    let detected = created.detected.unwrap();
+    assert_eq!(detected.dataset_type, DatasetType::Yolo);
+    assert_eq!(created.annotation_errors, 0);
+
+    // Given formats are taken as they are
+    let updated = update_cache_db(
+        &dataset.cache(),
+        dataset.root(),
+        &DatasetType::Yolo,
+        &DatasetTask::ObjectDetection,
+        &ScanOptions::default(),
+        &NoProgress,
+    )
+    .unwrap();
+    assert!(updated.detected.is_none());
+
+    let images_only = TempDataset::new("detect-cache-images");
+    images_only.image("a.png", 4, 4);
+    let created = create(&images_only);
+    assert!(created.detected.is_none());
+    assert_eq!(created.images, 1);
+}
@@ -0,0 +1,184 @@
+mod common;
+
+use common::image;
+use datalint_core::db::models::Image;
+use datalint_core::db::Database;
+use datalint_core::enums::Severity;
+use datalint_core::lint::duplicates::{
+    DuplicateImage, NearDuplicate, NearDuplicateLeakage, SplitLeakage,
+};
+use datalint_core::lint::{find_duplicate_images, find_near_duplicate_images, Rule};
+
+/// A copy in `split` whose file contents hash to `file_hash`
+fn copy(filename: &str, split: &str, file_hash: &str) -> Image {
+    Image {
+        relative_path: format!("images/{}", split),
+        split: Some(split.to_string()),
+        file_hash: file_hash.to_string(),
+        ..image(filename)
+    }
+}
+
+fn seeded(images: &mut [Image]) -> Database {
+    let mut db = Database::new_memory().unwrap();
+    db.batch_insert_images(images).unwrap();
+    db
+}
+
+/// (image id, kept image id) of each finding
+fn reported(db: &Database, rule: &dyn Rule) -> Vec<(i32, i64)> {
+    rule.check(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|finding| {
+            let keep = finding.extra.unwrap()["keep_image_id"].as_i64().unwrap();
+            (finding.image_id.unwrap(), keep)
+        })
+        .collect()
+}
+
+#[test]
+fn keeps_test_then_val_then_train_then_unknown() {
+    // Copies listed from least to most preferred split
+    let images = vec![
+        copy("a.jpg", "unknown", "h"),
+        copy("b.jpg", "train", "h"),
+        copy("c.jpg", "val", "h"),
+        copy("d.jpg", "test", "h"),
+        copy("e.jpg", "test", "h"),
+    ];
+    let db = seeded(&mut images.clone());
+
+    let clusters = find_duplicate_images(db.conn()).unwrap();
+    assert_eq!(clusters.len(), 1);
+    let cluster = &clusters[0];
+    assert_eq!(cluster.splits, vec!["test", "val", "train", "unknown"]);
+    // The lowest id wins within the preferred split
+    assert_eq!(cluster.keep, 4);
+    assert_eq!(cluster.remove, vec![1, 2, 3, 5]);
+
+    // Taking away the preferred split each time moves the kept copy down
+    for (copies, split) in [(3, "val"), (2, "train"), (1, "unknown")] {
+        let mut images = images[..copies].to_vec();
+        images.push(copy("z.jpg", "unknown", "h"));
+        let db = seeded(&mut images);
+        let cluster = &find_duplicate_images(db.conn()).unwrap()[0];
+        assert_eq!(cluster.keep, copies as i32, "expected the {} copy", split);
+    }
+}
+
+#[test]
+fn clusters_split_where_the_file_hash_changes() {
+    let mut images = vec![
+        copy("a1.jpg", "train", "a"),
+        copy("b1.jpg", "train", "b"),
+        copy("a2.jpg", "train", "a"),
+        copy("c1.jpg", "train", "c"),
+        copy("b2.jpg", "val", "b"),
+        copy("b3.jpg", "train", "b"),
+    ];
+    let db = seeded(&mut images);
+
+    let clusters: Vec<(String, Vec<Option<i32>>)> = find_duplicate_images(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|cluster| {
+            let ids = cluster.images.iter().map(|image| image.id).collect();
+            (cluster.file_hash, ids)
+        })
+        .collect();
+    // The unique file "c" forms no cluster
+    assert_eq!(
+        clusters,
+        vec![
+            ("a".to_string(), vec![Some(1), Some(3)]),
+            ("b".to_string(), vec![Some(2), Some(5), Some(6)]),
+        ]
+    );
+}
+
+#[test]
+fn same_split_copies_are_duplicates_and_cross_split_copies_leak() {
+    let mut images = vec![
+        copy("a1.jpg", "train", "a"),
+        copy("a2.jpg", "train", "a"),
+        copy("b1.jpg", "train", "b"),
+        copy("b2.jpg", "val", "b"),
+        copy("b3.jpg", "train", "b"),
+    ];
+    let db = seeded(&mut images);
+
+    let clusters = find_duplicate_images(db.conn()).unwrap();
+    assert!(!clusters[0].crosses_splits());
+    assert!(clusters[1].crosses_splits());
+
+    // Each cluster goes to exactly one rule, with a finding per removed copy
+    assert_eq!(reported(&db, &DuplicateImage), vec![(2, 1)]);
+    assert_eq!(reported(&db, &SplitLeakage), vec![(3, 4), (5, 4)]);
+}
+
+/// A distinct file with a given pHash
+fn hashed(filename: &str, split: &str, phash: u64) -> Image {
+    let mut image = copy(filename, split, filename);
+    image.phash = Some(phash as i64);
+    image
+}
+
+#[test]
+fn near_duplicate_groups_spanning_splits_leak() {
+    let base: u64 = 0xf0f0_3c3c_aa55_0ff0;
+    let mut images = vec![
+        hashed("a.jpg", "train", base),
+        hashed("b.jpg", "val", base ^ 0b111),
+        // Linked to the group through b only
+        hashed("c.jpg", "train", base ^ 0b111 ^ (0b11 << 40)),
+        hashed("d.jpg", "train", !base),
+        hashed("e.jpg", "train", !base ^ 1),
+        hashed("f.jpg", "test", base.rotate_left(17)),
+    ];
+    let db = seeded(&mut images);
+
+    let groups = find_near_duplicate_images(db.conn(), "phash", 4).unwrap();
+    let members: Vec<Vec<Option<i32>>> = groups
+        .iter()
+        .map(|group| group.images.iter().map(|image| image.id).collect())
+        .collect();
+    assert_eq!(
+        members,
+        vec![vec![Some(1), Some(2), Some(3)], vec![Some(4), Some(5)]]
+    );
+    assert_eq!(groups[0].splits, vec!["val", "train"]);
+    assert_eq!(groups[0].distance, 3);
+    assert_eq!(groups[0].keep, 2);
+
+    let leakage = NearDuplicateLeakage { max_distance: 4 };
+    let same_split = NearDuplicate { max_distance: 4 };
+    assert_eq!(reported(&db, &leakage), vec![(1, 2), (3, 2)]);
+    assert_eq!(reported(&db, &same_split), vec![(5, 4)]);
+}
+
+#[test]
+fn near_duplicate_rules_report_missing_perceptual_hashes() {
+    let mut images = vec![
+        hashed("a.jpg", "train", 0),
+        copy("b.jpg", "train", "b"),
+        copy("c.jpg", "val", "c"),
+    ];
+    let db = seeded(&mut images);
+
+    let rules: [&dyn Rule; 2] = [
+        &NearDuplicate { max_distance: 6 },
+        &NearDuplicateLeakage { max_distance: 6 },
+    ];
+    for rule in rules {
+        let findings = rule.check(db.conn()).unwrap();
+        assert_eq!(findings.len(), 1, "{}", rule.id());
+        assert_eq!(findings[0].severity, Severity::Info);
+        assert_eq!(findings[0].rule_id, rule.id());
+        let extra = findings[0].extra.as_ref().unwrap();
+        assert_eq!(
+            (extra["missing"].as_i64(), extra["images"].as_i64()),
+            (Some(2), Some(3))
+        );
+    }
+}
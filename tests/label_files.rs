@@ -0,0 +1,102 @@
+mod common;
+
+use common::TempDataset;
+use datalint_core::db::Database;
+use datalint_core::enums::{DatasetTask, DatasetType};
+use datalint_core::lint::image::CORRUPTED_IMAGE;
+use datalint_core::lint::label_files::{EMPTY_LABEL_FILE, MISSING_LABEL_FILE, ORPHAN_LABEL_FILE};
+use datalint_core::lint::Rule;
+
+/// YOLO dataset with one label file problem of each kind
+fn dataset(name: &str) -> (TempDataset, Database) {
+    let dataset = TempDataset::new(name);
+    dataset.write("data.yaml", "names: [cat]\n");
+    dataset.image("images/train/a.png", 10, 10);
+    dataset.write("labels/train/a.txt", "0 0.5 0.5 0.2 0.2\n");
+    dataset.image("images/train/empty.png", 10, 10);
+    dataset.write("labels/train/empty.txt", "");
+    dataset.write("labels/train/orphan.txt", "0 0.5 0.5 0.2 0.2\n");
+    // Unlabeled on purpose
+    dataset.image("images/train/backgrounds/b.png", 10, 10);
+    dataset.image("images/train/negative_01.png", 10, 10);
+    // Unlabeled by mistake, one of them at the dataset root
+    dataset.image("images/train/unlabeled.png", 10, 10);
+    dataset.image("root.png", 10, 10);
+    dataset.write("broken.png", "not a png");
+
+    let db = dataset.create(DatasetType::Yolo, DatasetTask::ObjectDetection);
+    (dataset, db)
+}
+
+/// (image id set, message, path) of each finding
+fn reported(db: &Database, rule: &dyn Rule) -> Vec<(bool, String, Option<String>)> {
+    rule.check(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|finding| (finding.image_id.is_some(), finding.message, finding.path))
+        .collect()
+}
+
+#[test]
+fn reports_label_files_without_an_image() {
+    let (_dataset, db) = dataset("label-files-orphan");
+    assert_eq!(
+        reported(&db, &ORPHAN_LABEL_FILE),
+        vec![(
+            false,
+            "label file has no matching image: labels/train/orphan.txt".to_string(),
+            Some("labels/train/orphan.txt".to_string())
+        )]
+    );
+}
+
+#[test]
+fn reports_empty_label_files_as_background() {
+    let (_dataset, db) = dataset("label-files-empty");
+    assert_eq!(
+        reported(&db, &EMPTY_LABEL_FILE),
+        vec![(
+            true,
+            "empty label file, image treated as background: labels/train/empty.txt".to_string(),
+            Some("labels/train/empty.txt".to_string())
+        )]
+    );
+}
+
+#[test]
+fn reports_unlabeled_images_unless_marked_as_background() {
+    let (_dataset, db) = dataset("label-files-missing");
+    // Background and negative images are intentional, corrupted images have
+    // their own rule, and root images carry no leading slash
+    let mut paths: Vec<Option<String>> = reported(&db, &MISSING_LABEL_FILE)
+        .into_iter()
+        .map(|(_, message, path)| {
+            assert_eq!(
+                message,
+                format!("no label file for {}", path.clone().unwrap())
+            );
+            path
+        })
+        .collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        vec![
+            Some("images/train/unlabeled.png".to_string()),
+            Some("root.png".to_string())
+        ]
+    );
+}
+
+#[test]
+fn corrupted_images_at_the_root_have_relative_paths() {
+    let (_dataset, db) = dataset("label-files-corrupted");
+    assert_eq!(
+        reported(&db, &CORRUPTED_IMAGE),
+        vec![(
+            true,
+            "image cannot be decoded: broken.png".to_string(),
+            Some("broken.png".to_string())
+        )]
+    );
+}
@@ -0,0 +1,215 @@
+mod common;
+
+use common::{count, image};
+use datalint_core::db::models::{Bbox, Label};
+use datalint_core::db::queries::{FindingQueries, LabelQueries};
+use datalint_core::db::Database;
+use datalint_core::enums::{DatasetTask, Severity};
+use datalint_core::lint::{run_rules, LintConfig, Rule, RuleRegistry, SqlRule};
+use datalint_core::progress::NoProgress;
+use serde_json::json;
+
+/// One finding per image
+const PER_IMAGE: SqlRule = SqlRule {
+    id: "test.per_image",
+    severity: Severity::Info,
+    description: "Reports every image",
+    tasks: &[],
+    sql: r#"
+        SELECT id, NULL, 'image ' || filename, NULL, filename
+        FROM images ORDER BY id
+    "#,
+};
+
+/// One finding per box, with every column set
+const PER_BOX: SqlRule = SqlRule {
+    id: "test.per_box",
+    severity: Severity::Error,
+    description: "Reports every box",
+    tasks: &[DatasetTask::ObjectDetection],
+    sql: r#"
+        SELECT b.image_id, b.id, 'box ' || b.id || ' on ' || i.filename,
+               '{"width": ' || b.w || ', "tags": ["a", "b"]}',
+               i.relative_path || '/' || i.filename
+        FROM bboxes b JOIN images i ON i.id = b.image_id
+        ORDER BY b.id
+    "#,
+};
+
+fn test_rules() -> RuleRegistry {
+    let mut registry = RuleRegistry::new();
+    registry.register(Box::new(PER_IMAGE));
+    registry.register(Box::new(PER_BOX));
+    registry
+}
+
+/// Two images, the second holding one box
+fn seeded() -> Database {
+    let mut db = Database::new_memory().unwrap();
+    db.batch_insert_images(&mut [image("a.jpg"), image("b.jpg")])
+        .unwrap();
+    let label_id = LabelQueries::insert(
+        db.conn(),
+        &Label {
+            id: None,
+            name: "cat".to_string(),
+            color: None,
+            supercategory: None,
+        },
+    )
+    .unwrap() as i32;
+    db.batch_insert_bboxes(&mut [Bbox::new(2, label_id, 10.0, 10.0, 30.0, 20.0)])
+        .unwrap();
+    db
+}
+
+fn ids(rules: Vec<&dyn Rule>) -> Vec<String> {
+    rules.iter().map(|rule| rule.id().to_string()).collect()
+}
+
+#[test]
+fn selects_rules_applying_to_the_task() {
+    let registry = RuleRegistry::with_builtin(&LintConfig::default());
+
+    let classification = ids(registry.select(&DatasetTask::Classification, None).unwrap());
+    assert!(classification.contains(&"image.corrupted".to_string()));
+    assert!(classification.contains(&"image.split_leakage".to_string()));
+    assert!(!classification.iter().any(|id| id.starts_with("bbox.")));
+    assert!(!classification.iter().any(|id| id.starts_with("label.")));
+
+    let detection = ids(registry
+        .select(&DatasetTask::ObjectDetection, None)
+        .unwrap());
+    assert_eq!(detection.len(), registry.rules().count());
+
+    let registry = test_rules();
+    assert_eq!(
+        ids(registry.select(&DatasetTask::Classification, None).unwrap()),
+        vec!["test.per_image"]
+    );
+    assert_eq!(
+        ids(registry
+            .select(&DatasetTask::ObjectDetection, None)
+            .unwrap()),
+        vec!["test.per_image", "test.per_box"]
+    );
+}
+
+#[test]
+fn selects_rules_by_id_in_the_order_given() {
+    let registry = test_rules();
+    let wanted = vec!["test.per_box".to_string(), "test.per_image".to_string()];
+    // Ids are taken as given, even for a task the rule does not list
+    let selected = registry
+        .select(&DatasetTask::Classification, Some(&wanted))
+        .unwrap();
+    assert_eq!(ids(selected), wanted);
+}
+
+#[test]
+fn rejects_unknown_rule_ids() {
+    let registry = test_rules();
+    let wanted = vec!["test.per_image".to_string(), "test.missing".to_string()];
+    let err = registry
+        .select(&DatasetTask::ObjectDetection, Some(&wanted))
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("Unknown rule 'test.missing'"));
+
+    let mut db = seeded();
+    assert!(run_rules(
+        &mut db,
+        &registry,
+        &DatasetTask::ObjectDetection,
+        Some(&wanted),
+        &NoProgress
+    )
+    .is_err());
+    assert_eq!(count(&db, "findings"), 0);
+}
+
+#[test]
+#[should_panic(expected = "registered twice")]
+fn rejects_duplicate_rule_ids() {
+    let mut registry = test_rules();
+    registry.register(Box::new(PER_IMAGE));
+}
+
+#[test]
+fn rerunning_a_rule_replaces_its_findings() {
+    let mut db = seeded();
+    let registry = test_rules();
+    let task = DatasetTask::ObjectDetection;
+
+    let summary = run_rules(&mut db, &registry, &task, None, &NoProgress).unwrap();
+    assert_eq!(
+        summary.by_rule,
+        vec![
+            ("test.per_image".to_string(), 2),
+            ("test.per_box".to_string(), 1)
+        ]
+    );
+    assert_eq!(summary.total(), 3);
+
+    run_rules(&mut db, &registry, &task, None, &NoProgress).unwrap();
+    assert_eq!(count(&db, "findings"), 3);
+
+    // Only the rule that runs again has its findings replaced
+    db.conn()
+        .execute("DELETE FROM images WHERE id = 1", [])
+        .unwrap();
+    let only = vec!["test.per_image".to_string()];
+    let summary = run_rules(&mut db, &registry, &task, Some(&only), &NoProgress).unwrap();
+    assert_eq!(summary.by_rule, vec![("test.per_image".to_string(), 1)]);
+    assert_eq!(
+        FindingQueries::count_by_rule(db.conn()).unwrap(),
+        vec![
+            ("test.per_box".to_string(), 1),
+            ("test.per_image".to_string(), 1)
+        ]
+    );
+}
+
+#[test]
+fn sql_rule_findings_round_trip_through_the_table() {
+    let mut db = seeded();
+    let only = vec!["test.per_box".to_string()];
+    run_rules(
+        &mut db,
+        &test_rules(),
+        &DatasetTask::ObjectDetection,
+        Some(&only),
+        &NoProgress,
+    )
+    .unwrap();
+
+    let findings = FindingQueries::get_by_rule(db.conn(), "test.per_box").unwrap();
+    assert_eq!(findings.len(), 1);
+    let finding = &findings[0];
+    assert!(finding.id.is_some());
+    assert_eq!(finding.rule_id, "test.per_box");
+    assert_eq!(finding.severity, Severity::Error);
+    assert_eq!(finding.image_id, Some(2));
+    assert_eq!(finding.bbox_id, Some(1));
+    assert_eq!(finding.message, "box 1 on b.jpg");
+    assert_eq!(
+        finding.extra,
+        Some(json!({ "width": 20.0, "tags": ["a", "b"] }))
+    );
+    assert_eq!(finding.path.as_deref(), Some("images/train/b.jpg"));
+
+    // Findings without image, box or extra keep them empty
+    run_rules(
+        &mut db,
+        &test_rules(),
+        &DatasetTask::ObjectDetection,
+        None,
+        &NoProgress,
+    )
+    .unwrap();
+    let findings = FindingQueries::get_by_image(db.conn(), 1).unwrap();
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].severity, Severity::Info);
+    assert_eq!(findings[0].bbox_id, None);
+    assert_eq!(findings[0].extra, None);
+}
@@ -0,0 +1,166 @@
+use datalint_core::db::schema::{MIGRATIONS, SCHEMA_VERSION};
+use datalint_core::db::Database;
+use duckdb::Connection;
+use std::path::PathBuf;
+
+/// Fresh cache path in the system temp dir, removed on drop
+struct TempCache(PathBuf);
+
+impl TempCache {
+    fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("datalint-{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        Self(path)
+    }
+}
+
+impl Drop for TempCache {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+        let _ = std::fs::remove_file(self.0.with_extension("db.wal"));
+    }
+}
+
+/// Write a cache the way releases before versioning did: schema v1, no
+/// schema_version table
+fn write_v1_cache(path: &PathBuf) {
+    let conn = Connection::open(path).unwrap();
+    conn.execute_batch(MIGRATIONS[0].sql).unwrap();
+    conn.execute_batch(
+        r#"
+        INSERT INTO cache_metadata
+            (id, created_at, updated_at, datalint_version, dataset_path, dataset_type, dataset_task)
+        VALUES (1, '2025-01-01T00:00:00Z', '2025-01-01T00:00:00Z', '0.1.0', '/data', 'yolo', 'detect');
+        INSERT INTO labels (name) VALUES ('person');
+        INSERT INTO images (name, filename, relative_path, split, file_hash)
+        VALUES ('a', 'a.jpg', 'images/train', 'train', '00');
+        INSERT INTO bboxes (image_id, label_id, x1, y1, x2, y2, cx, cy, w, h, area)
+        VALUES (1, 1, 0, 0, 10, 10, 5, 5, 10, 10, 100);
+        "#,
+    )
+    .unwrap();
+}
+
+fn column_exists(db: &Database, table: &str, column: &str) -> bool {
+    let count: i64 = db
+        .conn()
+        .query_row(
+            "SELECT COUNT(*) FROM information_schema.columns WHERE table_name = ? AND column_name = ?",
+            [table, column],
+            |row| row.get(0),
+        )
+        .unwrap();
+    count > 0
+}
+
+#[test]
+fn fresh_database_is_at_latest_version() {
+    let db = Database::new_memory().unwrap();
+    assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+}
+
+#[test]
+fn migrations_are_numbered_in_order() {
+    for (i, migration) in MIGRATIONS.iter().enumerate() {
+        assert_eq!(migration.version as usize, i + 1, "{}", migration.name);
+    }
+}
+
+#[test]
+fn upgrades_v1_cache_and_keeps_data() {
+    let cache = TempCache::new("upgrade-v1");
+    write_v1_cache(&cache.0);
+
+    let db = Database::open(&cache.0).unwrap();
+    assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+
+    assert!(column_exists(&db, "labels", "supercategory"));
+    assert!(column_exists(&db, "bboxes", "attributes"));
+    assert!(column_exists(&db, "images", "file_mtime"));
+    assert!(column_exists(&db, "images", "phash"));
+    assert!(column_exists(&db, "images", "is_decoded"));
+    assert!(column_exists(&db, "masks", "counts"));
+    assert!(column_exists(&db, "label_files", "annotation_count"));
+    assert!(column_exists(&db, "findings", "path"));
+
+    let metadata = db.get_cache_metadata().unwrap().unwrap();
+    assert_eq!(metadata.dataset_path, "/data");
+    let bboxes: i64 = db
+        .conn()
+        .query_row("SELECT COUNT(*) FROM bboxes", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(bboxes, 1);
+
+    let applied: i64 = db
+        .conn()
+        .query_row("SELECT COUNT(*) FROM schema_version", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(applied as usize, MIGRATIONS.len());
+}
+
+#[test]
+fn upgraded_cache_keeps_degenerate_boxes() {
+    let cache = TempCache::new("degenerate");
+    write_v1_cache(&cache.0);
+
+    let db = Database::open(&cache.0).unwrap();
+    db.conn()
+        .execute_batch(
+            r#"
+            INSERT INTO bboxes (image_id, label_id, x1, y1, x2, y2, cx, cy, w, h, area)
+            VALUES (1, 1, 10, 10, 10, 20, 10, 15, 0, 10, 0);
+            INSERT INTO masks (bbox_id, height, width, counts, area) VALUES (1, 10, 10, '100', 100);
+            "#,
+        )
+        .unwrap();
+
+    let bboxes: i64 = db
+        .conn()
+        .query_row("SELECT COUNT(*) FROM bboxes", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(bboxes, 2);
+}
+
+#[test]
+fn upgraded_cache_logs_annotation_errors() {
+    let cache = TempCache::new("annotation-errors");
+    write_v1_cache(&cache.0);
+
+    let db = Database::open(&cache.0).unwrap();
+    db.conn()
+        .execute_batch(
+            r#"
+            INSERT INTO scan_errors (path, stage, kind, message, created_at)
+            VALUES ('labels/a.txt', 'annotation', 'invalid', 'line 1: empty line', 'now');
+            "#,
+        )
+        .unwrap();
+}
+
+#[test]
+fn reopening_is_a_no_op() {
+    let cache = TempCache::new("reopen");
+    drop(Database::open(&cache.0).unwrap());
+
+    let mut db = Database::open(&cache.0).unwrap();
+    assert_eq!(db.migrate().unwrap(), SCHEMA_VERSION);
+}
+
+#[test]
+fn refuses_newer_cache() {
+    let cache = TempCache::new("newer");
+    let db = Database::open(&cache.0).unwrap();
+    db.conn()
+        .execute(
+            "INSERT INTO schema_version (version, name, applied_at) VALUES (?, 'future', 'now')",
+            [SCHEMA_VERSION + 1],
+        )
+        .unwrap();
+    drop(db);
+
+    let err = Database::open(&cache.0)
+        .err()
+        .expect("newer cache must be refused");
+    assert!(err.to_string().contains("newer than the supported version"));
+}
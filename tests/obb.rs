@@ -0,0 +1,140 @@
+use datalint_core::db::models::Bbox;
+use datalint_core::formats::yolo::{parse_obb_line, YoloRow};
+use std::f64::consts::{FRAC_PI_2, PI};
+
+const EPSILON: f64 = 1e-9;
+
+/// Box with the given geometry, as read back from the cache. The envelope is
+/// only right for unrotated boxes, which take their corners from it.
+fn rotated(cx: f64, cy: f64, w: f64, h: f64, angle: f64) -> Bbox {
+    let (dx, dy) = (w / 2.0, h / 2.0);
+    let mut bbox = Bbox::new(0, 0, cx - dx, cy - dy, cx + dx, cy + dy);
+    bbox.cx = Some(cx);
+    bbox.cy = Some(cy);
+    bbox.w = Some(w);
+    bbox.h = Some(h);
+    bbox.angle = Some(angle);
+    bbox
+}
+
+/// Whether both quads have the same corners, in any order
+fn same_corners(a: &[(f64, f64); 4], b: &[(f64, f64); 4]) -> bool {
+    a.iter().all(|p| {
+        b.iter()
+            .any(|q| (p.0 - q.0).abs() < EPSILON && (p.1 - q.1).abs() < EPSILON)
+    }) && b.iter().all(|q| {
+        a.iter()
+            .any(|p| (p.0 - q.0).abs() < EPSILON && (p.1 - q.1).abs() < EPSILON)
+    })
+}
+
+#[test]
+fn round_trips_rotated_corners() {
+    let angles = [
+        0.0,
+        1e-12,
+        -1e-12,
+        0.3,
+        FRAC_PI_2 - 1e-12,
+        FRAC_PI_2,
+        FRAC_PI_2 + 1e-12,
+        2.5,
+        PI - 1e-12,
+        PI,
+        PI + 1e-12,
+        -0.7,
+    ];
+    for angle in angles {
+        for (w, h) in [(40.0, 10.0), (10.0, 40.0), (25.0, 25.0)] {
+            let corners = rotated(100.0, 50.0, w, h, angle).obb_corners();
+            let bbox = Bbox::from_obb_corners(0, 0, &corners);
+
+            let stored = bbox.angle.unwrap();
+            assert!((0.0..PI).contains(&stored), "{} -> {}", angle, stored);
+            assert!((bbox.cx.unwrap() - 100.0).abs() < EPSILON);
+            assert!((bbox.cy.unwrap() - 50.0).abs() < EPSILON);
+            assert!((bbox.w.unwrap() - w).abs() < EPSILON);
+            assert!((bbox.h.unwrap() - h).abs() < EPSILON);
+            assert!(
+                same_corners(&bbox.obb_corners(), &corners),
+                "angle {} size {}x{}: {:?} != {:?}",
+                angle,
+                w,
+                h,
+                bbox.obb_corners(),
+                corners
+            );
+        }
+    }
+}
+
+#[test]
+fn stores_the_axis_aligned_envelope() {
+    let corners = rotated(10.0, 20.0, 4.0, 2.0, FRAC_PI_2 / 3.0).obb_corners();
+    let bbox = Bbox::from_obb_corners(0, 0, &corners);
+    let xs = corners.map(|p| p.0);
+    let ys = corners.map(|p| p.1);
+    assert_eq!(bbox.x1, xs.iter().copied().fold(f64::INFINITY, f64::min));
+    assert_eq!(bbox.y1, ys.iter().copied().fold(f64::INFINITY, f64::min));
+    assert_eq!(
+        bbox.x2,
+        xs.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+    );
+    assert_eq!(
+        bbox.y2,
+        ys.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+    );
+
+    // A 30 degree turn widens the 4x2 box to 2*cos + sin by 2*sin + cos
+    let (sin, cos) = (FRAC_PI_2 / 3.0).sin_cos();
+    assert!((bbox.x2 - bbox.x1 - (4.0 * cos + 2.0 * sin)).abs() < EPSILON);
+    assert!((bbox.y2 - bbox.y1 - (4.0 * sin + 2.0 * cos)).abs() < EPSILON);
+}
+
+#[test]
+fn axis_aligned_corners_are_not_rotated() {
+    // Clockwise from top-left, and the same box starting at the opposite corner
+    let clockwise = [(1.0, 2.0), (5.0, 2.0), (5.0, 4.0), (1.0, 4.0)];
+    let opposite = [(5.0, 4.0), (1.0, 4.0), (1.0, 2.0), (5.0, 2.0)];
+    for corners in [clockwise, opposite] {
+        let bbox = Bbox::from_obb_corners(0, 0, &corners);
+        assert!(!bbox.is_rotated(), "{:?}", bbox.angle);
+        assert_eq!((bbox.x1, bbox.y1, bbox.x2, bbox.y2), (1.0, 2.0, 5.0, 4.0));
+        assert_eq!(bbox.obb_corners(), clockwise);
+    }
+}
+
+#[test]
+fn handles_degenerate_quads() {
+    // Zero width: the first edge collapses, the angle comes from the second
+    let line = rotated(10.0, 10.0, 0.0, 6.0, 0.4).obb_corners();
+    let bbox = Bbox::from_obb_corners(0, 0, &line);
+    assert!((bbox.angle.unwrap() - 0.4).abs() < EPSILON);
+    assert_eq!(bbox.w, Some(0.0));
+    assert!(same_corners(&bbox.obb_corners(), &line));
+
+    // Zero height keeps the first edge's direction
+    let line = rotated(10.0, 10.0, 6.0, 0.0, 2.0).obb_corners();
+    let bbox = Bbox::from_obb_corners(0, 0, &line);
+    assert!((bbox.angle.unwrap() - 2.0).abs() < EPSILON);
+    assert!(same_corners(&bbox.obb_corners(), &line));
+
+    // All four corners on one point
+    let point = [(3.0, 4.0); 4];
+    let bbox = Bbox::from_obb_corners(0, 0, &point);
+    assert!(!bbox.is_rotated());
+    assert_eq!((bbox.x1, bbox.y1, bbox.x2, bbox.y2), (3.0, 4.0, 3.0, 4.0));
+    assert_eq!((bbox.w, bbox.h), (Some(0.0), Some(0.0)));
+}
+
+#[test]
+fn denormalizes_yolo_obb_rows() {
+    let row = parse_obb_line("2 0.1 0.2 0.5 0.2 0.5 0.6 0.1 0.6").unwrap();
+    let bbox = row.to_pixels(100.0, 50.0).bbox;
+    assert_eq!(
+        (bbox.x1, bbox.y1, bbox.x2, bbox.y2),
+        (10.0, 10.0, 50.0, 30.0)
+    );
+    assert!(!bbox.is_rotated());
+    assert_eq!((bbox.w, bbox.h), (Some(40.0), Some(20.0)));
+}
@@ -0,0 +1,166 @@
+mod common;
+
+use common::image;
+use datalint_core::db::models::{Bbox, Image, Label};
+use datalint_core::db::queries::LabelQueries;
+use datalint_core::db::Database;
+use datalint_core::lint::overlap::{Duplicate, LabelConflict};
+use datalint_core::lint::Rule;
+
+const IMAGES: i32 = 4;
+
+/// Deterministic xorshift, enough to scatter boxes
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, n: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % n
+    }
+}
+
+/// Random boxes on integer corners spread over several images, with jittered
+/// copies so that high IoU pairs of both kinds exist
+fn random_boxes(seed: u64) -> Vec<Bbox> {
+    let mut random = Random(seed);
+    let mut boxes = Vec::new();
+    while boxes.len() < 400 {
+        let image_id = random.below(IMAGES as u64) as i32 + 1;
+        let label_id = random.below(2) as i32 + 1;
+        let x1 = random.below(90) as f64;
+        let y1 = random.below(90) as f64;
+        let x2 = x1 + 1.0 + random.below(40) as f64;
+        let y2 = y1 + 1.0 + random.below(40) as f64;
+        boxes.push(Bbox::new(image_id, label_id, x1, y1, x2, y2));
+        if random.below(3) == 0 {
+            let label_id = random.below(2) as i32 + 1;
+            let shift = random.below(3) as f64;
+            boxes.push(Bbox::new(
+                image_id,
+                label_id,
+                x1 + shift,
+                y1,
+                x2 + shift,
+                y2 + 1.0,
+            ));
+        }
+    }
+    boxes
+}
+
+fn seeded(boxes: &mut [Bbox]) -> Database {
+    let mut db = Database::new_memory().unwrap();
+    let mut images: Vec<Image> = (1..=IMAGES).map(|i| image(&format!("{}.jpg", i))).collect();
+    db.batch_insert_images(&mut images).unwrap();
+    for name in ["cat", "dog"] {
+        LabelQueries::insert(
+            db.conn(),
+            &Label {
+                id: None,
+                name: name.to_string(),
+                color: None,
+                supercategory: None,
+            },
+        )
+        .unwrap();
+    }
+    db.batch_insert_bboxes(boxes).unwrap();
+    db
+}
+
+fn iou(a: &Bbox, b: &Bbox) -> f64 {
+    let w = a.x2.min(b.x2) - a.x1.max(b.x1);
+    let h = a.y2.min(b.y2) - a.y1.max(b.y1);
+    if w <= 0.0 || h <= 0.0 {
+        return 0.0;
+    }
+    let area = |bbox: &Bbox| (bbox.x2 - bbox.x1) * (bbox.y2 - bbox.y1);
+    let intersection = w * h;
+    intersection / (area(a) + area(b) - intersection)
+}
+
+/// Every pair of every image compared, as (image, box, other box, IoU) in
+/// the order the rules report them
+fn brute_force(
+    boxes: &[Bbox],
+    min_iou: f64,
+    accept: impl Fn(&Bbox, &Bbox) -> bool,
+) -> Vec<(i32, i32, i32, f64)> {
+    let mut pairs = Vec::new();
+    for (i, second) in boxes.iter().enumerate() {
+        for first in &boxes[..i] {
+            if first.image_id != second.image_id || !accept(first, second) {
+                continue;
+            }
+            let iou = iou(first, second);
+            if iou >= min_iou {
+                pairs.push((second.image_id, second.id.unwrap(), first.id.unwrap(), iou));
+            }
+        }
+    }
+    pairs.sort_by_key(|&(image_id, second, first, _)| (image_id, second, first));
+    pairs
+}
+
+fn reported(db: &Database, rule: &dyn Rule) -> Vec<(i32, i32, i32, f64)> {
+    rule.check(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|finding| {
+            let extra = finding.extra.unwrap();
+            (
+                finding.image_id.unwrap(),
+                finding.bbox_id.unwrap(),
+                extra["other_bbox_id"].as_i64().unwrap() as i32,
+                extra["iou"].as_f64().unwrap(),
+            )
+        })
+        .collect()
+}
+
+fn assert_same_pairs(actual: &[(i32, i32, i32, f64)], expected: &[(i32, i32, i32, f64)]) {
+    assert_eq!(actual.len(), expected.len());
+    for (actual, expected) in actual.iter().zip(expected) {
+        assert_eq!(
+            (actual.0, actual.1, actual.2),
+            (expected.0, expected.1, expected.2)
+        );
+        assert!((actual.3 - expected.3).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn sweep_matches_brute_force_on_random_boxes() {
+    for seed in [1, 7, 42] {
+        let mut boxes = random_boxes(seed);
+        let db = seeded(&mut boxes);
+
+        for min_iou in [0.05, 0.5, 0.8] {
+            let duplicates = brute_force(&boxes, min_iou, |a, b| a.label_id == b.label_id);
+            let conflicts = brute_force(&boxes, min_iou, |a, b| a.label_id != b.label_id);
+            // Guard against a seed that tests nothing
+            assert!(!duplicates.is_empty() && !conflicts.is_empty());
+
+            assert_same_pairs(&reported(&db, &Duplicate { min_iou }), &duplicates);
+            assert_same_pairs(&reported(&db, &LabelConflict { min_iou }), &conflicts);
+        }
+    }
+}
+
+#[test]
+fn boxes_of_different_images_never_pair() {
+    let mut boxes = vec![
+        Bbox::new(1, 1, 10.0, 10.0, 50.0, 50.0),
+        Bbox::new(2, 1, 10.0, 10.0, 50.0, 50.0),
+        Bbox::new(2, 2, 10.0, 10.0, 50.0, 50.0),
+    ];
+    let db = seeded(&mut boxes);
+
+    assert!(reported(&db, &Duplicate { min_iou: 0.9 }).is_empty());
+    assert_same_pairs(
+        &reported(&db, &LabelConflict { min_iou: 0.9 }),
+        &[(2, 3, 2, 1.0)],
+    );
+}
@@ -0,0 +1,89 @@
+use datalint_core::phash::{ahash, dhash, hamming, phash, BkTree};
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgb, RgbImage};
+
+/// Deterministic xorshift for hashes with a spread of distances
+struct Random(u64);
+
+impl Random {
+    fn bits(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// A gradient with a bright disc, large enough to survive resizing
+fn scene(disc_x: f64) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(256, 192, |x, y| {
+        let (x, y) = (x as f64, y as f64);
+        let inside = (x - disc_x).powi(2) + (y - 96.0).powi(2) < 50.0f64.powi(2);
+        let base = (x / 256.0 * 160.0) as u8;
+        if inside {
+            Rgb([240, 220, 200])
+        } else {
+            Rgb([base, (y / 192.0 * 120.0) as u8, 60])
+        }
+    }))
+}
+
+#[test]
+fn bk_tree_finds_what_a_linear_scan_finds() {
+    let mut random = Random(0x9e37_79b9_7f4a_7c15);
+    // Near copies of a few bases so that small radii have hits too
+    let bases: Vec<u64> = (0..8).map(|_| random.bits()).collect();
+    let hashes: Vec<u64> = (0..500)
+        .map(|i| {
+            let flips =
+                (0..random.bits() % 12).fold(0u64, |mask, _| mask | (1 << (random.bits() % 64)));
+            bases[i % bases.len()] ^ flips
+        })
+        .collect();
+
+    let mut tree = BkTree::new();
+    for (i, &hash) in hashes.iter().enumerate() {
+        tree.insert(hash, i);
+    }
+    assert_eq!(tree.len(), hashes.len());
+
+    for query in (0..20).map(|i| hashes[i * 7] ^ (random.bits() & random.bits() & random.bits())) {
+        for max_distance in [0, 3, 6, 12, 32, 64] {
+            let mut found = tree.find(query, max_distance);
+            found.sort();
+            let expected: Vec<(usize, u32)> = hashes
+                .iter()
+                .enumerate()
+                .map(|(i, &hash)| (i, hamming(hash, query)))
+                .filter(|&(_, distance)| distance <= max_distance)
+                .collect();
+            assert_eq!(found, expected, "radius {}", max_distance);
+        }
+    }
+}
+
+#[test]
+fn empty_bk_tree_finds_nothing() {
+    let tree = BkTree::new();
+    assert!(tree.is_empty());
+    assert!(tree.find(0, 64).is_empty());
+}
+
+#[test]
+fn identical_and_resized_images_stay_within_distance() {
+    // The default near-duplicate distance
+    let max_distance = 6;
+    let original = scene(90.0);
+    let copy = original.clone();
+    let small = original.resize_exact(128, 96, FilterType::Lanczos3);
+    let large = original.resize_exact(512, 384, FilterType::Triangle);
+    let other = scene(180.0).fliph().flipv();
+
+    assert_eq!(phash(&original), phash(&copy));
+    for resized in [&small, &large] {
+        assert!(hamming(phash(&original), phash(resized)) <= max_distance);
+        assert!(hamming(ahash(&original), ahash(resized)) <= max_distance);
+        assert!(hamming(dhash(&original), dhash(resized)) <= max_distance);
+    }
+    assert!(hamming(phash(&original), phash(&other)) > max_distance);
+}
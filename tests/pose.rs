@@ -0,0 +1,138 @@
+mod common;
+
+use common::{count, TempDataset};
+use datalint_core::cache::create_cache_db;
+use datalint_core::db::queries::ScanErrorQueries;
+use datalint_core::enums::{DatasetTask, DatasetType};
+use datalint_core::formats::yolo::{parse_pose_line, YoloRow};
+use datalint_core::progress::NoProgress;
+use datalint_core::scanner::ScanOptions;
+
+#[test]
+fn parses_keypoints_with_two_dims() {
+    let pose = parse_pose_line("0 0.5 0.5 0.2 0.2 0.1 0.2 0.3 0.4", 2, 2).unwrap();
+    assert_eq!(pose.points, vec![(0.1, 0.2, None), (0.3, 0.4, None)]);
+
+    let keypoints = pose.to_pixels(100.0, 50.0).keypoints.unwrap();
+    assert_eq!(keypoints.point_count, 2);
+    assert!(!keypoints.has_visibility);
+    let points: Vec<(f64, f64)> = keypoints.points.iter().map(|p| (p.x, p.y)).collect();
+    assert_eq!(points, vec![(10.0, 10.0), (30.0, 20.0)]);
+}
+
+#[test]
+fn parses_keypoints_with_visibility() {
+    let pose = parse_pose_line("1 0.5 0.5 0.2 0.2 0.1 0.2 2 0 0 0", 2, 3).unwrap();
+    assert_eq!(pose.bbox.class_id, 1);
+    assert_eq!(
+        pose.points,
+        vec![(0.1, 0.2, Some(2.0)), (0.0, 0.0, Some(0.0))]
+    );
+
+    let keypoints = pose.to_pixels(100.0, 50.0).keypoints.unwrap();
+    assert!(keypoints.has_visibility);
+    assert_eq!(keypoints.points[1].visibility, Some(0.0));
+}
+
+#[test]
+fn rejects_the_wrong_number_of_keypoint_values() {
+    let cases = [
+        // One keypoint short
+        (
+            "0 0.5 0.5 0.2 0.2 0.1 0.2 2",
+            3,
+            "expected 11 values for 2 keypoints with 3 dims, found 8",
+        ),
+        // Visibility given although kpt_shape declares 2 dims
+        (
+            "0 0.5 0.5 0.2 0.2 0.1 0.2 2 0.3 0.4 2",
+            2,
+            "expected 9 values for 2 keypoints with 2 dims, found 11",
+        ),
+        // A plain detection row
+        (
+            "0 0.5 0.5 0.2 0.2",
+            2,
+            "expected 9 values for 2 keypoints with 2 dims, found 5",
+        ),
+    ];
+    for (line, dims, error) in cases {
+        assert_eq!(
+            parse_pose_line(line, 2, dims),
+            Err(error.to_string()),
+            "{}",
+            line
+        );
+    }
+}
+
+#[test]
+fn imports_keypoints_and_logs_bad_rows() {
+    let dataset = TempDataset::new("pose-import");
+    dataset.write("data.yaml", "names: [person]\nkpt_shape: [2, 3]\n");
+    dataset.image("images/train/a.png", 100, 50);
+    dataset.write(
+        "labels/train/a.txt",
+        "0 0.5 0.5 0.2 0.2 0.1 0.2 2 0.3 0.4 1\n0 0.5 0.5 0.2 0.2 0.1 0.2\n",
+    );
+
+    let db = dataset.create(DatasetType::Yolo, DatasetTask::PoseEstimation);
+    assert_eq!(count(&db, "bboxes"), 1);
+    let (point_count, has_visibility): (i32, i32) = db
+        .conn()
+        .query_row(
+            "SELECT point_count, has_visibility FROM keypoints",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!((point_count, has_visibility), (2, 1));
+
+    // Keypoint names default to their index
+    let metadata = db.get_cache_metadata().unwrap().unwrap();
+    assert_eq!(metadata.keypoint_names.as_deref(), Some(r#"["0","1"]"#));
+
+    let errors: Vec<String> = ScanErrorQueries::get_by_stage(db.conn(), "annotation")
+        .unwrap()
+        .into_iter()
+        .map(|error| error.message)
+        .collect();
+    assert_eq!(
+        errors,
+        vec!["line 2: expected 11 values for 2 keypoints with 3 dims, found 7"]
+    );
+}
+
+#[test]
+fn requires_a_valid_kpt_shape() {
+    let cases = [
+        (
+            "names: [person]\n",
+            "Pose datasets require kpt_shape in data.yaml",
+        ),
+        (
+            "names: [person]\nkpt_shape: [17, 4]\n",
+            "Invalid kpt_shape: keypoints must have 2 or 3 dims, found 4",
+        ),
+        (
+            "names: [person]\nkpt_shape: [17]\n",
+            "Invalid kpt_shape: expected [keypoints, dims], found [17]",
+        ),
+    ];
+    for (yaml, error) in cases {
+        let dataset = TempDataset::new("pose-shape");
+        dataset.write("data.yaml", yaml);
+        dataset.image("images/train/a.png", 100, 50);
+
+        let result = create_cache_db(
+            &dataset.cache(),
+            dataset.root(),
+            &DatasetType::Yolo,
+            &DatasetTask::PoseEstimation,
+            &ScanOptions::default(),
+            &NoProgress,
+        );
+        let message = result.err().map(|e| e.to_string()).unwrap_or_default();
+        assert!(message.contains(error), "{:?}: {}", yaml, message);
+    }
+}
@@ -0,0 +1,108 @@
+use datalint_core::probe::{probe, ImageHeader};
+use image::{DynamicImage, ImageFormat};
+use std::io::Cursor;
+
+fn encode(img: &DynamicImage, format: ImageFormat) -> Vec<u8> {
+    let mut data = Vec::new();
+    img.write_to(&mut Cursor::new(&mut data), format).unwrap();
+    data
+}
+
+/// The header must report what a full decode produces
+fn assert_matches_decode(data: &[u8]) {
+    let decoded = image::load_from_memory(data).unwrap();
+    assert_eq!(
+        probe(data),
+        Some(ImageHeader {
+            width: decoded.width(),
+            height: decoded.height(),
+            color: decoded.color(),
+        })
+    );
+}
+
+#[test]
+fn probes_every_encodable_layout() {
+    let layouts = [
+        DynamicImage::new_luma8(37, 21),
+        DynamicImage::new_luma_a8(37, 21),
+        DynamicImage::new_rgb8(37, 21),
+        DynamicImage::new_rgba8(37, 21),
+        DynamicImage::new_luma16(37, 21),
+        DynamicImage::new_rgb16(37, 21),
+        DynamicImage::new_rgba16(37, 21),
+    ];
+    let formats = [
+        ImageFormat::Jpeg,
+        ImageFormat::Png,
+        ImageFormat::WebP,
+        ImageFormat::Bmp,
+        ImageFormat::Tiff,
+    ];
+
+    for img in &layouts {
+        for format in formats {
+            let mut data = Vec::new();
+            // Not every encoder takes every layout
+            if img.write_to(&mut Cursor::new(&mut data), format).is_err() {
+                continue;
+            }
+            assert_matches_decode(&data);
+        }
+    }
+}
+
+#[test]
+fn probes_large_dimensions() {
+    let img = DynamicImage::new_rgb8(4000, 3);
+    for format in [ImageFormat::Jpeg, ImageFormat::Png, ImageFormat::WebP] {
+        assert_matches_decode(&encode(&img, format));
+    }
+}
+
+#[test]
+fn rejects_truncated_and_unknown_headers() {
+    let data = encode(&DynamicImage::new_rgb8(8, 8), ImageFormat::Png);
+    assert_eq!(probe(&data[..20]), None);
+    assert_eq!(probe(b"GIF89a"), None);
+    assert_eq!(probe(&[]), None);
+
+    let data = encode(&DynamicImage::new_rgb8(8, 8), ImageFormat::Jpeg);
+    assert_eq!(probe(&data[..4]), None);
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Insert a chunk right after IHDR
+fn with_png_chunk(png: &[u8], kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut chunk = (payload.len() as u32).to_be_bytes().to_vec();
+    chunk.extend_from_slice(kind);
+    chunk.extend_from_slice(payload);
+    chunk.extend_from_slice(&crc32(&chunk[4..]).to_be_bytes());
+
+    // Signature (8) + IHDR length, type, data (13) and CRC
+    let at = 8 + 4 + 4 + 13 + 4;
+    [&png[..at], &chunk, &png[at..]].concat()
+}
+
+#[test]
+fn png_transparency_chunk_adds_alpha() {
+    let gray = encode(&DynamicImage::new_luma8(5, 4), ImageFormat::Png);
+    let data = with_png_chunk(&gray, b"tRNS", &[0, 0]);
+    assert_matches_decode(&data);
+    assert_eq!(probe(&data).unwrap().color, image::ColorType::La8);
+
+    let rgb = encode(&DynamicImage::new_rgb8(5, 4), ImageFormat::Png);
+    let data = with_png_chunk(&rgb, b"tRNS", &[0, 0, 0, 0, 0, 0]);
+    assert_matches_decode(&data);
+    assert_eq!(probe(&data).unwrap().color, image::ColorType::Rgba8);
+}
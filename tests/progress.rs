@@ -0,0 +1,115 @@
+mod common;
+
+use common::{count, TempDataset};
+use datalint_core::cache::create_cache_db;
+use datalint_core::db::Database;
+use datalint_core::enums::{DatasetTask, DatasetType};
+use datalint_core::errors::{DatalintError, DatalintResult};
+use datalint_core::progress::{NoProgress, ProgressSink, Stage};
+use datalint_core::scanner::{scan_images_into, ScanOptions};
+use std::sync::Mutex;
+
+/// YOLO dataset of `images` labeled images
+fn yolo_dataset(name: &str, images: usize) -> TempDataset {
+    let dataset = TempDataset::new(name);
+    for i in 0..images {
+        dataset.image(&format!("images/train/{}.png", i), 8, 8);
+        dataset.write(&format!("labels/train/{}.txt", i), "0 0.5 0.5 0.2 0.2\n");
+    }
+    dataset
+}
+
+fn create(dataset: &TempDataset, progress: &dyn ProgressSink) -> DatalintResult<usize> {
+    create_cache_db(
+        &dataset.cache(),
+        dataset.root(),
+        &DatasetType::Yolo,
+        &DatasetTask::ObjectDetection,
+        &ScanOptions::default(),
+        progress,
+    )
+    .map(|created| created.images)
+}
+
+/// Records every update and cancels once `cancel_at` is reached
+#[derive(Default)]
+struct Recorder {
+    updates: Mutex<Vec<(Stage, usize, Option<usize>)>>,
+    cancel_at: Option<Stage>,
+}
+
+impl ProgressSink for Recorder {
+    fn update(&self, stage: Stage, done: usize, total: Option<usize>) -> DatalintResult<()> {
+        self.updates.lock().unwrap().push((stage, done, total));
+        if self.cancel_at == Some(stage) {
+            return Err(DatalintError::Generic("cancelled".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn reports_every_stage_to_completion() {
+    let dataset = yolo_dataset("progress-stages", 5);
+    let recorder = Recorder::default();
+    assert_eq!(create(&dataset, &recorder).unwrap(), 5);
+
+    let updates = recorder.updates.into_inner().unwrap();
+    for stage in [Stage::Hash, Stage::Annotations] {
+        let last = updates.iter().rev().find(|(s, _, _)| *s == stage);
+        assert_eq!(last, Some(&(stage, 5, Some(5))), "{:?}", stage);
+    }
+}
+
+#[test]
+fn cancelling_removes_the_partial_cache() {
+    let dataset = yolo_dataset("progress-cancel", 5);
+    let recorder = Recorder {
+        cancel_at: Some(Stage::Annotations),
+        ..Default::default()
+    };
+    let err = create(&dataset, &recorder).unwrap_err();
+    assert_eq!(err.to_string(), "cancelled");
+
+    // The scan had committed its batches, none of which may survive
+    assert!(!dataset.cache().exists());
+    assert_eq!(create(&dataset, &NoProgress).unwrap(), 5);
+    let db = Database::open(&dataset.cache()).unwrap();
+    assert_eq!(count(&db, "images"), 5);
+    assert_eq!(count(&db, "bboxes"), 5);
+}
+
+#[test]
+fn refuses_to_create_over_an_existing_cache() {
+    let dataset = yolo_dataset("progress-existing", 2);
+    assert_eq!(create(&dataset, &NoProgress).unwrap(), 2);
+
+    dataset.image("images/train/extra.png", 8, 8);
+    let err = create(&dataset, &NoProgress).unwrap_err();
+    assert!(err.to_string().contains("Cache already exists"));
+
+    // The existing cache is kept as it was
+    let db = Database::open(&dataset.cache()).unwrap();
+    assert_eq!(count(&db, "images"), 2);
+}
+
+#[test]
+fn cancelling_the_scan_stops_the_walk_after_whole_batches() {
+    // Many more images than the pipeline buffers, so the walk is still
+    // running when the first batch is reported and has to be stopped
+    let dataset = yolo_dataset("progress-pipeline", 40);
+    let db = Database::new_memory().unwrap();
+    let recorder = Recorder {
+        cancel_at: Some(Stage::Hash),
+        ..Default::default()
+    };
+    let err =
+        scan_images_into(&db, dataset.root(), &ScanOptions::default(), 3, &recorder).unwrap_err();
+    assert_eq!(err.to_string(), "cancelled");
+
+    // Only the batch reported before cancelling was written, in full
+    let updates = recorder.updates.into_inner().unwrap();
+    assert_eq!(updates.len(), 1);
+    assert_eq!((updates[0].0, updates[0].1), (Stage::Hash, 3));
+    assert_eq!(count(&db, "images"), 3);
+}
@@ -0,0 +1,235 @@
+use datalint_core::formats::coco::CocoRle;
+use datalint_core::rle::Rle;
+
+/// Column-major mask with the given (x, y) pixels set
+fn mask(height: u32, width: u32, pixels: &[(u32, u32)]) -> Vec<u8> {
+    let mut mask = vec![0u8; (height * width) as usize];
+    for &(x, y) in pixels {
+        mask[(x * height + y) as usize] = 1;
+    }
+    mask
+}
+
+fn rle(height: u32, width: u32, counts: &[u64]) -> Rle {
+    Rle {
+        height,
+        width,
+        counts: counts.to_vec(),
+    }
+}
+
+/// Even-odd test of a pixel center against every polygon
+fn contains(polygons: &[Vec<(f64, f64)>], x: f64, y: f64) -> bool {
+    polygons.iter().any(|polygon| {
+        let mut inside = false;
+        for (i, &(x0, y0)) in polygon.iter().enumerate() {
+            let (x1, y1) = polygon[(i + 1) % polygon.len()];
+            if (y0 <= y) != (y1 <= y) && x < x0 + (y - y0) * (x1 - x0) / (y1 - y0) {
+                inside = !inside;
+            }
+        }
+        inside
+    })
+}
+
+#[test]
+fn compresses_counts_like_pycocotools() {
+    // Strings follow pycocotools' rleToString: 5-bit groups offset by '0',
+    // with counts after the third stored as a delta to two runs back
+    let cases: [(u32, u32, &[u64], &str); 7] = [
+        (3, 3, &[4, 1, 4], "414"),
+        (3, 5, &[1, 2, 3, 4, 5], "12322"),
+        // Negative deltas
+        (3, 5, &[5, 4, 3, 2, 1], "543NN"),
+        (6, 9, &[2, 40, 2, 10], "2X12RO"),
+        // Values needing a continuation group
+        (10, 10, &[100], "T3"),
+        // 16 sets the sign bit of the first group
+        (4, 4, &[16], "`0"),
+        // Fully set mask starts with an empty background run
+        (4, 4, &[0, 16], "0`0"),
+    ];
+    for (height, width, counts, compressed) in cases {
+        let expected = rle(height, width, counts);
+        assert_eq!(expected.to_compressed(), compressed, "{:?}", counts);
+        assert_eq!(
+            Rle::from_compressed(compressed, height, width).unwrap(),
+            expected,
+            "{}",
+            compressed
+        );
+    }
+}
+
+#[test]
+fn rejects_malformed_compressed_counts() {
+    // Runs must cover the whole mask
+    assert!(Rle::from_compressed("414", 3, 4).is_err());
+    // Characters outside the 64-symbol alphabet
+    assert!(Rle::from_compressed("4 4", 3, 3).is_err());
+    // Continuation bit on the last character
+    assert!(Rle::from_compressed("4T", 3, 3).is_err());
+}
+
+#[test]
+fn reads_uncompressed_and_compressed_coco_objects() {
+    let uncompressed: CocoRle =
+        serde_json::from_str(r#"{"counts": [2, 40, 2, 10], "size": [6, 9]}"#).unwrap();
+    let compressed: CocoRle =
+        serde_json::from_str(r#"{"counts": "2X12RO", "size": [6, 9]}"#).unwrap();
+    assert_eq!(uncompressed.to_rle().unwrap(), rle(6, 9, &[2, 40, 2, 10]));
+    assert_eq!(compressed.to_rle().unwrap(), uncompressed.to_rle().unwrap());
+
+    let short: CocoRle = serde_json::from_str(r#"{"counts": [2, 40], "size": [6, 9]}"#).unwrap();
+    assert!(short.to_rle().is_err());
+}
+
+#[test]
+fn encodes_and_decodes_column_major_masks() {
+    // Column 0 holds rows 1-2, column 1 holds row 0
+    let pixels = mask(3, 2, &[(0, 1), (0, 2), (1, 0)]);
+    let encoded = Rle::encode(&pixels, 3, 2);
+    assert_eq!(encoded, rle(3, 2, &[1, 3, 2]));
+    assert_eq!(encoded.decode(), pixels);
+    assert_eq!(encoded.area(), 3);
+
+    let empty = Rle::encode(&mask(3, 2, &[]), 3, 2);
+    assert_eq!(empty, rle(3, 2, &[6]));
+    assert_eq!(empty.bbox(), None);
+}
+
+#[test]
+fn computes_the_foreground_box() {
+    let encoded = Rle::encode(&mask(5, 6, &[(1, 2), (3, 1), (3, 3)]), 5, 6);
+    assert_eq!(encoded.bbox(), Some((1.0, 1.0, 3.0, 3.0)));
+
+    // A run wrapping into the next column spans the full height
+    assert_eq!(rle(3, 2, &[1, 3, 2]).bbox(), Some((0.0, 0.0, 2.0, 3.0)));
+}
+
+#[test]
+fn rasterizes_pixel_centers_inside_polygons() {
+    let square = vec![vec![(1.0, 1.0), (4.0, 1.0), (4.0, 3.0), (1.0, 3.0)]];
+    let rasterized = Rle::from_polygons(&square, 5, 6);
+    let pixels = [(1, 1), (1, 2), (2, 1), (2, 2), (3, 1), (3, 2)];
+    assert_eq!(rasterized, Rle::encode(&mask(5, 6, &pixels), 5, 6));
+    assert_eq!(rasterized.bbox(), Some((1.0, 1.0, 3.0, 2.0)));
+
+    // Parts outside the image are clipped
+    let overhang = vec![vec![(-2.0, -2.0), (3.0, -2.0), (3.0, 2.0), (-2.0, 2.0)]];
+    assert_eq!(Rle::from_polygons(&overhang, 4, 4).area(), 6);
+
+    // Fewer than three points enclose nothing
+    let line = vec![vec![(0.0, 0.0), (4.0, 4.0)]];
+    assert_eq!(Rle::from_polygons(&line, 4, 4), rle(4, 4, &[16]));
+}
+
+#[test]
+fn rasterizes_like_a_per_pixel_test() {
+    // Deterministic pseudo-random polygons, including concave and overlapping ones
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    let mut next = move |scale: f64| {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        (seed >> 11) as f64 / (1u64 << 53) as f64 * scale
+    };
+
+    let (height, width) = (23u32, 31u32);
+    for _ in 0..50 {
+        let polygons: Vec<Vec<(f64, f64)>> = (0..2)
+            .map(|_| {
+                (0..3 + (next(4.0) as usize))
+                    .map(|_| (next(36.0) - 2.0, next(28.0) - 2.0))
+                    .collect()
+            })
+            .collect();
+
+        let mut expected = vec![0u8; (height * width) as usize];
+        for x in 0..width {
+            for y in 0..height {
+                if contains(&polygons, x as f64 + 0.5, y as f64 + 0.5) {
+                    expected[(x * height + y) as usize] = 1;
+                }
+            }
+        }
+        let rasterized = Rle::from_polygons(&polygons, height, width);
+        assert_eq!(rasterized.decode(), expected, "{:?}", polygons);
+        assert_eq!(rasterized, Rle::encode(&expected, height, width));
+    }
+}
+
+#[test]
+fn traces_region_outlines() {
+    let square = Rle::encode(
+        &mask(5, 6, &[(1, 1), (1, 2), (2, 1), (2, 2), (3, 1), (3, 2)]),
+        5,
+        6,
+    );
+    assert_eq!(
+        square.to_polygons(),
+        vec![vec![(1.0, 1.0), (4.0, 1.0), (4.0, 3.0), (1.0, 3.0)]]
+    );
+
+    // Diagonal neighbours are separate regions
+    let diagonal = Rle::encode(&mask(2, 2, &[(0, 0), (1, 1)]), 2, 2);
+    assert_eq!(diagonal.to_polygons().len(), 2);
+    assert_eq!(Rle::from_polygons(&diagonal.to_polygons(), 2, 2), diagonal);
+}
+
+#[test]
+fn traced_outlines_rasterize_back_to_the_mask() {
+    // An L shape and a separate blob touching the image border
+    let pixels = [
+        (0, 0),
+        (0, 1),
+        (0, 2),
+        (1, 2),
+        (2, 2),
+        (5, 3),
+        (5, 4),
+        (6, 3),
+        (6, 4),
+        (6, 2),
+    ];
+    let original = Rle::encode(&mask(5, 7, &pixels), 5, 7);
+    let polygons = original.to_polygons();
+    assert_eq!(polygons.len(), 2);
+    assert_eq!(Rle::from_polygons(&polygons, 5, 7), original);
+}
+
+#[test]
+fn drops_holes_when_tracing() {
+    let ring: Vec<(u32, u32)> = (0..3)
+        .flat_map(|x| (0..3).map(move |y| (x, y)))
+        .filter(|&p| p != (1, 1))
+        .collect();
+    let polygons = Rle::encode(&mask(3, 3, &ring), 3, 3).to_polygons();
+    assert_eq!(
+        polygons,
+        vec![vec![(0.0, 0.0), (3.0, 0.0), (3.0, 3.0), (0.0, 3.0)]]
+    );
+    assert_eq!(Rle::from_polygons(&polygons, 3, 3).area(), 9);
+}
+
+#[test]
+fn runs_longer_than_u32_are_kept() {
+    // 80000 x 80000 pixels is more than u32::MAX
+    let side = 80_000u32;
+    let pixels = side as u64 * side as u64;
+    let square = vec![vec![(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)]];
+    let rle = Rle::from_polygons(&square, side, side);
+    assert_eq!(
+        rle.counts,
+        vec![0, 2, side as u64 - 2, 2, pixels - side as u64 - 2]
+    );
+    assert_eq!(rle.area(), 4);
+    rle.validate().unwrap();
+    assert_eq!(
+        Rle::from_compressed(&rle.to_compressed(), side, side).unwrap(),
+        rle
+    );
+
+    let empty = Rle::from_polygons(&[], side, side);
+    assert_eq!(empty.counts, vec![pixels]);
+}
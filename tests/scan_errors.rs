@@ -0,0 +1,99 @@
+mod common;
+
+use common::{image, TempDataset};
+use datalint_core::db::models::ScanError;
+use datalint_core::db::queries::ScanErrorQueries;
+use datalint_core::db::Database;
+use datalint_core::enums::{DatasetTask, DatasetType};
+use datalint_core::progress::NoProgress;
+use datalint_core::scanner::{process_images, scan_images_into, ScanOptions};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+/// (path, stage, kind) of each error
+fn summary(errors: &[ScanError]) -> Vec<(String, String, String)> {
+    errors
+        .iter()
+        .map(|e| (e.path.clone(), e.stage.clone(), e.kind.clone()))
+        .collect()
+}
+
+fn entry(path: &str, stage: &str, kind: &str) -> (String, String, String) {
+    (path.to_string(), stage.to_string(), kind.to_string())
+}
+
+#[test]
+fn unreadable_files_are_read_errors() {
+    let dataset = TempDataset::new("scan-errors-read");
+    dataset.write("data.yaml", "names: [cat]\n");
+    dataset.image("images/train/a.png", 10, 10);
+    let locked = dataset.image("images/train/locked.png", 10, 10);
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+    // Permissions do not stop a privileged user, who reads the file anyway
+    let readable = fs::read(&locked).is_ok();
+
+    let db = dataset.create(DatasetType::Yolo, DatasetTask::ObjectDetection);
+    let read_errors = ScanErrorQueries::get_by_stage(db.conn(), "read").unwrap();
+    if readable {
+        assert!(read_errors.is_empty());
+    } else {
+        assert_eq!(
+            summary(&read_errors),
+            vec![entry(
+                "images/train/locked.png",
+                "read",
+                "permission_denied"
+            )]
+        );
+        assert!(read_errors[0].message.contains("Permission denied"));
+    }
+
+    // A file gone between the walk and the read fails the same way
+    let missing = dataset.root().join("images/train/missing.png");
+    let (images, errors) = process_images(
+        &[dataset.root().join("images/train/a.png"), missing],
+        dataset.root(),
+        &ScanOptions::default(),
+    );
+    assert_eq!(images.len(), 1);
+    assert_eq!(
+        summary(&errors),
+        vec![entry("images/train/missing.png", "read", "not_found")]
+    );
+}
+
+#[test]
+fn rejected_rows_are_insert_errors() {
+    let dataset = TempDataset::new("scan-errors-insert");
+    for name in ["a", "b", "c"] {
+        dataset.image(&format!("images/train/{}.png", name), 10, 10);
+    }
+    // A row already stored under the same path makes its batch fail
+    let mut db = Database::new_memory().unwrap();
+    db.batch_insert_images(&mut [image("b.png")]).unwrap();
+
+    let (inserted, mut errors) =
+        scan_images_into(&db, dataset.root(), &ScanOptions::default(), 2, &NoProgress).unwrap();
+    // Only the conflicting image is lost from the failed batch
+    assert_eq!(inserted, 2);
+    assert_eq!(
+        summary(&errors),
+        vec![entry("images/train/b.png", "insert", "database")]
+    );
+
+    // The stage filter keeps the errors of one stage only
+    let mut read_error = errors[0].clone();
+    read_error.path = "images/train/d.png".to_string();
+    read_error.stage = "read".to_string();
+    errors.push(read_error);
+    ScanErrorQueries::append(db.conn(), &mut errors).unwrap();
+    assert_eq!(ScanErrorQueries::get_all(db.conn()).unwrap().len(), 2);
+    for stage in ["insert", "read"] {
+        let stored = ScanErrorQueries::get_by_stage(db.conn(), stage).unwrap();
+        assert_eq!(stored.len(), 1, "{}", stage);
+        assert_eq!(stored[0].stage, stage);
+    }
+    assert!(ScanErrorQueries::get_by_stage(db.conn(), "walk")
+        .unwrap()
+        .is_empty());
+}
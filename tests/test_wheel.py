@@ -4,7 +4,13 @@ Smoke test for datalint-core wheel installation.
 Used in CI to verify that built wheels work correctly.
 """
 
+import asyncio
+import os
+import struct
 import sys
+import tempfile
+import threading
+import zlib
 
 
 def test_import():
@@ -25,12 +31,251 @@ def test_import():
         return False
 
 
+def test_lint_config_is_checked():
+    """LintConfig values go through the range checks and stay fixed."""
+    import datalint_core
+
+    config = datalint_core.LintConfig(duplicate_iou=0.5)
+    assert config.duplicate_iou == 0.5
+    invalid = (
+        {"duplicate_iou": 0.0},
+        {"max_aspect_ratio": 0.5},
+        {"min_box_area": -1.0},
+    )
+    for kwargs in invalid:
+        try:
+            datalint_core.LintConfig(**kwargs)
+        except ValueError:
+            pass
+        else:
+            raise AssertionError(f"LintConfig accepted {kwargs}")
+    try:
+        config.duplicate_iou = 5.0
+    except AttributeError:
+        pass
+    else:
+        raise AssertionError("LintConfig attributes can be set")
+    assert config.duplicate_iou == 0.5
+
+
+def write_png(path, width, height):
+    """Write a black RGB png without any imaging library."""
+
+    def chunk(kind, data):
+        crc = zlib.crc32(kind + data) & 0xFFFFFFFF
+        return struct.pack(">I", len(data)) + kind + data + struct.pack(">I", crc)
+
+    rows = b"".join(b"\x00" + b"\x00\x00\x00" * width for _ in range(height))
+    header = struct.pack(">IIBBBBB", width, height, 8, 2, 0, 0, 0)
+    os.makedirs(os.path.dirname(path), exist_ok=True)
+    with open(path, "wb") as f:
+        f.write(b"\x89PNG\r\n\x1a\n")
+        f.write(chunk(b"IHDR", header))
+        f.write(chunk(b"IDAT", zlib.compress(rows)))
+        f.write(chunk(b"IEND", b""))
+
+
+def write_text(path, content):
+    os.makedirs(os.path.dirname(path), exist_ok=True)
+    with open(path, "w") as f:
+        f.write(content)
+
+
+def yolo_dataset(root):
+    """Three 100x100 images, two in train and one in val, with four boxes."""
+    dataset = os.path.join(root, "data")
+    write_text(os.path.join(dataset, "data.yaml"), "names: [cat, dog]\n")
+    labels = {
+        "train/a": "0 0.5 0.5 0.2 0.2\n1 0.25 0.25 0.1 0.1\n",
+        "train/b": "0 0.5 0.5 0.4 0.4\n",
+        "val/c": "1 0.5 0.5 0.2 0.2\n",
+    }
+    for name, content in labels.items():
+        write_png(os.path.join(dataset, "images", name + ".png"), 100, 100)
+        write_text(os.path.join(dataset, "labels", name + ".txt"), content)
+    return dataset, os.path.join(root, "cache.db")
+
+
+def create(dataset, cache_path):
+    import datalint_core
+
+    return datalint_core.Cache.create(
+        cache_path,
+        dataset,
+        datalint_core.DatasetType.YOLO,
+        datalint_core.DatasetTask.OBJECT_DETECTION,
+    )
+
+
+def test_cache_queries():
+    """A created cache can be opened again and queried."""
+    import datalint_core
+
+    with tempfile.TemporaryDirectory() as root:
+        dataset, cache_path = yolo_dataset(root)
+        create(dataset, cache_path)
+        cache = datalint_core.Cache.open(cache_path)
+
+        assert cache.metadata.dataset_type == "yolo"
+        assert len(cache.images()) == 3
+        train = cache.images(split="train")
+        assert sorted(image.filename for image in train) == ["a.png", "b.png"]
+        assert [image.filename for image in cache.images(split="val")] == ["c.png"]
+
+        a = next(image for image in train if image.filename == "a.png")
+        assert (a.width, a.height) == (100, 100)
+        boxes = sorted((box.x1, box.y1, box.x2, box.y2) for box in cache.bboxes(a.id))
+        assert boxes == [(20.0, 20.0, 30.0, 30.0), (40.0, 40.0, 60.0, 60.0)]
+
+        assert cache.count_by_split() == {"train": 2, "val": 1}
+        assert cache.count_by_label() == {"cat": 2, "dog": 2}
+
+
+def test_arrow_export():
+    """Exported tables go through the Arrow PyCapsule interface."""
+    with tempfile.TemporaryDirectory() as root:
+        cache = create(*yolo_dataset(root))
+        table = cache.to_arrow("bbox_details")
+        assert table.num_rows == len(table) == 4
+        assert "label" in table.column_names
+
+        try:
+            import pyarrow
+        except ImportError:
+            print("pyarrow not installed, skipping the round trip")
+            return
+        converted = pyarrow.table(table)
+        assert converted.num_rows == 4
+        assert converted.column_names == table.column_names
+        labels = sorted(converted.column("label").to_pylist())
+        assert labels == ["cat", "cat", "dog", "dog"]
+
+        reader = pyarrow.RecordBatchReader.from_stream(cache.to_arrow("images"))
+        assert reader.read_all().num_rows == 3
+
+
+def test_cache_task():
+    """Background builds resolve through result() and await, and cancel."""
+    import datalint_core
+
+    with tempfile.TemporaryDirectory() as root:
+        dataset, cache_path = yolo_dataset(root)
+        args = (
+            dataset,
+            datalint_core.DatasetType.YOLO,
+            datalint_core.DatasetTask.OBJECT_DETECTION,
+        )
+
+        task = datalint_core.create_cache_async(cache_path, *args)
+        assert isinstance(task.result(timeout=60), str)
+        assert task.done()
+        assert task.progress is not None
+        # Too late to stop it
+        assert not task.cancel()
+
+        async def build(path):
+            return await datalint_core.Cache.create_async(path, *args)
+
+        cache = asyncio.run(build(os.path.join(root, "awaited.db")))
+        assert len(cache.images()) == 3
+
+        # Hold the task in its first progress update until it is cancelled
+        cancelled = threading.Event()
+
+        def progress(stage, done, total):
+            cancelled.wait(timeout=60)
+
+        cancelled_path = os.path.join(root, "cancelled.db")
+        task = datalint_core.create_cache_async(
+            cancelled_path, *args, progress=progress
+        )
+        assert task.cancel()
+        cancelled.set()
+        try:
+            task.result(timeout=60)
+        except asyncio.CancelledError:
+            pass
+        else:
+            raise AssertionError("cancelled task completed")
+        assert not os.path.exists(cancelled_path)
+
+
+def test_lint_async_holds_queries():
+    """Queries on a cache wait for a background lint to finish."""
+    with tempfile.TemporaryDirectory() as root:
+        cache = create(*yolo_dataset(root))
+        release = threading.Event()
+
+        def progress(stage, done, total):
+            release.wait(timeout=60)
+
+        task = cache.lint_async(progress=progress)
+        counts = []
+        query = threading.Thread(target=lambda: counts.append(cache.count_by_rule()))
+        query.start()
+        query.join(timeout=0.5)
+        assert query.is_alive(), "query ran while the lint held the cache"
+
+        release.set()
+        summary = task.result(timeout=60)
+        query.join(timeout=60)
+        assert counts == [{rule: count for rule, count in summary.items() if count}]
+
+
+def test_scan_errors_by_stage():
+    """Scan errors are recorded with their stage and filtered on it."""
+    import datalint_core
+
+    with tempfile.TemporaryDirectory() as root:
+        dataset, cache_path = yolo_dataset(root)
+        write_text(os.path.join(dataset, "labels", "train", "b.txt"), "0 0.5\n")
+        locked = os.path.join(dataset, "images", "train", "locked.png")
+        write_png(locked, 10, 10)
+        os.chmod(locked, 0)
+        # Permissions do not stop a privileged user
+        readable = os.access(locked, os.R_OK)
+        cache = create(dataset, cache_path)
+
+        annotation = cache.scan_errors(stage="annotation")
+        assert [(e.path, e.kind) for e in annotation] == [
+            ("labels/train/b.txt", "invalid")
+        ]
+        read = cache.scan_errors(stage="read")
+        if readable:
+            assert read == []
+        else:
+            assert [(e.path, e.kind) for e in read] == [
+                ("images/train/locked.png", "permission_denied")
+            ]
+        assert cache.scan_errors(stage="insert") == []
+        assert len(cache.scan_errors()) == len(annotation) + len(read)
+        os.chmod(locked, 0o644)
+
+
+TESTS = [
+    test_import,
+    test_lint_config_is_checked,
+    test_cache_queries,
+    test_arrow_export,
+    test_cache_task,
+    test_lint_async_holds_queries,
+    test_scan_errors_by_stage,
+]
+
+
 def main():
     """Run smoke tests."""
     print("Running datalint-core smoke tests...")
     print("-" * 40)
 
-    success = test_import()
+    success = True
+    for test in TESTS:
+        try:
+            passed = test() is not False
+        except Exception as e:
+            print(f"{test.__name__} failed: {e!r}", file=sys.stderr)
+            passed = False
+        success = success and passed
 
     print("-" * 40)
     if success:
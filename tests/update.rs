@@ -0,0 +1,372 @@
+mod common;
+
+use common::{count, TempDataset};
+use datalint_core::cache::{update_cache_db, CacheUpdate};
+use datalint_core::db::queries::{
+    BboxQueries, ImageQueries, LabelQueries, MetadataQueries, ScanErrorQueries,
+};
+use datalint_core::db::Database;
+use datalint_core::enums::{DatasetTask, DatasetType};
+use datalint_core::progress::NoProgress;
+use datalint_core::scanner::ScanOptions;
+use std::fs;
+use std::path::Path;
+
+fn update(
+    dataset: &TempDataset,
+    dataset_type: DatasetType,
+    dataset_task: DatasetTask,
+) -> CacheUpdate {
+    update_cache_db(
+        &dataset.cache(),
+        dataset.root(),
+        &dataset_type,
+        &dataset_task,
+        &ScanOptions::default(),
+        &NoProgress,
+    )
+    .unwrap()
+}
+
+fn counts(update: &CacheUpdate) -> (usize, usize, usize, usize) {
+    (
+        update.added,
+        update.changed,
+        update.removed,
+        update.unchanged,
+    )
+}
+
+/// Box ids and left edges of an image, in id order
+fn boxes(db: &Database, filename: &str) -> Vec<(Option<i32>, f64)> {
+    let images = ImageQueries::get_all(db.conn()).unwrap();
+    let image = images
+        .iter()
+        .find(|image| image.filename == filename)
+        .unwrap();
+    let mut boxes: Vec<(Option<i32>, f64)> =
+        BboxQueries::get_by_image(db.conn(), image.id.unwrap())
+            .unwrap()
+            .into_iter()
+            .map(|bbox| (bbox.id, bbox.x1))
+            .collect();
+    boxes.sort_by_key(|(id, _)| *id);
+    boxes
+}
+
+fn yolo_dataset(name: &str) -> TempDataset {
+    let dataset = TempDataset::new(name);
+    dataset.write("data.yaml", "names: [cat, dog]\n");
+    for name in ["a", "b", "c"] {
+        dataset.image(&format!("images/train/{}.png", name), 100, 100);
+        dataset.write(&format!("labels/train/{}.txt", name), "0 0.5 0.5 0.2 0.2\n");
+    }
+    dataset
+}
+
+#[test]
+fn counts_added_changed_removed_and_unchanged_images() {
+    let dataset = yolo_dataset("update-counts");
+    dataset.create(DatasetType::Yolo, DatasetTask::ObjectDetection);
+
+    dataset.image("images/train/b.png", 120, 80);
+    fs::remove_file(dataset.root().join("images/train/c.png")).unwrap();
+    dataset.image("images/train/d.png", 100, 100);
+    dataset.write("labels/train/d.txt", "1 0.5 0.5 0.4 0.4\n");
+
+    let result = update(&dataset, DatasetType::Yolo, DatasetTask::ObjectDetection);
+    assert_eq!(counts(&result), (1, 1, 1, 1));
+
+    let db = Database::open(&dataset.cache()).unwrap();
+    let mut filenames: Vec<String> = ImageQueries::get_all(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|image| image.filename)
+        .collect();
+    filenames.sort();
+    assert_eq!(filenames, vec!["a.png", "b.png", "d.png"]);
+    // Labels of the changed image follow its new size
+    assert_eq!(
+        boxes(&db, "b.png").iter().map(|b| b.1).collect::<Vec<_>>(),
+        vec![48.0]
+    );
+    assert_eq!(
+        boxes(&db, "d.png").iter().map(|b| b.1).collect::<Vec<_>>(),
+        vec![30.0]
+    );
+    assert_eq!(count(&db, "bboxes"), 3);
+
+    // The removed image's label file is now an orphan
+    let errors: Vec<(String, String)> = ScanErrorQueries::get_by_stage(db.conn(), "annotation")
+        .unwrap()
+        .into_iter()
+        .map(|error| (error.path, error.kind))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![(
+            "labels/train/c.txt".to_string(),
+            "missing_image".to_string()
+        )]
+    );
+    assert_eq!(result.annotation_errors, 1);
+    drop(db);
+
+    let result = update(&dataset, DatasetType::Yolo, DatasetTask::ObjectDetection);
+    assert_eq!(counts(&result), (0, 0, 0, 3));
+    assert_eq!(result.annotation_errors, 1);
+}
+
+#[test]
+fn reimports_only_the_label_files_that_changed() {
+    let dataset = yolo_dataset("update-labels");
+    let db = dataset.create(DatasetType::Yolo, DatasetTask::ObjectDetection);
+    let untouched = boxes(&db, "a.png");
+    drop(db);
+
+    // Edited, removed and new label files of unchanged images
+    dataset.write(
+        "labels/train/b.txt",
+        "1 0.3 0.3 0.2 0.2\n1 0.7 0.7 0.2 0.2\n",
+    );
+    fs::remove_file(dataset.root().join("labels/train/c.txt")).unwrap();
+    dataset.image("images/train/e.png", 100, 100);
+    let result = update(&dataset, DatasetType::Yolo, DatasetTask::ObjectDetection);
+    assert_eq!(counts(&result), (1, 0, 0, 3));
+
+    let db = Database::open(&dataset.cache()).unwrap();
+    // Rows of images whose labels did not change are kept as they were
+    assert_eq!(boxes(&db, "a.png"), untouched);
+    assert_eq!(
+        boxes(&db, "b.png").iter().map(|b| b.1).collect::<Vec<_>>(),
+        vec![20.0, 60.0]
+    );
+    assert!(boxes(&db, "c.png").is_empty());
+    assert_eq!(count(&db, "label_files"), 2);
+    drop(db);
+
+    dataset.write("labels/train/e.txt", "0 0.5 0.5 0.2 0.2\n");
+    update(&dataset, DatasetType::Yolo, DatasetTask::ObjectDetection);
+    let db = Database::open(&dataset.cache()).unwrap();
+    assert_eq!(boxes(&db, "a.png"), untouched);
+    assert_eq!(boxes(&db, "e.png").len(), 1);
+    assert_eq!(count(&db, "bboxes"), 4);
+}
+
+#[test]
+fn reimports_everything_when_data_yaml_changes() {
+    let dataset = yolo_dataset("update-config");
+    dataset.create(DatasetType::Yolo, DatasetTask::ObjectDetection);
+
+    dataset.write("data.yaml", "names: [kitten, puppy, bird]\n");
+    let result = update(&dataset, DatasetType::Yolo, DatasetTask::ObjectDetection);
+    assert_eq!(counts(&result), (0, 0, 0, 3));
+
+    let db = Database::open(&dataset.cache()).unwrap();
+    let labels: Vec<String> = LabelQueries::get_all(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|label| label.name)
+        .collect();
+    assert_eq!(labels, vec!["kitten", "puppy", "bird"]);
+    let label_id: i32 = db
+        .conn()
+        .query_row("SELECT DISTINCT label_id FROM bboxes", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(
+        LabelQueries::find_by_name(db.conn(), "kitten")
+            .unwrap()
+            .unwrap()
+            .id,
+        Some(label_id)
+    );
+}
+
+#[test]
+fn replaces_objects_of_changed_voc_files() {
+    let xml = |name: &str, xmax: u32| {
+        format!(
+            "<annotation><filename>{}.jpg</filename><object><name>{}</name>\
+             <bndbox><xmin>1</xmin><ymin>1</ymin><xmax>{}</xmax><ymax>9</ymax></bndbox>\
+             </object></annotation>",
+            name,
+            if xmax > 9 { "dog" } else { "cat" },
+            xmax
+        )
+    };
+    let dataset = TempDataset::new("update-voc");
+    for name in ["a", "b"] {
+        dataset.image(&format!("VOC2012/JPEGImages/{}.jpg", name), 20, 20);
+        dataset.write(&format!("VOC2012/Annotations/{}.xml", name), &xml(name, 5));
+    }
+    let db = dataset.create(DatasetType::Voc, DatasetTask::ObjectDetection);
+    let untouched = boxes(&db, "a.jpg");
+    drop(db);
+
+    dataset.write("VOC2012/Annotations/b.xml", &xml("b", 15));
+    let result = update(&dataset, DatasetType::Voc, DatasetTask::ObjectDetection);
+    assert_eq!(counts(&result), (0, 0, 0, 2));
+
+    let db = Database::open(&dataset.cache()).unwrap();
+    assert_eq!(boxes(&db, "a.jpg"), untouched);
+    assert_eq!(count(&db, "bboxes"), 2);
+    let labels: Vec<String> = LabelQueries::get_all(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|label| label.name)
+        .collect();
+    assert_eq!(labels, vec!["cat", "dog"]);
+    drop(db);
+
+    // Labels no object uses any more go with their last object
+    dataset.write("VOC2012/Annotations/b.xml", &xml("b", 7));
+    update(&dataset, DatasetType::Voc, DatasetTask::ObjectDetection);
+    let db = Database::open(&dataset.cache()).unwrap();
+    let labels: Vec<String> = LabelQueries::get_all(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|label| label.name)
+        .collect();
+    assert_eq!(labels, vec!["cat"]);
+}
+
+#[test]
+fn keeps_images_of_directories_the_walk_could_not_list() {
+    let dataset = yolo_dataset("update-unreadable");
+    dataset.image("images/val/v.png", 100, 100);
+    dataset.create(DatasetType::Yolo, DatasetTask::ObjectDetection);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let locked = dataset.root().join("images/val");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        // Permissions do not apply to root, nothing to test then
+        let readable = fs::read_dir(&locked).is_ok();
+        let result = update(&dataset, DatasetType::Yolo, DatasetTask::ObjectDetection);
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        if readable {
+            return;
+        }
+
+        assert_eq!(counts(&result), (0, 0, 0, 3));
+        assert_eq!(result.scan_errors, 1);
+        let db = Database::open(&dataset.cache()).unwrap();
+        assert_eq!(count(&db, "images"), 4);
+    }
+}
+
+#[test]
+fn reimports_everything_when_a_split_list_changes() {
+    let dataset = yolo_dataset("update-split-list");
+    dataset.write("data.yaml", "train: train.txt\nnames: [cat, dog]\n");
+    dataset.write("train.txt", "images/train/a.png\nimages/train/b.png\n");
+    let db = dataset.create(DatasetType::Yolo, DatasetTask::ObjectDetection);
+    let before = MetadataQueries::annotation_sources(db.conn()).unwrap();
+    drop(db);
+
+    dataset.write("train.txt", "images/train/a.png\n");
+    update(&dataset, DatasetType::Yolo, DatasetTask::ObjectDetection);
+
+    let db = Database::open(&dataset.cache()).unwrap();
+    assert_ne!(
+        MetadataQueries::annotation_sources(db.conn()).unwrap(),
+        before
+    );
+    let mut splits: Vec<(String, Option<String>)> = ImageQueries::get_all(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|image| (image.filename, image.split))
+        .collect();
+    splits.sort();
+    assert_eq!(
+        splits,
+        vec![
+            ("a.png".to_string(), Some("train".to_string())),
+            ("b.png".to_string(), Some("unknown".to_string())),
+            ("c.png".to_string(), Some("unknown".to_string())),
+        ]
+    );
+}
+
+#[test]
+fn accepts_other_spellings_of_the_dataset_path() {
+    let dataset = yolo_dataset("update-spelling");
+    dataset.create(DatasetType::Yolo, DatasetTask::ObjectDetection);
+
+    let dotted = dataset.root().join(".");
+    let trailing = format!("{}/", dataset.root().display());
+    for path in [dotted.as_path(), Path::new(&trailing)] {
+        let result = update_cache_db(
+            &dataset.cache(),
+            path,
+            &DatasetType::Yolo,
+            &DatasetTask::ObjectDetection,
+            &ScanOptions::default(),
+            &NoProgress,
+        )
+        .unwrap();
+        assert_eq!(counts(&result), (0, 0, 0, 3), "{}", path.display());
+    }
+
+    let other = TempDataset::new("update-spelling-other");
+    let err = update_cache_db(
+        &dataset.cache(),
+        other.root(),
+        &DatasetType::Yolo,
+        &DatasetTask::ObjectDetection,
+        &ScanOptions::default(),
+        &NoProgress,
+    )
+    .err()
+    .unwrap();
+    assert!(err.to_string().contains("Cache was built for"));
+}
+
+#[test]
+fn failed_full_decodes_are_not_marked_decoded() {
+    let dataset = yolo_dataset("update-full-decode");
+    // A header that probes fine in front of pixel data that is cut off
+    let broken = dataset.image("images/train/broken.png", 100, 100);
+    let data = fs::read(&broken).unwrap();
+    fs::write(&broken, &data[..data.len() / 2]).unwrap();
+    dataset.create(DatasetType::Yolo, DatasetTask::ObjectDetection);
+
+    let full_decode = ScanOptions {
+        full_decode: true,
+        ..ScanOptions::default()
+    };
+    let refresh = || {
+        update_cache_db(
+            &dataset.cache(),
+            dataset.root(),
+            &DatasetType::Yolo,
+            &DatasetTask::ObjectDetection,
+            &full_decode,
+            &NoProgress,
+        )
+        .unwrap()
+    };
+    // Images whose header only was read are decoded once, a failed decode
+    // is not tried again
+    assert_eq!(counts(&refresh()), (0, 4, 0, 0));
+    assert_eq!(counts(&refresh()), (0, 0, 0, 4));
+
+    let db = Database::open(&dataset.cache()).unwrap();
+    let mut decoded: Vec<(String, bool, bool)> = ImageQueries::get_all(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|image| (image.filename, image.is_decoded, image.is_corrupted))
+        .collect();
+    decoded.sort();
+    assert_eq!(
+        decoded,
+        vec![
+            ("a.png".to_string(), true, false),
+            ("b.png".to_string(), true, false),
+            ("broken.png".to_string(), false, true),
+            ("c.png".to_string(), true, false),
+        ]
+    );
+}
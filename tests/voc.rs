@@ -0,0 +1,210 @@
+mod common;
+
+use common::{count, TempDataset};
+use datalint_core::cache::update_cache_db;
+use datalint_core::db::queries::{BboxQueries, ImageQueries, LabelFileQueries, ScanErrorQueries};
+use datalint_core::db::Database;
+use datalint_core::enums::{DatasetTask, DatasetType};
+use datalint_core::progress::NoProgress;
+use datalint_core::scanner::ScanOptions;
+use serde_json::json;
+use std::fs;
+
+const DOG: &str = r#"<annotation>
+    <filename>2007_000001.jpg</filename>
+    <size><width>50</width><height>40</height><depth>3</depth></size>
+    <object>
+        <name>dog</name>
+        <pose>Left</pose>
+        <truncated>1</truncated>
+        <occluded>0</occluded>
+        <difficult>1</difficult>
+        <bndbox><xmin>5</xmin><ymin>6</ymin><xmax>25</xmax><ymax>30</ymax></bndbox>
+    </object>
+    <object>
+        <name>person</name>
+        <bndbox><xmin>1</xmin><ymin>2</ymin><xmax>9</xmax><ymax>12</ymax></bndbox>
+    </object>
+    <object>
+        <name>cat</name>
+        <bndbox><xmin>1</xmin><ymin>2</ymin><xmax>oops</xmax><ymax>12</ymax></bndbox>
+    </object>
+</annotation>"#;
+
+// No <filename>: the image is found through the xml basename
+const EMPTY: &str = r#"<annotation>
+    <size><width>50</width><height>40</height></size>
+</annotation>"#;
+
+#[test]
+fn imports_objects_splits_and_label_files() {
+    let dataset = TempDataset::new("voc-import");
+    let base = "VOCdevkit/VOC2007";
+    for name in ["2007_000001", "2007_000002", "2007_000003"] {
+        dataset.image(&format!("{}/JPEGImages/{}.jpg", base, name), 50, 40);
+    }
+    dataset.write(&format!("{}/Annotations/2007_000001.xml", base), DOG);
+    dataset.write(&format!("{}/Annotations/2007_000002.xml", base), EMPTY);
+    dataset.write(
+        &format!("{}/ImageSets/Main/train.txt", base),
+        "2007_000001\n",
+    );
+    dataset.write(
+        &format!("{}/ImageSets/Main/val.txt", base),
+        "2007_000002\n2007_999999\n",
+    );
+    // The union of train and val is ignored
+    dataset.write(
+        &format!("{}/ImageSets/Main/trainval.txt", base),
+        "2007_000001\n2007_000002\n2007_000003\n",
+    );
+
+    let db = dataset.create(DatasetType::Voc, DatasetTask::ObjectDetection);
+    let images = ImageQueries::get_all(db.conn()).unwrap();
+    let image = |name: &str| images.iter().find(|image| image.name == name).unwrap();
+
+    // Images missing from every list fall back to unknown
+    let mut splits: Vec<(&str, Option<&str>)> = images
+        .iter()
+        .map(|image| (image.name.as_str(), image.split.as_deref()))
+        .collect();
+    splits.sort();
+    assert_eq!(
+        splits,
+        vec![
+            ("2007_000001", Some("train")),
+            ("2007_000002", Some("val")),
+            ("2007_000003", Some("unknown")),
+        ]
+    );
+
+    let bboxes = BboxQueries::get_by_image(db.conn(), image("2007_000001").id.unwrap()).unwrap();
+    let mut objects: Vec<([f64; 4], Option<serde_json::Value>)> = bboxes
+        .into_iter()
+        .map(|bbox| ([bbox.x1, bbox.y1, bbox.x2, bbox.y2], bbox.attributes))
+        .collect();
+    objects.sort_by(|a, b| b.0[0].total_cmp(&a.0[0]));
+    assert_eq!(
+        objects,
+        vec![
+            (
+                [5.0, 6.0, 25.0, 30.0],
+                Some(json!({
+                    "pose": "Left",
+                    "truncated": true,
+                    "occluded": false,
+                    "difficult": true
+                }))
+            ),
+            ([1.0, 2.0, 9.0, 12.0], None),
+        ]
+    );
+    assert_eq!(count(&db, "bboxes"), 2);
+
+    // Invalid objects still count towards their file
+    let mut files: Vec<(String, Option<i32>, i32)> = LabelFileQueries::get_all(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|file| (file.path, file.image_id, file.annotation_count))
+        .collect();
+    files.sort();
+    assert_eq!(
+        files,
+        vec![
+            (
+                format!("{}/Annotations/2007_000001.xml", base),
+                image("2007_000001").id,
+                3
+            ),
+            (
+                format!("{}/Annotations/2007_000002.xml", base),
+                image("2007_000002").id,
+                0
+            ),
+        ]
+    );
+
+    let errors: Vec<(String, String)> = ScanErrorQueries::get_by_stage(db.conn(), "annotation")
+        .unwrap()
+        .into_iter()
+        .map(|error| (error.kind, error.message))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![(
+            "invalid".to_string(),
+            "object 3: invalid <xmax> value 'oops'".to_string()
+        )]
+    );
+}
+
+#[test]
+fn logs_unreadable_split_lists_and_size_mismatches() {
+    let dataset = TempDataset::new("voc-lists");
+    let base = "VOCdevkit/VOC2007";
+    dataset.image(&format!("{}/JPEGImages/2007_000001.jpg", base), 50, 40);
+    dataset.image(&format!("{}/JPEGImages/2007_000002.jpg", base), 60, 40);
+    dataset.write(&format!("{}/Annotations/2007_000001.xml", base), DOG);
+    dataset.write(&format!("{}/Annotations/2007_000002.xml", base), EMPTY);
+    dataset.write(
+        &format!("{}/ImageSets/Main/train.txt", base),
+        "2007_000001\n",
+    );
+    let val = dataset.write(&format!("{}/ImageSets/Main/val.txt", base), "");
+    fs::write(&val, [0xff, 0xfe, b'\n']).unwrap();
+
+    let errors = |db: &Database| -> Vec<(String, String)> {
+        ScanErrorQueries::get_by_stage(db.conn(), "annotation")
+            .unwrap()
+            .into_iter()
+            .filter(|error| error.kind != "invalid")
+            .map(|error| (error.path, error.kind))
+            .collect()
+    };
+    let expected = vec![
+        (
+            format!("{}/Annotations/2007_000002.xml", base),
+            "size_mismatch".to_string(),
+        ),
+        (
+            format!("{}/ImageSets/Main/val.txt", base),
+            "unreadable".to_string(),
+        ),
+    ];
+
+    // The other list is still applied
+    let db = dataset.create(DatasetType::Voc, DatasetTask::ObjectDetection);
+    assert_eq!(errors(&db), expected);
+    let mut splits: Vec<Option<String>> = ImageQueries::get_all(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|image| image.split)
+        .collect();
+    splits.sort();
+    assert_eq!(
+        splits,
+        vec![Some("train".to_string()), Some("unknown".to_string())]
+    );
+    let message = &ScanErrorQueries::get_by_stage(db.conn(), "annotation")
+        .unwrap()
+        .into_iter()
+        .find(|error| error.kind == "size_mismatch")
+        .unwrap()
+        .message;
+    assert_eq!(message, "<size> 50x40 does not match the image, 60x40");
+    drop(db);
+
+    // A refresh reading the lists again logs them once
+    dataset.image(&format!("{}/JPEGImages/2007_000001.jpg", base), 50, 40);
+    update_cache_db(
+        &dataset.cache(),
+        dataset.root(),
+        &DatasetType::Voc,
+        &DatasetTask::ObjectDetection,
+        &ScanOptions::default(),
+        &NoProgress,
+    )
+    .unwrap();
+    let db = Database::open(&dataset.cache()).unwrap();
+    assert_eq!(errors(&db), expected);
+}
@@ -0,0 +1,201 @@
+mod common;
+
+use common::{count, TempDataset};
+use datalint_core::db::models::Image;
+use datalint_core::db::queries::{BboxQueries, ImageQueries, LabelFileQueries, LabelQueries};
+use datalint_core::enums::{DatasetTask, DatasetType};
+use datalint_core::formats::yolo::{label_path_for, parse_label_file, parse_line, YoloBox};
+use std::path::Path;
+
+fn image(relative_path: &str, filename: &str) -> Image {
+    let (name, extension) = filename.rsplit_once('.').unwrap();
+    Image {
+        id: None,
+        name: name.to_string(),
+        filename: filename.to_string(),
+        extension: Some(extension.to_string()),
+        relative_path: relative_path.to_string(),
+        split: None,
+        width: None,
+        height: None,
+        channels: None,
+        file_size: None,
+        file_mtime: None,
+        file_hash: String::new(),
+        is_corrupted: false,
+        ahash: None,
+        dhash: None,
+        phash: None,
+        is_decoded: false,
+    }
+}
+
+#[test]
+fn parses_detection_lines() {
+    assert_eq!(
+        parse_line("3 0.5 0.25 0.1 0.2"),
+        Ok(YoloBox {
+            class_id: 3,
+            cx: 0.5,
+            cy: 0.25,
+            w: 0.1,
+            h: 0.2,
+        })
+    );
+    // Surrounding and repeated whitespace is ignored
+    assert!(parse_line("  0\t0.5  0.5 0.1 0.1 ").is_ok());
+}
+
+#[test]
+fn rejects_malformed_lines() {
+    let cases = [
+        ("0 0.5 0.5 0.1", "expected 5 values, found 4"),
+        ("0 0.5 0.5 0.1 0.1 0.3", "expected 5 values, found 6"),
+        ("person 0.5 0.5 0.1 0.1", "invalid class id 'person'"),
+        ("1.5 0.5 0.5 0.1 0.1", "invalid class id '1.5'"),
+        ("-1 0.5 0.5 0.1 0.1", "invalid class id '-1'"),
+        ("0 0.5 x 0.1 0.1", "invalid coordinate 'x'"),
+        ("0 nan 0.5 0.1 0.1", "invalid coordinate 'nan'"),
+        ("0 0.5 0.5 inf 0.1", "invalid coordinate 'inf'"),
+        ("", "empty line"),
+    ];
+    for (line, error) in cases {
+        assert_eq!(parse_line(line), Err(error.to_string()), "{:?}", line);
+    }
+}
+
+#[test]
+fn denormalizes_to_pixel_corners() {
+    let row = parse_line("0 0.5 0.5 0.2 0.4").unwrap();
+    assert_eq!(row.to_corners(100.0, 50.0), (40.0, 15.0, 60.0, 35.0));
+}
+
+#[test]
+fn swaps_the_last_images_directory_for_labels() {
+    let root = Path::new("/data");
+    let cases = [
+        ("images/train", "a.jpg", "/data/labels/train/a.txt"),
+        (
+            "images/set/images/val",
+            "b.png",
+            "/data/images/set/labels/val/b.txt",
+        ),
+        ("raw/images", "c.jpeg", "/data/raw/labels/c.txt"),
+        // `images` only counts as a whole directory name
+        ("my_images/train", "d.jpg", "/data/my_images/train/d.txt"),
+        // Outside an images directory the label sits next to the image
+        ("train", "e.jpg", "/data/train/e.txt"),
+        ("", "f.jpg", "/data/f.txt"),
+        ("images", "g.v2.jpg", "/data/labels/g.v2.txt"),
+    ];
+    for (relative_path, filename, expected) in cases {
+        assert_eq!(
+            label_path_for(root, &image(relative_path, filename)),
+            Path::new(expected),
+            "{}/{}",
+            relative_path,
+            filename
+        );
+    }
+}
+
+#[test]
+fn reports_bad_lines_with_their_number() {
+    let dataset = TempDataset::new("yolo-lines");
+    let path = dataset.write(
+        "labels/a.txt",
+        "0 0.5 0.5 0.1 0.1\n\n0 0.5\n   \n2 0.1 0.1 0.1 0.1\n",
+    );
+    let (rows, errors) = parse_label_file(&path, parse_line).unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(errors, vec!["line 3: expected 5 values, found 2"]);
+
+    let empty = dataset.write("labels/empty.txt", "");
+    let (rows, errors) = parse_label_file(&empty, parse_line).unwrap();
+    assert!(rows.is_empty() && errors.is_empty());
+}
+
+#[test]
+fn imports_boxes_and_skips_invalid_rows() {
+    let dataset = TempDataset::new("yolo-import");
+    dataset.write("data.yaml", "names: [person]\n");
+    dataset.image("images/train/a.png", 100, 50);
+    dataset.image("images/train/b.png", 100, 50);
+    dataset.image("images/train/c.png", 100, 50);
+    dataset.write(
+        "labels/train/a.txt",
+        "0 0.5 0.5 0.2 0.4\n7 0.5 0.5 0.1 0.1\n0 0.5\n-1 0.5 0.5 0.1 0.1\n",
+    );
+    dataset.write("labels/train/b.txt", "");
+
+    let db = dataset.create(DatasetType::Yolo, DatasetTask::ObjectDetection);
+    let images = ImageQueries::get_all(db.conn()).unwrap();
+    let id_of = |filename: &str| {
+        images
+            .iter()
+            .find(|image| image.filename == filename)
+            .and_then(|image| image.id)
+            .unwrap()
+    };
+
+    // Class ids beyond data.yaml names get a label named after the id
+    let labels: Vec<String> = LabelQueries::get_all(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|label| label.name)
+        .collect();
+    assert_eq!(labels, vec!["person", "7"]);
+
+    let bboxes = BboxQueries::get_by_image(db.conn(), id_of("a.png")).unwrap();
+    assert_eq!(bboxes.len(), 2);
+    let person = &bboxes[0];
+    assert_eq!(
+        (person.x1, person.y1, person.x2, person.y2),
+        (40.0, 15.0, 60.0, 35.0)
+    );
+    assert_eq!(person.area, Some(400.0));
+    assert_eq!(count(&db, "bboxes"), 2);
+
+    // The empty file is recorded, the image without one is a background
+    let mut files: Vec<(String, Option<i32>, i32)> = LabelFileQueries::get_all(db.conn())
+        .unwrap()
+        .into_iter()
+        .map(|file| (file.path, file.image_id, file.annotation_count))
+        .collect();
+    files.sort();
+    assert_eq!(
+        files,
+        vec![
+            ("labels/train/a.txt".to_string(), Some(id_of("a.png")), 4),
+            ("labels/train/b.txt".to_string(), Some(id_of("b.png")), 0),
+        ]
+    );
+}
+
+#[test]
+fn stores_segmentation_polygons_without_masks() {
+    let dataset = TempDataset::new("yolo-segment");
+    dataset.write("data.yaml", "names: [person]\n");
+    dataset.image("images/train/a.png", 10, 20);
+    dataset.write(
+        "labels/train/a.txt",
+        "0 0.1 0.05 0.5 0.05 0.5 0.25 0.1 0.25\n",
+    );
+
+    let db = dataset.create(DatasetType::Yolo, DatasetTask::InstanceSegmentation);
+    let image_id = ImageQueries::get_all(db.conn()).unwrap()[0].id.unwrap();
+    let bboxes = BboxQueries::get_by_image(db.conn(), image_id).unwrap();
+    assert_eq!(bboxes.len(), 1);
+    assert_eq!(count(&db, "masks"), 0);
+
+    // The outline is rasterized on demand
+    let segmentations = BboxQueries::get_segmentations(db.conn(), bboxes[0].id.unwrap()).unwrap();
+    assert_eq!(segmentations.len(), 1);
+    assert_eq!(
+        segmentations[0].vertices,
+        vec![(1.0, 1.0), (5.0, 1.0), (5.0, 5.0), (1.0, 5.0)]
+    );
+    let rle = segmentations[0].to_rle(20, 10);
+    assert_eq!(rle.area(), 16);
+    assert_eq!(rle.bbox(), Some((1.0, 1.0, 4.0, 4.0)));
+}